@@ -0,0 +1,208 @@
+use crate::export::TemplateError;
+use std::fmt::{Display, Formatter};
+
+/// Errors returned by the crate
+#[derive(Debug)]
+pub enum Error {
+    /// Misuse of a [crate::export::Template]
+    Template(TemplateError),
+
+    /// Failure to read or write a file
+    Io(std::io::Error),
+
+    /// Invalid toml document
+    TomlParse(toml_edit::TomlError),
+
+    /// Invalid toml in a template, see [crate::export::Template::read_as_toml]
+    TemplateParse {
+        /// Path given in [crate::export::Template::new]
+        path: std::path::PathBuf,
+
+        /// Line and column of the syntax error, from 1
+        position: Option<(usize, usize)>,
+
+        /// The error of [toml_edit]
+        error: Box<toml_edit::TomlError>,
+    },
+
+    /// Generated code that isn't a valid [proc_macro2::TokenStream]
+    TokenStreamParse(proc_macro2::LexError),
+
+    /// Invalid pattern given to [crate::import::DiscoveryOptions]
+    Regex(regex::Error),
+
+    /// Failure of bindgen to generate the bindings, see [crate::import::discover_dir]
+    Bindgen(bindgen::BindgenError),
+
+    /// Failure to format the generated text
+    Fmt(std::fmt::Error),
+
+    /// Anonymous type without a name in the rename rules, holds its Rust name
+    /// see [crate::import::UnnamedTypePolicy::Error]
+    UnnamedType(String),
+
+    /// Two types have the same key in the rename rules (same Rust name, or same name after
+    /// [crate::import::MappingsCodegen::strip_rust_prefix]) or in the inverse map
+    /// (see [crate::import::MappingsCodegen::bidirectional])
+    RenameKeyCollision {
+        /// The shared key
+        key: String,
+
+        /// The Rust names of the colliding types
+        rust_names: [String; 2],
+    },
+
+    /// An alias was found for two different types, holds the alias
+    /// see [crate::import::AliasConflictPolicy::Error]
+    AliasConflict(String),
+
+    /// The generated region can't be placed in the file, see [crate::output::replace_region]
+    Region(String),
+
+    /// Name that isn't a valid C identifier, see [crate::import::CName::new]
+    InvalidIdentifier(String),
+
+    /// Name of the generated variable that isn't a Rust identifier or is a keyword,
+    /// see [crate::import::MappingsCodegen::variable_name]
+    InvalidVariableName(String),
+
+    /// Condition of the generated `#[cfg(..)]` attributes that isn't a cfg predicate,
+    /// see [crate::import::MappingsCodegen::cfg]
+    InvalidCfg(String),
+
+    /// Key of the template that must hold a table but holds something else, e.g.: `export = "oops"`,
+    /// see [crate::export::Template::generate_toml]
+    NotATable {
+        /// Dotted path of the key, e.g.: `export.rename`
+        path: String,
+
+        /// Type of the value found instead, e.g.: `string`
+        found: String,
+    },
+
+    /// Line of a JSON Lines file that isn't a discovered item,
+    /// see [crate::import::NameMappings::from_jsonl]
+    Jsonl {
+        /// Number of the line, from 1
+        line: usize,
+
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Template(error) => write!(f, "{}", error),
+            Error::Io(error) => write!(f, "IO error: {}", error),
+            Error::TomlParse(error) => write!(f, "Invalid toml: {}", error),
+            Error::TemplateParse {
+                path,
+                position,
+                error,
+            } => {
+                write!(f, "Invalid toml in {}", path.display())?;
+                if let Some((line, column)) = position {
+                    write!(f, " at line {}, column {}", line, column)?;
+                }
+                write!(f, ": {}", error.message().trim_end())
+            }
+            Error::TokenStreamParse(error) => write!(f, "Invalid generated code: {}", error),
+            Error::Regex(error) => write!(f, "Invalid pattern: {}", error),
+            Error::Bindgen(error) => write!(f, "bindgen error: {}", error),
+            Error::Fmt(error) => write!(f, "Formatting error: {}", error),
+            Error::UnnamedType(rust_name) => write!(f, "Type {} has no C name", rust_name),
+            Error::RenameKeyCollision { key, rust_names } => write!(
+                f,
+                "Types {} and {} have the same rename key {}",
+                rust_names[0], rust_names[1], key
+            ),
+            Error::AliasConflict(alias) => {
+                write!(f, "Alias {} was found for two different types", alias)
+            }
+            Error::Region(reason) => write!(f, "Invalid generated region: {}", reason),
+            Error::InvalidIdentifier(name) => write!(f, "Invalid C identifier: {:?}", name),
+            Error::Jsonl { line, reason } => {
+                write!(f, "Invalid JSON Lines at line {}: {}", line, reason)
+            }
+            Error::NotATable { path, found } => write!(
+                f,
+                "Expected a table at {} in the template, found {}",
+                path, found
+            ),
+            Error::InvalidCfg(predicate) => write!(f, "Invalid cfg predicate: {:?}", predicate),
+            Error::InvalidVariableName(name) => write!(
+                f,
+                "Invalid variable name {:?}: not a Rust identifier, or a keyword",
+                name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Template(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::TomlParse(error) => Some(error),
+            Error::TemplateParse { error, .. } => Some(error.as_ref()),
+            Error::TokenStreamParse(error) => Some(error),
+            Error::Regex(error) => Some(error),
+            Error::Bindgen(error) => Some(error),
+            Error::Fmt(error) => Some(error),
+            Error::UnnamedType(_)
+            | Error::RenameKeyCollision { .. }
+            | Error::AliasConflict(_)
+            | Error::Region(_)
+            | Error::InvalidIdentifier(_)
+            | Error::InvalidVariableName(_)
+            | Error::InvalidCfg(_)
+            | Error::NotATable { .. }
+            | Error::Jsonl { .. } => None,
+        }
+    }
+}
+
+impl From<TemplateError> for Error {
+    fn from(value: TemplateError) -> Self {
+        Self::Template(value)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<toml_edit::TomlError> for Error {
+    fn from(value: toml_edit::TomlError) -> Self {
+        Self::TomlParse(value)
+    }
+}
+
+impl From<proc_macro2::LexError> for Error {
+    fn from(value: proc_macro2::LexError) -> Self {
+        Self::TokenStreamParse(value)
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(value: regex::Error) -> Self {
+        Self::Regex(value)
+    }
+}
+
+impl From<bindgen::BindgenError> for Error {
+    fn from(value: bindgen::BindgenError) -> Self {
+        Self::Bindgen(value)
+    }
+}
+
+impl From<std::fmt::Error> for Error {
+    fn from(value: std::fmt::Error) -> Self {
+        Self::Fmt(value)
+    }
+}
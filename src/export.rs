@@ -1,11 +1,14 @@
+use crate::import::{split_tag, unprefixed_c_name, NameMappings, RenameOptions, TAG_KEYWORDS};
+use crate::output::OutputFormat;
 use crate::Result;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use toml_edit::{table, Document, Formatted, Item, Table, Value};
+use toml_edit::{Array, Document, Formatted, Item, Key, Table, Value};
 
 /// Alias of the bindings as a [phf_codegen::Map]
 pub type BindingsMap = phf::Map<&'static str, &'static str>;
@@ -33,11 +36,122 @@ impl Display for TemplateError {
 
 impl Error for TemplateError {}
 
+/// A rename rule of the generated config that cbindgen would reject or misapply, see [Template::validate]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The config couldn't be generated, holds the error
+    Generation(String),
+
+    /// The rule of the Rust name renames to something else than a string
+    NotAString(String),
+
+    /// The rule of the Rust name renames to an empty string
+    EmptyValue(String),
+
+    /// The rule renames a type to its own bare name, which looks like a struct, union or enum
+    /// missing its tag keyword, see [Template::untagged_renames]
+    MissingTag {
+        /// The Rust name of the rule
+        rust_name: String,
+
+        /// The C name the rule renames to
+        c_name: String,
+    },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::Generation(error) => write!(f, "Config generation failed: {}", error),
+            ValidationIssue::NotAString(rust_name) => {
+                write!(f, "Rename rule {:?} is not a string", rust_name)
+            }
+            ValidationIssue::EmptyValue(rust_name) => {
+                write!(f, "Rename rule {:?} is empty", rust_name)
+            }
+            ValidationIssue::MissingTag { rust_name, c_name } => write!(
+                f,
+                "Rename rule {:?} = {:?} may be missing its tag keyword (struct, union or enum)",
+                rust_name, c_name
+            ),
+        }
+    }
+}
+
+/// What [Template::generate_toml_with_report] did to the rename table
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Rules added to the rename table
+    pub inserted: usize,
+
+    /// Rules replacing an entry of the template with the same Rust name
+    pub overwritten: usize,
+
+    /// Rules of a [BindingsMap] left out because a map added after it renames the same Rust name,
+    /// see [Template::add_bindings]
+    pub skipped: usize,
+
+    /// The rules that may be missing their tag keyword, only checked with [Template::check_tags],
+    /// see [Template::untagged_renames]
+    pub untagged_renames: Vec<(String, String)>,
+}
+
+/// Order of the rules in the rename table, see [Template::rename_order]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenameOrder {
+    /// Sorted by Rust name, for reviewability
+    Alphabetical,
+
+    /// In the order the types were declared, see [Template::declaration_order]
+    Declaration,
+
+    /// In the order of the [BindingsMap]s
+    #[default]
+    AsProvided,
+}
+
 /// A `cbindgen.toml` template
 pub struct Template<'bindings> {
     path: PathBuf,
     doc: Option<Document>,
-    bindings: Option<&'bindings BindingsMap>,
+    /// The maps providing the rename rules, in the order they were added, see [Template::add_bindings]
+    bindings: Vec<&'bindings BindingsMap>,
+
+    /// Whether [Template::doc] was provided with [Template::use_document] instead of read from [Template::path]
+    in_memory: bool,
+
+    /// Whether the [Template] started from an empty [Document], see [Template::empty]
+    from_scratch: bool,
+
+    /// Path of the table receiving the rename rules, see [Template::rename_table_path]
+    rename_table_path: Vec<String>,
+
+    /// see [Template::check_tags]
+    check_tags: bool,
+
+    /// Values written at their key path by [Template::generate_toml], see [Template::set]
+    values: Vec<(Vec<String>, Value)>,
+
+    /// see [Template::with_parse_includes]
+    parse_includes: Vec<String>,
+
+    /// `(subtable, rust_name, value)` entries written next to the rename table, see [Template::set_per_type]
+    per_type: Vec<(String, String, Value)>,
+
+    /// see [Template::prefix_awareness]
+    prefix_awareness: Option<String>,
+
+    /// see [Template::rename_order]
+    rename_order: RenameOrder,
+
+    /// see [Template::declaration_order]
+    declaration_order: Vec<String>,
+
+    /// see [Template::summary_comment]
+    summary_comment: bool,
+
+    /// see [Template::output_format]
+    output_format: OutputFormat,
 }
 
 impl<'template> Template<'template> {
@@ -46,21 +160,63 @@ impl<'template> Template<'template> {
         Self {
             path: path.into(),
             doc: None,
-            bindings: None,
+            bindings: Vec::new(),
+            in_memory: false,
+            from_scratch: false,
+            rename_table_path: vec!["export".to_string(), "rename".to_string()],
+            check_tags: false,
+            values: Vec::new(),
+            parse_includes: Vec::new(),
+            per_type: Vec::new(),
+            prefix_awareness: None,
+            rename_order: RenameOrder::AsProvided,
+            declaration_order: Vec::new(),
+            summary_comment: false,
+            output_format: OutputFormat::default(),
         }
     }
 
+    /// Make a template without source, starting from an empty [Document], to build the whole config programmatically
+    /// e.g.: with the bindings and [Template::set] for the other options
+    ///
+    /// The `name` (e.g.: the crate generating the config) is shown in the [Template::config_header],
+    /// which says there is no template to modify
+    pub fn empty(name: &str) -> Self {
+        let mut template = Self::new(name);
+        template.doc = Some(Document::new());
+        template.in_memory = true;
+        template.from_scratch = true;
+
+        template
+    }
+
     /// Reads the path given in [Template::new] as a toml file with [toml_edit]
     pub fn read_as_toml(&mut self) -> Result<&mut Self> {
-        let mut file = File::open(&self.path)?;
-        let mut content = if let Ok(metadata) = file.metadata() {
-            String::with_capacity(metadata.len() as usize)
-        } else {
-            String::new()
-        };
+        let file = File::open(&self.path)?;
+        self.read_from(file)?;
+        self.in_memory = false;
 
-        file.read_to_string(&mut content)?;
-        self.doc = Some(Document::from_str(&content)?);
+        Ok(self)
+    }
+
+    /// Reads the toml [Document] from any [Read] source (archive, embedded resource, pipe...)
+    ///
+    /// The path given in [Template::new] is kept as the name shown in the [Template::config_header]
+    /// and in the [crate::Error::TemplateParse] errors
+    pub fn read_from<R: Read>(&mut self, mut reader: R) -> Result<&mut Self> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        let document =
+            Document::from_str(&content).map_err(|error| crate::Error::TemplateParse {
+                path: self.path.clone(),
+                position: error
+                    .span()
+                    .map(|span| line_and_column(&content, span.start)),
+                error: Box::new(error),
+            })?;
+        self.doc = Some(document);
+        self.in_memory = true;
+        self.from_scratch = false;
 
         Ok(self)
     }
@@ -70,76 +226,554 @@ impl<'template> Template<'template> {
     /// Just provide a file name instead of a path in [Template::new]
     pub fn use_document(&mut self, document: Document) -> Result<&mut Self> {
         self.doc = Some(document);
+        self.in_memory = true;
+        self.from_scratch = false;
         Ok(self)
     }
 
+    /// Tell cargo to re-run the build script when the template file changes
+    ///
+    /// Prints `cargo:rerun-if-changed=<path>`, so this must be called from a build script (`build.rs`).
+    /// Does nothing if the template uses an in-memory [Document] (see [Template::use_document])
+    /// or if its path is not an existing file (e.g. a synthetic name)
+    pub fn emit_rerun_if_changed(&self) {
+        if self.in_memory || !self.path.is_file() {
+            return;
+        }
+
+        println!("cargo:rerun-if-changed={}", self.path.display());
+    }
+
     /// Provide the [BindingsMap] to use for the config file generation
+    /// Replaces the maps provided before, see [Template::add_bindings] to combine several maps
     pub fn with_bindings<'bindings: 'template>(
         &mut self,
         map: &'bindings BindingsMap,
     ) -> &mut Self {
-        self.bindings = Some(map);
+        self.bindings = vec![map];
+        self
+    }
+
+    /// Add a [BindingsMap] to the ones used for the config file generation
+    /// e.g.: to gather the rename rules generated by the dependencies of a crate in a single config
+    ///
+    /// If several maps rename the same Rust name, the last map added wins
+    pub fn add_bindings<'bindings: 'template>(&mut self, map: &'bindings BindingsMap) -> &mut Self {
+        self.bindings.push(map);
+        self
+    }
+
+    /// The rename rules of all the [BindingsMap]s, the last map added winning on duplicate keys
+    fn merged_bindings(&self) -> Result<HashMap<&'template str, &'template str>> {
+        if self.bindings.is_empty() {
+            return Err(TemplateError::MissingBindings.into());
+        }
+
+        Ok(self
+            .bindings
+            .iter()
+            .flat_map(|map| map.entries())
+            .map(|(rust_name, c_name)| (*rust_name, *c_name))
+            .collect())
+    }
+
+    /// Set the path of the table in which [Template::generate_toml] writes the rename rules
+    /// e.g.: `&["profiles", "a", "export", "rename"]` for `[profiles.a.export.rename]`
+    ///
+    /// default: `&["export", "rename"]`
+    pub fn rename_table_path(&mut self, path: &[&str]) -> &mut Self {
+        self.rename_table_path = path.iter().map(|key| key.to_string()).collect();
+        self
+    }
+
+    /// Write a scalar or an array at the given key path of the generated toml, creating the missing tables
+    /// e.g.: `&["export", "prefix"]` and `"my_".into()` for `prefix = "my_"` in the `[export]` table
+    ///
+    /// The value replaces an existing one but keeps its comments and formatting.
+    /// Applied by [Template::generate_toml] after the rename rules, in the order they were set.
+    /// An empty path is ignored
+    pub fn set(&mut self, path: &[&str], value: Value) -> &mut Self {
+        if !path.is_empty() {
+            self.values
+                .push((path.iter().map(|key| key.to_string()).collect(), value));
+        }
+        self
+    }
+
+    /// Write the per-type config `value` of the type `rust_name` in the `subtable` of the `[export]` table
+    /// e.g.: `"body"`, `"bmp_peer"` and `"uint8_t padding[3];".into()` for `bmp_peer = "uint8_t padding[3];"`
+    /// in the `[export.body]` table, or `"pre_body"`, `"mangle"`, ...
+    ///
+    /// The `[export]` table is the parent of the [Template::rename_table_path], so the entries follow the rename
+    /// rules into a profile. The value replaces an existing one but keeps its comments and formatting.
+    /// Applied by [Template::generate_toml] after the rename rules, before the values of [Template::set]
+    pub fn set_per_type(&mut self, subtable: &str, rust_name: &str, value: Value) -> &mut Self {
+        self.per_type
+            .push((subtable.to_string(), rust_name.to_string(), value));
+        self
+    }
+
+    /// Crates that cbindgen should parse along with the crate, e.g.: the dependencies the types were imported from
+    /// Written to the `include` array of the `[parse]` table by [Template::generate_toml]
+    ///
+    /// The crates already in the array are kept with their formatting, the missing ones are appended.
+    /// Replaces the crates given before. Applied before the values of [Template::set]
+    pub fn with_parse_includes(&mut self, crates: &[&str]) -> &mut Self {
+        self.parse_includes = crates.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// The `prefix` configured in cbindgen's `[export]` table, removed from the C names of the [BindingsMap]
+    /// so that cbindgen doesn't apply it twice, see [crate::import::MappingsCodegen::prefix_awareness]
+    ///
+    /// default: None, the C names are kept as-is
+    pub fn prefix_awareness(&mut self, prefix: Option<String>) -> &mut Self {
+        self.prefix_awareness = prefix;
+        self
+    }
+
+    /// Should the rename table be sorted by Rust name, for reviewability
+    /// Same as [Template::rename_order] with [RenameOrder::Alphabetical] or [RenameOrder::AsProvided]
+    ///
+    /// default: false, the rules keep the order of the [BindingsMap]
+    pub fn sort_renames(&mut self, will: bool) -> &mut Self {
+        self.rename_order = match will {
+            true => RenameOrder::Alphabetical,
+            false => RenameOrder::AsProvided,
+        };
+        self
+    }
+
+    /// Order of the rules in the rename table, also applied to the existing rules of the template
+    ///
+    /// default: [RenameOrder::AsProvided]
+    pub fn rename_order(&mut self, order: RenameOrder) -> &mut Self {
+        self.rename_order = order;
+        self
+    }
+
+    /// The Rust names in the order their types were declared, used by [RenameOrder::Declaration]
+    /// e.g.: [NameMappings::declaration_order]
+    ///
+    /// The rules missing from it come after the listed ones, sorted by Rust name
+    pub fn declaration_order<S: Into<String>>(
+        &mut self,
+        rust_names: impl IntoIterator<Item = S>,
+    ) -> &mut Self {
+        self.declaration_order = rust_names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Should the rename table be preceded by a comment counting its rules by kind
+    /// e.g.: `# 12 structs, 3 unions, 1 alias`, to make the committed configs self-describing
+    ///
+    /// The kinds are read from the tag keyword of the C names, the untagged ones are counted as aliases.
+    /// The comment is part of the table's decor, after the one already in the template
+    ///
+    /// default: false
+    pub fn summary_comment(&mut self, will: bool) -> &mut Self {
+        self.summary_comment = will;
+        self
+    }
+
+    /// Line endings and trailing newline of the config written by [Template::write_to]
+    ///
+    /// default: [OutputFormat::default], LF with a single trailing newline
+    pub fn output_format(&mut self, format: OutputFormat) -> &mut Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Should [Template::generate_toml] warn about the rename rules that look like they lost their tag keyword,
+    /// see [Template::untagged_renames]
+    ///
+    /// default: false
+    pub fn check_tags(&mut self, will: bool) -> &mut Self {
+        self.check_tags = will;
         self
     }
 
+    /// The rename rules of the [BindingsMap] that look like a struct, union or enum missing its tag keyword,
+    /// sorted by Rust name
+    ///
+    /// The map doesn't hold the kind of the types, so this is a heuristic: a rule renaming a type to its own
+    /// bare name (`"Foo" = "Foo"`) is suspicious since the C name of a type is either tagged (`"struct Foo"`)
+    /// or an alias. cbindgen would silently rename these to a name it can't resolve
+    pub fn untagged_renames(&self) -> Result<Vec<(&'template str, &'template str)>> {
+        let mut suspicious: Vec<(&str, &str)> = self
+            .merged_bindings()?
+            .into_iter()
+            .filter(|(rust_name, c_name)| split_tag(c_name).is_none() && rust_name == c_name)
+            .collect();
+        suspicious.sort();
+
+        Ok(suspicious)
+    }
+
     /// Generate a toml [Document] with the `[export.rename]` section containing the rename rules for our bindings
+    /// (or the table set with [Template::rename_table_path])
     /// WILL NOT overwrite an existing `[export.rename]` table, but WILL overwrite a colliding entry in it
     pub fn generate_toml(&self) -> Result<Document> {
-        if self.bindings.is_none() {
-            return Err(Box::new(TemplateError::MissingBindings));
+        Ok(self.generate_toml_with_report()?.0)
+    }
+
+    /// Same as [Template::generate_toml], along with the counts of the rules it wrote and the findings
+    /// of the checks, e.g.: for CI to assert on them
+    pub fn generate_toml_with_report(&self) -> Result<(Document, ExportReport)> {
+        if self.bindings.is_empty() {
+            return Err(TemplateError::MissingBindings.into());
         }
 
         if self.doc.is_none() {
-            return Err(Box::new(TemplateError::DocumentNotRead));
+            return Err(TemplateError::DocumentNotRead.into());
         }
 
-        let mut document = self.doc.clone().unwrap();
+        let mut report = ExportReport::default();
+        if self.check_tags {
+            for (rust_name, c_name) in self.untagged_renames()? {
+                eprintln!(
+                    "Warn: rename rule {rust_name:?} = {c_name:?} may be missing its tag keyword (struct, union or enum)"
+                );
+                report
+                    .untagged_renames
+                    .push((rust_name.to_string(), c_name.to_string()));
+            }
+        }
 
-        let mut renames = if let Some(table) = document.get_mut("export.rename") {
-            table.as_table_mut().unwrap()
-        } else {
-            document["export"]["rename"] = table();
-            document["export"]["rename"].as_table_mut().unwrap()
-        };
+        // only the last rule of each Rust name is written
+        let mut written: HashMap<&str, usize> = HashMap::new();
+        for (rust_name, _) in self.bindings.iter().flat_map(|map| map.entries()) {
+            *written.entry(rust_name).or_default() += 1;
+        }
+        report.skipped = written.values().map(|count| count - 1).sum();
+
+        // in order, so that the rules of the last maps overwrite the ones of the first maps
+        let document = self.generate_toml_with(|renames| {
+            report.overwritten = written
+                .keys()
+                .filter(|rust_name| renames.contains_key(rust_name))
+                .count();
+            report.inserted = written.len() - report.overwritten;
+
+            for bindings in &self.bindings {
+                extend_toml_table_with_bindings_map(
+                    renames,
+                    bindings,
+                    self.prefix_awareness.as_deref(),
+                )
+            }
+        })?;
+
+        Ok((document, report))
+    }
+
+    /// Same as [Template::generate_toml] but the rename table is filled by `extend` instead of the [BindingsMap]
+    fn generate_toml_with(&self, extend: impl FnOnce(&mut Table)) -> Result<Document> {
+        let mut document = self.doc.clone().ok_or(TemplateError::DocumentNotRead)?;
+
+        let renames = get_or_insert_table(&mut document, &self.rename_table_path)?;
+        extend(renames);
+        match self.rename_order {
+            RenameOrder::Alphabetical => renames.sort_values(),
+            RenameOrder::Declaration => {
+                let position = |key: &Key| {
+                    let position = self
+                        .declaration_order
+                        .iter()
+                        .position(|rust_name| rust_name == key.get());
+                    (position.unwrap_or(usize::MAX), key.get().to_string())
+                };
+                renames.sort_values_by(|a, _, b, _| position(a).cmp(&position(b)));
+            }
+            RenameOrder::AsProvided => {}
+        }
+        if self.summary_comment {
+            let prefix = renames
+                .decor()
+                .prefix()
+                .and_then(|prefix| prefix.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let summary = rename_summary(renames);
+            // an empty implicit table would hide its header and the comment with it
+            renames.set_implicit(false);
+            renames
+                .decor_mut()
+                .set_prefix(format!("{prefix}# {summary}\n"));
+        }
+
+        if !self.parse_includes.is_empty() {
+            let parse = get_or_insert_table(&mut document, &["parse".to_string()])?;
+            let include = parse
+                .entry("include")
+                .or_insert_with(|| Item::Value(Value::Array(Array::new())));
+            match include.as_array_mut() {
+                Some(array) => {
+                    for name in &self.parse_includes {
+                        if !array.iter().any(|value| value.as_str() == Some(name)) {
+                            array.push(name.as_str());
+                        }
+                    }
+                }
+                None => *include = Item::Value(self.parse_includes.iter().collect()),
+            }
+        }
 
-        let bindings = self.bindings.unwrap();
-        extend_toml_table_with_bindings_map(&mut renames, bindings);
+        let export = self
+            .rename_table_path
+            .split_last()
+            .map(|(_, export)| export)
+            .unwrap_or_default();
+        for (subtable, rust_name, value) in &self.per_type {
+            let mut path = export.to_vec();
+            path.push(subtable.clone());
+            let table = get_or_insert_table(&mut document, &path)?;
+            insert_keeping_decor(table, rust_name, value);
+        }
+
+        for (path, value) in &self.values {
+            let (key, tables) = path.split_last().unwrap();
+            let table = get_or_insert_table(&mut document, tables)?;
+            insert_keeping_decor(table, key, value);
+        }
 
         Ok(document)
     }
 
+    /// Checks the rename table of the generated config before it is written, to avoid cbindgen failing later
+    /// with a cryptic error: its values must be non-empty strings, and must not look like a type missing its
+    /// tag keyword (see [Template::untagged_renames])
+    ///
+    /// The whole table is checked, so the rules written by hand in the template are too
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationIssue>> {
+        let document = self
+            .generate_toml()
+            .map_err(|error| vec![ValidationIssue::Generation(error.to_string())])?;
+
+        let mut issues = Vec::new();
+        let renames = self
+            .rename_table_path
+            .iter()
+            .try_fold(document.as_item(), |item, key| item.get(key))
+            .and_then(Item::as_table_like);
+        for (rust_name, value) in renames.iter().flat_map(|renames| renames.iter()) {
+            let Some(c_name) = value.as_str() else {
+                issues.push(ValidationIssue::NotAString(rust_name.to_string()));
+                continue;
+            };
+
+            let untagged = split_tag(c_name).is_none();
+            if c_name.trim().is_empty() {
+                issues.push(ValidationIssue::EmptyValue(rust_name.to_string()));
+            } else if untagged && rust_name == c_name {
+                issues.push(ValidationIssue::MissingTag {
+                    rust_name: rust_name.to_string(),
+                    c_name: c_name.to_string(),
+                });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Write the generated config, after its [Template::config_header], with the [Template::output_format]
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        self.write_document(&self.generate_toml()?, writer)
+    }
+
+    /// Same as [Template::write_to], to the file at `path` (replaced if it exists)
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_to(File::create(path)?)
+    }
+
+    /// Write the document after the [Template::config_header], with the [Template::output_format]
+    fn write_document<W: Write>(&self, document: &Document, mut writer: W) -> Result<()> {
+        let text = format!("{}{}", self.config_header()?, document);
+        writer.write_all(self.output_format.apply(&text).as_bytes())?;
+
+        Ok(())
+    }
+
     /// Generate a config header explaining that the configuration file was automatically generated
     /// and that modifying this will result in loss of the changes when the project is built again
     ///
-    /// Includes the provided template path (or name if using [Template::use_document]) in [Template::new]
+    /// Includes the provided template path (or name if using [Template::use_document]) in [Template::new],
+    /// or the name given to [Template::empty] with no template to modify
     pub fn config_header(&self) -> Result<String> {
+        if self.from_scratch {
+            let name = self.path.display();
+            return Ok(format!(
+                "# This configuration file has been automatically generated by {name}, from no template source\n\
+    # Do not modify it manually, your changes will be lost. Instead, change the code generating it\n\n",
+            ));
+        }
+
         if let Some(path) = self.path.to_str() {
             Ok(format!(
                 "# This configuration file has been automatically generated\n\
     # Do not modify it manually, your changes will be lost. Instead, make changes to its associated template : {path}\n\n",
             ))
         } else {
-            Err(Box::new(TemplateError::InvalidSourcePath))
+            Err(TemplateError::InvalidSourcePath.into())
         }
     }
 }
 
+/// Line and column, from 1, of the byte `offset` in the `text`
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Walks down the given path of keys in the document, creating the missing tables along the way
+///
+/// Fails with [crate::Error::NotATable] if the template already has something else than a table on the path,
+/// e.g.: `export = "oops"` or an inline table
+fn get_or_insert_table<'doc>(
+    document: &'doc mut Document,
+    path: &[String],
+) -> Result<&'doc mut Table> {
+    let mut current = document.as_table_mut();
+    for (depth, key) in path.iter().enumerate() {
+        let item = current.entry(key).or_insert_with(|| {
+            // only show the header of the tables that actually hold values
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        let found = item.type_name();
+        current = item.as_table_mut().ok_or_else(|| crate::Error::NotATable {
+            path: path[..=depth].join("."),
+            found: found.to_string(),
+        })?;
+    }
+
+    Ok(current)
+}
+
+/// Counts the rename rules of the table by the tag of their C name: `12 structs, 3 unions, 1 alias`
+fn rename_summary(renames: &Table) -> String {
+    let mut counts = [0usize; TAG_KEYWORDS.len() + 1];
+    for (_, item) in renames.iter() {
+        let index = split_tag(item.as_str().unwrap_or_default())
+            .and_then(|(tag, _)| TAG_KEYWORDS.iter().position(|keyword| *keyword == tag))
+            .unwrap_or(TAG_KEYWORDS.len());
+        counts[index] += 1;
+    }
+
+    let summary: Vec<String> = counts
+        .iter()
+        .zip(TAG_KEYWORDS.iter().chain(&["alias"]))
+        .filter(|(count, _)| **count > 0)
+        .map(|(count, kind)| match count {
+            1 => format!("1 {kind}"),
+            _ if kind.ends_with('s') => format!("{count} {kind}es"),
+            _ => format!("{count} {kind}s"),
+        })
+        .collect();
+
+    if summary.is_empty() {
+        "no rename rules".to_string()
+    } else {
+        summary.join(", ")
+    }
+}
+
 /// Converts [BindingsMap] entries into toml [Table] entries and insert them into the given table
-fn extend_toml_table_with_bindings_map(table: &mut Table, map: &BindingsMap) {
-    map.into_iter().for_each(|(rust_name, c_name)| {
-        // need this to escape the string quotes
-        let c_name_text = c_name.to_string();
+///
+/// The `prefix_awareness` prefix is removed from the C names, see [Template::prefix_awareness]
+fn extend_toml_table_with_bindings_map(
+    table: &mut Table,
+    map: &BindingsMap,
+    prefix_awareness: Option<&str>,
+) {
+    let renames: Vec<(&str, String)> = map
+        .into_iter()
+        .map(|(rust_name, c_name)| match prefix_awareness {
+            Some(prefix) => (*rust_name, unprefixed_c_name(c_name, prefix)),
+            None => (*rust_name, c_name.to_string()),
+        })
+        .collect();
+
+    extend_toml_table(
+        table,
+        renames
+            .iter()
+            .map(|(rust_name, c_name)| (*rust_name, c_name.as_str())),
+    );
+}
+
+/// Converts rename rules into toml [Table] entries and insert them into the given table
+fn extend_toml_table<'a>(table: &mut Table, renames: impl IntoIterator<Item = (&'a str, &'a str)>) {
+    renames.into_iter().for_each(|(rust_name, c_name)| {
+        // toml_edit quotes and escapes the keys that can't be bare (e.g.: `bgp.peer`), and the values
         table.insert(
-            &rust_name,
-            Item::Value(Value::String(Formatted::new(c_name_text))),
+            rust_name,
+            Item::Value(Value::String(Formatted::new(c_name.to_string()))),
         );
     });
 }
 
+/// Insert the `value` at the `key` of the `table`, with the comments and formatting of the value it replaces
+fn insert_keeping_decor(table: &mut Table, key: &str, value: &Value) {
+    let mut value = value.clone();
+    if let Some(existing) = table.get(key).and_then(Item::as_value) {
+        *value.decor_mut() = existing.decor().clone();
+    }
+    table.insert(key, Item::Value(value));
+}
+
+/// Writes a `cbindgen.toml` at `out_path` from the template at `template_path`,
+/// with the rename rules of the `mappings` selected according to the `options`
+///
+/// Same as reading a [Template], generating its toml with the rename rules of the mappings
+/// and writing it after its [Template::config_header]. Use these directly for more control
+pub fn generate_cbindgen_toml<P: Into<PathBuf>, Q: AsRef<Path>>(
+    mappings: &NameMappings,
+    template_path: P,
+    out_path: Q,
+    options: &RenameOptions,
+) -> Result<()> {
+    let mut template = Template::new(template_path);
+    template.read_as_toml()?;
+
+    let rules = mappings.rename_rules(options)?;
+    let document = template.generate_toml_with(|renames| {
+        extend_toml_table(
+            renames,
+            rules
+                .iter()
+                .map(|rule| (rule.key.as_str(), rule.c_name.as_str())),
+        )
+    })?;
+
+    template.write_document(&document, File::create(out_path)?)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::export::{extend_toml_table_with_bindings_map, BindingsMap};
+    use crate::export::{
+        extend_toml_table_with_bindings_map, generate_cbindgen_toml, BindingsMap, ExportReport,
+        RenameOrder, Template, TemplateError, ValidationIssue,
+    };
+    use crate::import::{CompositeKind, NameMapping, NameMappings, RenameOptions};
+    use crate::output::{LineEnding, OutputFormat};
+    use crate::testing::mapping;
+    use crate::Error;
+    use bindgen::callbacks::DiscoveredItemId;
     use phf_macros::phf_map;
+    use std::collections::{BTreeSet, HashMap};
+    use std::fs;
+    use std::io::Cursor;
+    use toml_edit::{Document, Value};
 
     #[test]
     fn convert_map() {
@@ -149,9 +783,681 @@ mod tests {
         };
 
         let mut converted = toml_edit::Table::new();
-        extend_toml_table_with_bindings_map(&mut converted, &map);
+        extend_toml_table_with_bindings_map(&mut converted, &map, None);
 
         assert_eq!(converted.to_string(),
                    String::from("bmp_peer_hdr = \"struct bmp_peer_hdr\"\nbmp_common_hdr = \"struct bmp_common_hdr\"\n"))
     }
+
+    #[test]
+    fn custom_rename_table_path() {
+        let map: BindingsMap = phf_map! {
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map)
+            .rename_table_path(&["profiles", "a", "export", "rename"]);
+
+        let document = template.generate_toml().unwrap();
+
+        assert_eq!(
+            document["profiles"]["a"]["export"]["rename"]["bmp_common_hdr"].as_str(),
+            Some("struct bmp_common_hdr")
+        );
+        assert!(document.get("export").is_none());
+        assert_eq!(
+            document.to_string(),
+            "[profiles.a.export.rename]\nbmp_common_hdr = \"struct bmp_common_hdr\"\n"
+        );
+    }
+
+    #[test]
+    fn read_from_reader() {
+        let map: BindingsMap = phf_map! {
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+        };
+
+        let mut template = Template::new("embedded/cbindgen.toml.template");
+        template
+            .read_from(Cursor::new(b"language = \"C\"\n".as_slice()))
+            .unwrap()
+            .with_bindings(&map);
+
+        assert_eq!(
+            template.generate_toml().unwrap().to_string(),
+            "language = \"C\"\n\n[export.rename]\nbmp_common_hdr = \"struct bmp_common_hdr\"\n"
+        );
+        assert!(template
+            .config_header()
+            .unwrap()
+            .contains("embedded/cbindgen.toml.template"));
+
+        assert!(matches!(
+            Template::new("invalid").read_from(Cursor::new(b"[[".as_slice())),
+            Err(Error::TemplateParse { .. })
+        ));
+    }
+
+    #[test]
+    fn untagged_renames() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "bmp_peer_hdr",
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+            "bmp_data" => "bmp_data_t",
+            "bmp_type" => "enum bmp_type",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map)
+            .check_tags(true);
+
+        assert_eq!(
+            template.untagged_renames().unwrap(),
+            vec![("bmp_peer_hdr", "bmp_peer_hdr")]
+        );
+
+        // only a warning, the rule is still written
+        let document = template.generate_toml().unwrap();
+        assert_eq!(
+            document["export"]["rename"]["bmp_peer_hdr"].as_str(),
+            Some("bmp_peer_hdr")
+        );
+
+        assert!(matches!(
+            Template::new("cbindgen.toml.template").untagged_renames(),
+            Err(Error::Template(TemplateError::MissingBindings))
+        ));
+    }
+
+    #[test]
+    fn set_values() {
+        let map: BindingsMap = phf_map! {
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(
+                "language = \"C++\" # the language\n\n[export]\nprefix = \"old_\"\n"
+                    .parse::<Document>()
+                    .unwrap(),
+            )
+            .unwrap()
+            .with_bindings(&map)
+            .set(&["export", "prefix"], "my_".into())
+            .set(&["language"], "C".into())
+            .set(
+                &["export", "include"],
+                Value::Array(["foo", "bar"].into_iter().collect()),
+            );
+
+        let document = template.generate_toml().unwrap();
+        assert_eq!(document["export"]["prefix"].as_str(), Some("my_"));
+        assert_eq!(
+            document["export"]["rename"]["bmp_common_hdr"].as_str(),
+            Some("struct bmp_common_hdr")
+        );
+        assert_eq!(
+            document.to_string(),
+            "language = \"C\" # the language\n\n\
+             [export]\n\
+             prefix = \"my_\"\n\
+             include = [\"foo\", \"bar\"]\n\n\
+             [export.rename]\n\
+             bmp_common_hdr = \"struct bmp_common_hdr\"\n"
+        );
+    }
+
+    #[test]
+    fn prefix_awareness() {
+        let map: BindingsMap = phf_map! {
+            "Foo" => "struct my_foo",
+            "Bar" => "bar_t",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map);
+
+        let document = template.generate_toml().unwrap();
+        assert_eq!(
+            document["export"]["rename"]["Foo"].as_str(),
+            Some("struct my_foo")
+        );
+
+        let document = template
+            .prefix_awareness(Some("my_".to_string()))
+            .generate_toml()
+            .unwrap();
+        assert_eq!(
+            document["export"]["rename"]["Foo"].as_str(),
+            Some("struct foo")
+        );
+        assert_eq!(document["export"]["rename"]["Bar"].as_str(), Some("bar_t"));
+    }
+
+    #[test]
+    fn add_bindings() {
+        static BGP: BindingsMap = phf_map! {
+            "bgp_peer" => "struct bgp_peer",
+            "peer_hdr" => "struct bgp_peer_hdr",
+        };
+        static BMP: BindingsMap = phf_map! {
+            "bmp_data" => "union bmp_data",
+            "peer_hdr" => "struct bmp_peer_hdr",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .add_bindings(&BGP)
+            .add_bindings(&BMP);
+
+        let document = template.generate_toml().unwrap();
+        let renames = document["export"]["rename"].as_table().unwrap();
+        assert_eq!(renames.len(), 3);
+        assert_eq!(renames["bgp_peer"].as_str(), Some("struct bgp_peer"));
+        assert_eq!(renames["bmp_data"].as_str(), Some("union bmp_data"));
+        // the last map wins
+        assert_eq!(renames["peer_hdr"].as_str(), Some("struct bmp_peer_hdr"));
+
+        // with_bindings replaces all the maps
+        let document = template.with_bindings(&BGP).generate_toml().unwrap();
+        assert_eq!(document["export"]["rename"].as_table().unwrap().len(), 2);
+        assert_eq!(
+            document["export"]["rename"]["peer_hdr"].as_str(),
+            Some("struct bgp_peer_hdr")
+        );
+    }
+
+    #[test]
+    fn summary_comment() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "struct bmp_peer_hdr",
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+            "bmp_data" => "union bmp_data",
+            "bmp_type_t" => "bmp_type_t",
+            "bmp_stats" => "Struct  bmp_stats",
+            "BmpSession" => "class BmpSession",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document("language = \"C\"\n".parse::<Document>().unwrap())
+            .unwrap()
+            .with_bindings(&map)
+            .sort_renames(true)
+            .summary_comment(true);
+
+        assert_eq!(
+            template.generate_toml().unwrap().to_string(),
+            "language = \"C\"\n\
+             # 3 structs, 1 union, 1 class, 1 alias\n\
+             [export.rename]\n\
+             BmpSession = \"class BmpSession\"\n\
+             bmp_common_hdr = \"struct bmp_common_hdr\"\n\
+             bmp_data = \"union bmp_data\"\n\
+             bmp_peer_hdr = \"struct bmp_peer_hdr\"\n\
+             bmp_stats = \"Struct  bmp_stats\"\n\
+             bmp_type_t = \"bmp_type_t\"\n"
+        );
+
+        let empty: BindingsMap = phf_map! {};
+        let document = template.with_bindings(&empty).generate_toml().unwrap();
+        assert!(document
+            .to_string()
+            .contains("# no rename rules\n[export.rename]"));
+    }
+
+    #[test]
+    fn rename_order() {
+        let mapping = |name: &str, order| NameMapping {
+            order,
+            ..mapping(CompositeKind::Struct, name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(0), mapping("bmp_peer_hdr", 0)),
+                (DiscoveredItemId::new(1), mapping("bmp_common_hdr", 2)),
+                (DiscoveredItemId::new(2), mapping("bmp_data", 1)),
+            ]),
+            ..Default::default()
+        };
+        let map: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "struct bmp_peer_hdr",
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+            "bmp_data" => "struct bmp_data",
+            "bmp_type_t" => "bmp_type_t",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map)
+            .declaration_order(
+                mappings
+                    .declaration_order(&RenameOptions::default())
+                    .unwrap(),
+            );
+        let keys = |template: &Template| -> Vec<String> {
+            template.generate_toml().unwrap()["export"]["rename"]
+                .as_table()
+                .unwrap()
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .collect()
+        };
+
+        template.rename_order(RenameOrder::Alphabetical);
+        assert_eq!(
+            keys(&template),
+            ["bmp_common_hdr", "bmp_data", "bmp_peer_hdr", "bmp_type_t"]
+        );
+
+        // the rules missing from the declaration order come last
+        template.rename_order(RenameOrder::Declaration);
+        assert_eq!(
+            keys(&template),
+            ["bmp_peer_hdr", "bmp_data", "bmp_common_hdr", "bmp_type_t"]
+        );
+    }
+
+    #[test]
+    fn export_report() {
+        const BMP: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "struct bmp_peer_hdr",
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+        };
+        const BMP_TYPES: BindingsMap = phf_map! {
+            "bmp_common_hdr" => "struct bmp_common_hdr",
+            "BmpType" => "BmpType",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(
+                "[export.rename]\nbmp_peer_hdr = \"struct old_peer_hdr\"\n"
+                    .parse::<Document>()
+                    .unwrap(),
+            )
+            .unwrap()
+            .with_bindings(&BMP)
+            .add_bindings(&BMP_TYPES)
+            .check_tags(true);
+
+        let (document, report) = template.generate_toml_with_report().unwrap();
+        assert_eq!(
+            report,
+            ExportReport {
+                inserted: 2,
+                overwritten: 1,
+                skipped: 1,
+                untagged_renames: vec![("BmpType".to_string(), "BmpType".to_string())],
+            }
+        );
+        assert_eq!(
+            document["export"]["rename"]["bmp_peer_hdr"].as_str(),
+            Some("struct bmp_peer_hdr")
+        );
+        assert_eq!(
+            document.to_string(),
+            template.generate_toml().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "struct bmp_peer_hdr",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(
+                "[export.rename]\n\
+                 bgp_peer = \"\"\n\
+                 BgpState = \"BgpState\"\n\
+                 bgp_open = \"struct bgp_open\"\n\
+                 bgp_count = 3\n"
+                    .parse::<Document>()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(matches!(
+            template.validate().unwrap_err().as_slice(),
+            [ValidationIssue::Generation(_)]
+        ));
+
+        template.with_bindings(&map);
+        assert_eq!(
+            template.validate().unwrap_err(),
+            [
+                ValidationIssue::EmptyValue("bgp_peer".to_string()),
+                ValidationIssue::MissingTag {
+                    rust_name: "BgpState".to_string(),
+                    c_name: "BgpState".to_string(),
+                },
+                ValidationIssue::NotAString("bgp_count".to_string()),
+            ]
+        );
+
+        template.use_document(Document::new()).unwrap();
+        assert_eq!(template.validate(), Ok(()));
+    }
+
+    #[test]
+    fn parse_includes() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer_hdr" => "struct bmp_peer_hdr",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map)
+            .with_parse_includes(&["netgauze-bgp-pkt", "netgauze-bmp-pkt"]);
+        let document = template.generate_toml().unwrap();
+        let includes: Vec<&str> = document["parse"]["include"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|value| value.as_str())
+            .collect();
+        assert_eq!(includes, ["netgauze-bgp-pkt", "netgauze-bmp-pkt"]);
+
+        // the existing crates and their comments are kept
+        template.use_document(
+            "[parse]\nparse_deps = true\ninclude = [\n    \"netgauze-bmp-pkt\", # the BMP types\n]\n"
+                .parse::<Document>()
+                .unwrap(),
+        )
+        .unwrap();
+        let document = template.generate_toml().unwrap().to_string();
+        assert!(document.starts_with(
+            "[parse]\nparse_deps = true\ninclude = [\n    \"netgauze-bmp-pkt\", \"netgauze-bgp-pkt\","
+        ));
+        assert!(document.contains("# the BMP types\n]"));
+        assert_eq!(document.matches("netgauze-bmp-pkt").count(), 1);
+    }
+
+    #[test]
+    fn per_type_config() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer" => "struct bmp_peer",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(
+                "[export.body]\nbmp_peer = \"int old;\" # hand-written\n"
+                    .parse::<Document>()
+                    .unwrap(),
+            )
+            .unwrap()
+            .with_bindings(&map)
+            .set_per_type("body", "bmp_peer", "uint8_t padding[3];".into())
+            .set_per_type("pre_body", "bmp_peer", "uint8_t version;".into());
+        let document = template.generate_toml().unwrap();
+        assert_eq!(
+            document["export"]["rename"]["bmp_peer"].as_str(),
+            Some("struct bmp_peer")
+        );
+        assert_eq!(
+            document["export"]["pre_body"]["bmp_peer"].as_str(),
+            Some("uint8_t version;")
+        );
+        // the replaced value keeps its comment
+        assert!(document
+            .to_string()
+            .contains("bmp_peer = \"uint8_t padding[3];\" # hand-written\n"));
+
+        // the entries follow the rename rules into a profile
+        template.rename_table_path(&["profiles", "a", "export", "rename"]);
+        let document = template.generate_toml().unwrap();
+        assert_eq!(
+            document["profiles"]["a"]["export"]["body"]["bmp_peer"].as_str(),
+            Some("uint8_t padding[3];")
+        );
+    }
+
+    #[test]
+    fn non_table_template_keys() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer" => "struct bmp_peer",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document("export = \"oops\"\n".parse::<Document>().unwrap())
+            .unwrap()
+            .with_bindings(&map);
+        let error = template.generate_toml().unwrap_err();
+        assert!(matches!(
+            &error,
+            crate::Error::NotATable { path, found } if path == "export" && found == "string"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Expected a table at export in the template, found string"
+        );
+        assert!(template.write_to(Vec::new()).is_err());
+
+        template
+            .use_document(
+                "[export]\nrename = { bmp_peer = \"struct bmp_peer\" }\n"
+                    .parse::<Document>()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(matches!(
+            template.generate_toml(),
+            Err(crate::Error::NotATable { path, found }) if path == "export.rename" && found == "inline table"
+        ));
+
+        template
+            .use_document("parse = 1\n".parse::<Document>().unwrap())
+            .unwrap();
+        assert!(template.generate_toml().is_ok());
+        template.with_parse_includes(&["netgauze-bmp-pkt"]);
+        assert!(matches!(
+            template.generate_toml(),
+            Err(crate::Error::NotATable { path, found }) if path == "parse" && found == "integer"
+        ));
+    }
+
+    #[test]
+    fn special_keys() {
+        let map: BindingsMap = phf_map! {
+            "bgp.peer" => "struct bgp_peer",
+            "bmp\"data" => "union bmp_data",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document(Document::new())
+            .unwrap()
+            .with_bindings(&map);
+
+        let mut written = Vec::new();
+        template.write_to(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("\"bgp.peer\" = \"struct bgp_peer\"\n"));
+        assert!(written.contains("\"bmp\\\"data\" = \"union bmp_data\"\n"));
+
+        // the keys parse back as they were, and not as dotted keys
+        let document = written.parse::<Document>().unwrap();
+        let renames = document["export"]["rename"].as_table().unwrap();
+        assert_eq!(renames.len(), 2);
+        assert_eq!(renames["bgp.peer"].as_str(), Some("struct bgp_peer"));
+        assert_eq!(renames["bmp\"data"].as_str(), Some("union bmp_data"));
+    }
+
+    #[test]
+    fn empty_template() {
+        let map: BindingsMap = phf_map! {
+            "bmp_peer" => "struct bmp_peer",
+            "bmp_data" => "union bmp_data",
+        };
+
+        let mut template = Template::empty("bmp-ffi");
+        template
+            .with_bindings(&map)
+            .rename_order(RenameOrder::Alphabetical)
+            .set(&["language"], "C".into())
+            .set(&["export", "prefix"], "ffi_".into());
+
+        let mut written = Vec::new();
+        template.write_to(&mut written).unwrap();
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "# This configuration file has been automatically generated by bmp-ffi, from no template source\n\
+             # Do not modify it manually, your changes will be lost. Instead, change the code generating it\n\n\
+             language = \"C\"\n\n\
+             [export]\nprefix = \"ffi_\"\n\n\
+             [export.rename]\nbmp_data = \"union bmp_data\"\nbmp_peer = \"struct bmp_peer\"\n"
+        );
+        // nothing to watch
+        template.emit_rerun_if_changed();
+
+        // a document given afterwards is a template again
+        template.use_document(Document::new()).unwrap();
+        assert!(template
+            .config_header()
+            .unwrap()
+            .ends_with("associated template : bmp-ffi\n\n"));
+    }
+
+    #[test]
+    fn write_with_line_endings() {
+        let map: BindingsMap = phf_map! {
+            "bmp_data" => "union bmp_data",
+        };
+
+        let mut template = Template::new("cbindgen.toml.template");
+        template
+            .use_document("language = \"C\"\n".parse::<Document>().unwrap())
+            .unwrap()
+            .with_bindings(&map);
+
+        let mut lf = Vec::new();
+        template.write_to(&mut lf).unwrap();
+        let expected = format!(
+            "{}language = \"C\"\n\n[export.rename]\nbmp_data = \"union bmp_data\"\n",
+            template.config_header().unwrap()
+        );
+        assert_eq!(lf, expected.as_bytes());
+
+        let mut crlf = Vec::new();
+        template
+            .output_format(OutputFormat {
+                line_ending: LineEnding::Crlf,
+                trailing_newline: true,
+            })
+            .write_to(&mut crlf)
+            .unwrap();
+        assert_eq!(crlf, expected.replace('\n', "\r\n").as_bytes());
+    }
+
+    #[test]
+    fn typed_errors() {
+        let mut template = Template::new("cbindgen.toml.template");
+        template.use_document(Document::new()).unwrap();
+        assert!(matches!(
+            template.generate_toml(),
+            Err(Error::Template(TemplateError::MissingBindings))
+        ));
+
+        let map: BindingsMap = phf_map! {};
+        let template = Template::new("cbindgen.toml.template")
+            .with_bindings(&map)
+            .generate_toml();
+        assert!(matches!(
+            template,
+            Err(Error::Template(TemplateError::DocumentNotRead))
+        ));
+
+        let mut template = Template::new("this/template/does/not/exist.toml");
+        assert!(matches!(template.read_as_toml(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn template_syntax_error() {
+        let dir = std::env::temp_dir().join(format!(
+            "bindgen-bridge-syntax-error-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let template_path = dir.join("cbindgen.toml.template");
+        fs::write(
+            &template_path,
+            "language = \"C\"\n\n[export]\nprefix = \"unterminated\n",
+        )
+        .unwrap();
+
+        let error = Template::new(&template_path).read_as_toml().err().unwrap();
+        assert!(matches!(
+            &error,
+            Error::TemplateParse { path, position: Some((4, _)), .. } if path == &template_path
+        ));
+        let message = error.to_string();
+        assert!(message.contains(&template_path.display().to_string()));
+        assert!(message.contains("at line 4, column"));
+        assert!(std::error::Error::source(&error).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn generate_cbindgen_toml_file() {
+        let dir = std::env::temp_dir().join(format!("bindgen-bridge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let template_path = dir.join("cbindgen.toml.template");
+        let out_path = dir.join("cbindgen.toml");
+        fs::write(&template_path, "language = \"C\"\n").unwrap();
+
+        let mappings = NameMappings {
+            types: HashMap::from([(
+                DiscoveredItemId::new(1),
+                NameMapping {
+                    aliases: BTreeSet::from(["bgp_peer_t".to_string()]),
+                    ..mapping(CompositeKind::Struct, "bgp_peer")
+                },
+            )]),
+            ..Default::default()
+        };
+
+        generate_cbindgen_toml(
+            &mappings,
+            &template_path,
+            &out_path,
+            &RenameOptions {
+                force_aliases_use: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let config = fs::read_to_string(&out_path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(config.starts_with("# This configuration file has been automatically generated\n"));
+        let document = config.parse::<Document>().unwrap();
+        assert_eq!(document["language"].as_str(), Some("C"));
+        assert_eq!(
+            document["export"]["rename"]["bgp_peer"].as_str(),
+            Some("bgp_peer_t")
+        );
+    }
 }
@@ -1,16 +1,26 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashMap};
-use std::fmt::Write;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use bindgen::callbacks::{DiscoveredItem, DiscoveredItemId};
+use bindgen::callbacks::{
+    DeriveInfo, DiscoveredItem, DiscoveredItemId, EnumVariantCustomBehavior, EnumVariantValue,
+    FieldInfo, IntKind, ItemInfo, ItemKind,
+};
+use bindgen::FieldVisibilityKind;
 use phf_codegen::Map;
-use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
+use proc_macro2::{Delimiter, Literal, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use regex::Regex;
+use toml_edit::{Formatted, Key};
 
-use crate::Result;
+use crate::output::{replace_region, OutputFormat, RegionPlacement};
+use crate::{Error, Result};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
 pub struct CName {
     /// The identifier used to address a type
     pub identifier: String,
@@ -19,481 +29,9223 @@ pub struct CName {
     pub aliased: bool,
 }
 
+impl CName {
+    /// A [CName] whose `identifier` is checked to be a plausible C identifier: ASCII letters, digits and `_`,
+    /// not starting with a digit. C++ names may be qualified with their namespaces (`ns::Foo`)
+    ///
+    /// Fails with [Error::InvalidIdentifier] otherwise, e.g.: for `2fast`, `my-type` or `struct Foo`
+    pub fn new<S: Into<String>>(identifier: S, aliased: bool) -> Result<Self> {
+        let identifier = identifier.into();
+        let is_identifier = |segment: &str| {
+            segment
+                .chars()
+                .next()
+                .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        };
+        if !identifier.split("::").all(is_identifier) {
+            return Err(Error::InvalidIdentifier(identifier));
+        }
+
+        Ok(Self {
+            identifier,
+            aliased,
+        })
+    }
+
+    /// The tag-qualified C name of a type of the `kind` named `name`, e.g.: `struct Foo` for a struct `Foo`
+    ///
+    /// A tag keyword already in `name` (`struct Foo`) is replaced by the one of the `kind`,
+    /// like in [NameMapping::validated_original_name]. Fails like [CName::new] for the untagged name
+    pub fn tagged(kind: CompositeKind, name: &str) -> Result<String> {
+        let c_name = Self::new(NameMapping::strip_tag_keywords(name.trim()), false)?;
+
+        Ok(format!(
+            "{}{}",
+            kind.tag_prefix(Language::C),
+            c_name.identifier
+        ))
+    }
+}
+
 #[derive(Clone, Copy, Debug, Ord, PartialOrd, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompositeKind {
     Struct,
     Union,
-}
 
-impl TryFrom<&DiscoveredItem> for CompositeKind {
-    type Error = ();
+    /// C++ `class`
+    Class,
 
-    fn try_from(value: &DiscoveredItem) -> std::result::Result<Self, Self::Error> {
-        match value {
-            DiscoveredItem::Struct { .. } => Ok(Self::Struct),
-            DiscoveredItem::Union { .. } => Ok(Self::Union),
-            DiscoveredItem::Alias { .. } => Err(())
+    /// Unscoped `enum`
+    Enum,
+
+    /// C++ scoped enum (`enum class` / `enum struct`)
+    ScopedEnum,
+
+    /// `typedef` of a non-composite type (e.g.: `typedef uint32_t as_t;`), addressed without tag,
+    /// see [NameMappings::capture_pending_typedefs]
+    Typedef,
+}
+
+impl CompositeKind {
+    /// The tag keyword (followed by a space) needed to address a type of this kind in the given [Language]
+    ///
+    /// C++ types can be used unqualified, so no tag is needed in [Language::Cxx]
+    pub fn tag_prefix(&self, language: Language) -> &'static str {
+        match language {
+            Language::C => match self {
+                CompositeKind::Struct | CompositeKind::Class => "struct ",
+                CompositeKind::Union => "union ",
+                CompositeKind::Enum | CompositeKind::ScopedEnum => "enum ",
+                CompositeKind::Typedef => "",
+            },
+            Language::Cxx => "",
         }
     }
 }
 
-/// One mapping between a type's C name, Rust name, and C aliases
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct NameMapping {
-    /// The kind of composite type (struct or union)
-    pub kind: CompositeKind,
+impl Display for CompositeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            CompositeKind::Struct => "struct",
+            CompositeKind::Union => "union",
+            CompositeKind::Class => "class",
+            CompositeKind::Enum => "enum",
+            CompositeKind::ScopedEnum => "enum class",
+            CompositeKind::Typedef => "typedef",
+        };
 
-    /// Name of the imported type from C
-    /// This is optional because of anonymous types
-    pub c_name: Option<CName>,
+        write!(f, "{}", str)
+    }
+}
 
-    /// Name of the type in Rust after import by bindgen
-    pub rust_name: String,
+/// Language of the parsed headers, decides how the types are addressed in the rename rules
+#[derive(Clone, Copy, Debug, Default, Ord, PartialOrd, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub enum Language {
+    /// Types are addressed with their tag keyword (`struct my_struct`)
+    #[default]
+    C,
 
-    /// List of known aliases for the type
-    pub aliases: BTreeSet<String>,
+    /// Types are addressed unqualified (`my_class`)
+    Cxx,
 }
 
-impl NameMapping {
-    /// Figures out the original name in the C code based on the type and its name
-    ///
-    /// the name of a struct named A is "struct A"
-    /// the name of an union named B is "union A"
-    ///
-    /// If the passed name is an alias, keep it that way
-    pub fn validated_original_name(c_name: Option<&CName>, kind: CompositeKind) -> Option<String> {
-        let original_name = &c_name?.identifier;
-
-        // has a space because we use it to ensure it is not yet present in the name
-        let prefix = match kind {
-            CompositeKind::Struct => "struct ",
-            CompositeKind::Union => "enum ",
-        };
+/// How the namespace path of a C++ type is joined to its name in the rename rules
+#[derive(Clone, Copy, Debug, Default, Ord, PartialOrd, PartialEq, Eq)]
+pub enum NamespaceStyle {
+    /// Fully-qualified C++ name: `foo::bar::Baz`
+    #[default]
+    Qualified,
 
-        // do not prepend the prefix to an aliased type
-        let result = if c_name?.aliased || original_name.starts_with(prefix) {
-            original_name.clone()
-        } else {
-            format!("{prefix}{original_name}")
-        };
+    /// Flattened name: `foo__bar__Baz`
+    Flattened,
+}
 
-        Some(result)
+impl NamespaceStyle {
+    /// Separator placed between the namespaces and the name
+    pub fn separator(&self) -> &'static str {
+        match self {
+            NamespaceStyle::Qualified => "::",
+            NamespaceStyle::Flattened => "__",
+        }
     }
 }
 
-#[derive(Debug, Default, Clone, Eq, PartialEq)]
-pub struct NameMappings {
-    /// The discovered types and their mappings
-    pub types: HashMap<DiscoveredItemId, NameMapping>,
+/// Case convention applied to the C names in the rename rules, see [MappingsCodegen::case_style]
+#[derive(Clone, Copy, Debug, Ord, PartialOrd, PartialEq, Eq)]
+pub enum CaseStyle {
+    /// `my_type`
+    Snake,
 
-    /// The known aliases without an associated type mappings
-    pub aliases: HashMap<DiscoveredItemId, BTreeSet<String>>,
+    /// `MyType`
+    Pascal,
 }
 
-impl NameMappings {
-    /// Drain the temporary alias cache
-    pub fn forget_unused_aliases(&mut self) -> usize {
-        self.aliases.drain().map(|(_, set)| set.len()).sum()
+impl CaseStyle {
+    /// Converts a name to this case, keeping its leading underscores and its C++ namespaces separators (`::`)
+    /// e.g.: `HTTPServer_config` is `http_server_config` in [CaseStyle::Snake] and `HttpServerConfig` in [CaseStyle::Pascal]
+    pub fn convert(&self, name: &str) -> String {
+        name.split("::")
+            .map(|segment| self.convert_segment(segment))
+            .collect::<Vec<_>>()
+            .join("::")
     }
 
-    /// Generate a cbindgen.toml [export.rename] section, without the section header
-    pub fn to_cbindgen_toml_renames(&self, force_aliases_use: bool) -> Result<String> {
-        let mut result = String::with_capacity(self.types.len() * 16); // rough approximate of the capacity
-
-        for (id, mapping) in &self.types {
-            let use_name =
-                if mapping.c_name.is_none() || (force_aliases_use && !mapping.aliases.is_empty()) {
-                    mapping.aliases.iter().next().cloned()
-                } else {
-                    NameMapping::validated_original_name(mapping.c_name.as_ref(), mapping.kind)
-                };
+    fn convert_segment(&self, segment: &str) -> String {
+        let identifier = segment.trim_start_matches('_');
+        let leading = &segment[..segment.len() - identifier.len()];
 
-            if let Some(use_name) = use_name {
-                writeln!(&mut result, "\"{}\" = \"{}\"", mapping.rust_name, use_name)?;
-            } else {
-                eprintln!(
-                    "Warn: type with no valid name during rename export! id={:#?} info={:#?}",
-                    id, mapping
-                );
-                continue;
-            }
-        }
+        let words = split_words(identifier);
+        let converted = match self {
+            CaseStyle::Snake => words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            CaseStyle::Pascal => words
+                .iter()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| {
+                        first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect()
+                    })
+                })
+                .collect(),
+        };
 
-        Ok(result)
+        format!("{leading}{converted}")
     }
+}
 
-    /// Generates a [phf_codegen] static map from the mappings
+/// The tag keywords (followed by a space) prepended to the C names of each [CompositeKind]
+///
+/// default: the C keywords, see [CompositeKind::tag_prefix]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagPrefixes {
+    /// Prefix of [CompositeKind::Struct] names
+    pub struct_prefix: String,
+
+    /// Prefix of [CompositeKind::Union] names
+    pub union_prefix: String,
+
+    /// Prefix of [CompositeKind::Class] names
+    pub class_prefix: String,
+
+    /// Prefix of [CompositeKind::Enum] names
+    pub enum_prefix: String,
+
+    /// Prefix of [CompositeKind::ScopedEnum] names
+    pub scoped_enum_prefix: String,
+
+    /// Also prefix the names taken from an alias (see [CName::aliased]), e.g.: `struct foo_t` for
+    /// `typedef struct { .. } foo_t;`, for the dialects expecting the tag anyway
     ///
-    /// Uses the first alias given by the [NameMappings::aliases]'s [BTreeSet] values for the rename rule
-    /// (no guarantee on which one, but it's likely based on Strings' alphabetical ordering)
-    pub fn to_static_map(&self, force_aliases_use: bool) -> Result<Map<String>> {
-        let mut result = Map::new();
+    /// default: false, the alias is used as-is since it names the type without its tag
+    pub tag_aliased_names: bool,
 
-        for (id, mapping) in &self.types {
-            let use_name =
-                if mapping.c_name.is_none() || (force_aliases_use && !mapping.aliases.is_empty()) {
-                    mapping.aliases.iter().next().cloned()
-                } else {
-                    NameMapping::validated_original_name(mapping.c_name.as_ref(), mapping.kind)
-                };
+    /// Also strip the tag keywords already in the names regardless of their case, e.g.: `Struct Foo` becomes
+    /// `struct Foo` instead of `struct Struct Foo`, for preprocessed headers with unusual spellings
+    ///
+    /// default: false, only the lowercase C keywords are stripped
+    pub case_insensitive_tags: bool,
+}
 
-            if let Some(use_name) = use_name {
-                result.entry(mapping.rust_name.clone(), &format!("\"{}\"", use_name));
-            } else {
-                eprintln!(
-                    "Warn: type with no valid name during rename export! id={:#?} info={:#?}",
-                    id, mapping
-                );
-                continue;
-            }
+impl TagPrefixes {
+    /// Empty prefixes for every kind, the names are used bare
+    pub fn none() -> Self {
+        Self {
+            struct_prefix: String::new(),
+            union_prefix: String::new(),
+            class_prefix: String::new(),
+            enum_prefix: String::new(),
+            scoped_enum_prefix: String::new(),
+            tag_aliased_names: false,
+            case_insensitive_tags: false,
         }
-
-        Ok(result)
     }
 
-    /// Wraps these mappings in a [MappingsCodegen] builder to export the mappings as static code
-    ///
-    /// Reversible with [MappingsCodegen::mappings]
-    pub fn codegen<'a>(self) -> MappingsCodegen<'a> {
-        self.into()
+    /// The prefix used for the given [CompositeKind]
+    pub fn get(&self, kind: CompositeKind) -> &str {
+        match kind {
+            CompositeKind::Struct => &self.struct_prefix,
+            CompositeKind::Union => &self.union_prefix,
+            CompositeKind::Class => &self.class_prefix,
+            CompositeKind::Enum => &self.enum_prefix,
+            CompositeKind::ScopedEnum => &self.scoped_enum_prefix,
+            // a typedef is never tagged
+            CompositeKind::Typedef => "",
+        }
     }
 }
 
-/// The callback to include with [bindgen::Builder::parse_callbacks] in your `build.rs`
-/// to discover types and aliases during the C header parsing.
-#[derive(Debug)]
-pub struct NameMappingsCallback(pub Rc<RefCell<NameMappings>>);
-
-// callback behaviour pseudo code
-// types: Map ItemId => Info { canonical_ident (final rust name), original_name(item.kind.type.name), HashSetAlias> }
-// found_aliases: Map ItemId => Alias
-//
-// on new type/item: call new composite callback => insert to map, check found_aliases
-// on new alias: call new alias callback => if alias.type in types types.get(alias.type.id).push_alias(alias) else found_aliases.push(alias)
-// on resolvedtyperef: call new alias callback => ^ + typeref.name != original_name
-impl bindgen::callbacks::ParseCallbacks for NameMappingsCallback {
-    fn new_item_found(&self, id: DiscoveredItemId, item: DiscoveredItem) {
-        match &item {
-            DiscoveredItem::Struct { original_name, final_name }
-            | DiscoveredItem::Union { original_name, final_name } => {
-                self.new_composite_found(id, CompositeKind::try_from(&item).unwrap(), original_name.as_ref().map(String::as_str), final_name)
-            }
-            DiscoveredItem::Alias { alias_name, alias_for } => {
-                self.new_alias_found(id, alias_name, *alias_for)
-            }
+impl From<Language> for TagPrefixes {
+    fn from(language: Language) -> Self {
+        let prefix = |kind: CompositeKind| kind.tag_prefix(language).to_string();
+        Self {
+            struct_prefix: prefix(CompositeKind::Struct),
+            union_prefix: prefix(CompositeKind::Union),
+            class_prefix: prefix(CompositeKind::Class),
+            enum_prefix: prefix(CompositeKind::Enum),
+            scoped_enum_prefix: prefix(CompositeKind::ScopedEnum),
+            tag_aliased_names: false,
+            case_insensitive_tags: false,
         }
     }
 }
 
-impl NameMappingsCallback {
-    /// Called when a new composite type is found (struct / union)
-    ///
-    /// Saves the type, its name, its aliases
-    fn new_composite_found(
-        &self,
-        id: DiscoveredItemId,
-        kind: CompositeKind,
-        original_name: Option<&str>,
-        final_ident: &str,
-    ) {
-        let mut mappings = self.0.borrow_mut();
+impl Default for TagPrefixes {
+    fn default() -> Self {
+        Language::C.into()
+    }
+}
 
-        let mut aliases = mappings
-            .aliases
-            .remove(&id)
-            .unwrap_or_else(|| BTreeSet::new());
+/// Settings deciding which name is used for each type in the generated rename rules
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RenameOptions {
+    /// see [MappingsCodegen::force_aliases_use]
+    pub force_aliases_use: bool,
 
-        // if the struct is not anonymous
-        let c_name = if original_name.is_some() {
-            // build a non-aliased CName since we know the type's actual name
-            let c_name = original_name.map(|name| CName {
-                identifier: name.to_string(),
-                aliased: false,
-            });
+    /// see [MappingsCodegen::namespace_style]
+    pub namespace_style: NamespaceStyle,
 
-            // remove all aliases with the same name (including the type keyword)
-            // this takes out "struct my_struct" while keeping "my_struct" as an alias for the
-            // typedef struct my_struct {..} my_struct; pattern
-            if let Some(original_name) = NameMapping::validated_original_name(c_name.as_ref(), kind)
-            {
-                aliases.retain(|value| !value.eq(&original_name));
-            }
+    /// see [MappingsCodegen::tag_prefixes]
+    pub tag_prefixes: Option<TagPrefixes>,
 
-            c_name
-        }
-        // if the struct is anonymous and we already know an alias for it
-        // we use use the latter as the new name, but remember that it was aliased
-        else if let Some(one_alias) = aliases.iter().next().cloned() {
-            aliases.take(&one_alias).map(|name| CName {
-                identifier: name,
-                aliased: true,
-            })
-            // for an unknown anonymous struct without aliases we can't invent a name
-        } else {
-            None
-        };
+    /// see [MappingsCodegen::unnamed_types]
+    pub unnamed_types: UnnamedTypePolicy,
 
-        println!(
-            "kind : {:?} original {:?} => {:?}",
-            kind, original_name, c_name
-        );
+    /// see [MappingsCodegen::case_style]
+    pub case_style: Option<CaseStyle>,
 
-        if let Some(duplicate) = mappings.types.insert(
-            id,
-            NameMapping {
-                kind,
-                c_name: c_name.clone(), // may still be unknown in case of anonymous struct without known aliases
-                rust_name: final_ident.to_string(),
-                aliases,
-            },
-        ) {
-            println!(
-                "Warn: duplicated definition for {{ id={:?} name={:?} }}! previous: {:?}",
-                id, c_name, duplicate
-            )
-        }
-    }
+    /// see [MappingsCodegen::strip_rust_prefix]
+    pub strip_rust_prefix: String,
 
-    /// Called when a new alias is found
-    ///
-    /// Saves the alias either as an alias or the base name (if none is known yet) for known types.
-    /// The alias is saved for later when the type is not known yet
-    fn new_alias_found(&self, _id: DiscoveredItemId, alias_name: &str, target_id: DiscoveredItemId) {
-        let mut mappings = self.0.borrow_mut();
+    /// see [MappingsCodegen::strip_rust_suffix]
+    pub strip_rust_suffix: String,
 
-        let aliased_name = alias_name.to_string();
+    /// see [MappingsCodegen::force_aliases_when], overrides [RenameOptions::force_aliases_use]
+    pub force_aliases_when: Option<MappingPredicate>,
 
-        if let Some(mapping) = mappings.types.get_mut(&target_id) {
-            // if the structure was anonymous let's use one of its aliases as a name
-            if let None = mapping.c_name {
-                mapping.c_name = Some(CName {
-                    identifier: aliased_name,
-                    aliased: true,
-                });
-            }
-            // if it wasn't, remember the alias
-            else {
-                mapping.aliases.insert(aliased_name);
+    /// see [MappingsCodegen::alias_selector]
+    pub alias_selector: AliasSelector,
+
+    /// see [MappingsCodegen::filter]
+    pub filter: Option<MappingPredicate>,
+
+    /// see [MappingsCodegen::prefix_awareness]
+    pub prefix_awareness: Option<String>,
+
+    /// see [MappingsCodegen::map_rust_name]
+    pub map_rust_name: Option<KeyMapper>,
+
+    /// see [MappingsCodegen::no_tag_prefix], overrides [RenameOptions::tag_prefixes]
+    pub no_tag_prefix: bool,
+
+    /// see [MappingsCodegen::keep_monomorphizations]
+    pub keep_monomorphizations: bool,
+
+    /// see [MappingsCodegen::name_sources], overrides [RenameOptions::force_aliases_use],
+    /// [RenameOptions::force_aliases_when] and [RenameOptions::alias_selector] if not empty
+    pub name_sources: Vec<NameSource>,
+}
+
+/// Where the C name of a type comes from, see [MappingsCodegen::name_sources]
+#[derive(Clone, Debug)]
+pub enum NameSource {
+    /// The original name with its tag keyword (`struct bmp_peer`), or the alias naming an anonymous type,
+    /// see [NameMapping::validated_original_name]
+    Original,
+
+    /// The alias picked by the [AliasSelector], if the type has aliases
+    Alias(AliasSelector),
+
+    /// The first alias, in the [BTreeSet] order, matched by the regex, e.g.: `_t$` for the `*_t` typedefs
+    AliasMatching(Regex),
+}
+
+impl PartialEq for NameSource {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (NameSource::Original, NameSource::Original) => true,
+            (NameSource::Alias(a), NameSource::Alias(b)) => a == b,
+            (NameSource::AliasMatching(a), NameSource::AliasMatching(b)) => {
+                a.as_str() == b.as_str()
             }
-        } else {
-            mappings
-                .aliases
-                .entry(target_id)
-                .or_default()
-                .insert(aliased_name);
-        };
+            _ => false,
+        }
     }
 }
 
-/// Code builder used to export mappings by generating [TokenStream]s
-#[derive(Debug, Clone)]
-pub struct MappingsCodegen<'var_name> {
-    /// Mappings used to generate code
-    mappings: NameMappings,
+impl Eq for NameSource {}
 
-    /// Always promote an alias to be the C name of a struct if possible.
-    /// e.g.: `struct MyStruct` with a `typedef struct MyStruct AliasOfMyStruct` will be known as `AliasOfMyStruct`
-    /// see [MappingsCodegen::force_aliases_use]
-    force_aliases_use: bool,
+/// Which alias is used when a type is renamed to one of its aliases
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AliasSelector {
+    /// The first alias in the [BTreeSet] order (alphabetical)
+    #[default]
+    First,
+
+    /// The first declared alias, see [NameMapping::alias_order]
+    /// e.g.: `S_t` for `typedef struct S S_t;` followed by `typedef S_t S_alias;`
+    ///
+    /// Falls back to [AliasSelector::First] for the aliases missing from the declaration order,
+    /// see [NameMapping::selected_alias]
+    FirstDeclared,
 
-    /// see [MappingsCodegen::as_static_map]
-    as_static_map: bool,
+    /// The shortest alias, the first one in the [BTreeSet] order among the shortest ones
+    Shortest,
 
-    /// see [MappingsCodegen::variable_name]
-    variable_name: Option<&'var_name str>,
+    /// The longest alias, the first one in the [BTreeSet] order among the longest ones
+    Longest,
 }
 
-impl From<NameMappings> for MappingsCodegen<'_> {
-    fn from(value: NameMappings) -> Self {
-        Self {
-            mappings: value,
-            force_aliases_use: false,
-            as_static_map: false,
-            variable_name: None,
+impl AliasSelector {
+    /// The selected alias among the given ones, regardless of their declaration order
+    /// which only [NameMapping::selected_alias] knows
+    fn select<'a>(&self, aliases: &'a BTreeSet<String>) -> Option<&'a String> {
+        match self {
+            AliasSelector::First | AliasSelector::FirstDeclared => aliases.iter().next(),
+            AliasSelector::Shortest => aliases.iter().min_by_key(|alias| alias.len()),
+            // max_by_key keeps the last maximum
+            AliasSelector::Longest => aliases.iter().rev().max_by_key(|alias| alias.len()),
         }
     }
 }
 
-impl From<MappingsCodegen<'_>> for NameMappings {
-    fn from(value: MappingsCodegen) -> Self {
-        value.mappings
-    }
-}
+/// A condition on a [NameMapping], see [MappingsCodegen::force_aliases_when]
+///
+/// Two predicates are only equal if they are the same closure
+#[derive(Clone)]
+pub struct MappingPredicate(Rc<dyn Fn(&NameMapping) -> bool>);
 
-impl<'var_name> MappingsCodegen<'var_name> {
-    /// Unwrap back into a [NameMappings], loses the settings of the [MappingsCodegen]
-    pub fn mappings(self) -> NameMappings {
-        self.into()
+impl MappingPredicate {
+    /// Wraps the closure deciding the condition
+    pub fn new(predicate: impl Fn(&NameMapping) -> bool + 'static) -> Self {
+        Self(Rc::new(predicate))
     }
 
-    /// Should we use the first (by [`BTreeSet<String>`] ordering) known alias of the types
-    /// as the C name of the types
-    ///
-    /// default: false
-    pub fn force_aliases_use(&mut self, will: bool) -> &mut Self {
-        self.force_aliases_use = will;
-        self
+    /// Whether the mapping satisfies the condition
+    pub fn test(&self, mapping: &NameMapping) -> bool {
+        (self.0)(mapping)
     }
+}
 
-    /// Should we export the code as a [Map]
-    /// if `false` (by default) the code generated is a static raw str in a toml format
-    /// without the section header to let you use it where you want
-    ///
-    /// default: false
-    pub fn as_static_map(&mut self, will: bool) -> &mut Self {
-        self.as_static_map = will;
-        self
+impl std::fmt::Debug for MappingPredicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MappingPredicate(..)")
     }
+}
 
-    /// Name of the static variable used to store the exported value in the generated code
-    /// If `None`, the generated code will just be the value, without a variable assignment
-    ///
-    /// default: None
-    pub fn variable_name(&mut self, variable_name: Option<&'var_name str>) -> &mut Self {
-        self.variable_name = if variable_name.is_some() && variable_name.unwrap() == "" {
-            None
-        } else {
-            variable_name
-        };
-
-        self
+impl PartialEq for MappingPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
     }
+}
 
-    /// Generate a [TokenStream] based on all the parameters set on [Self]
-    pub fn generate(&self) -> Result<TokenStream> {
-        let variable_name_ident = self.variable_name.map(|name| format_ident!("{}", name));
+impl Eq for MappingPredicate {}
 
-        let var_type = if self.as_static_map {
-            quote! {
-                phf::Map<&'static str, &'static str>
-            }
-        } else {
-            quote! {
-                &'static str
-            }
-        };
+/// A rewrite of the keys of the rename rules, see [MappingsCodegen::map_rust_name]
+///
+/// Two rewrites are only equal if they are the same closure
+#[derive(Clone)]
+pub struct KeyMapper(Rc<dyn Fn(&str) -> String>);
 
-        let mut value = if self.as_static_map {
-            self.mappings
-                .to_static_map(self.force_aliases_use)?
-                .build()
-                .to_string()
-        } else {
-            self.mappings.to_cbindgen_toml_renames(self.force_aliases_use)?
-        }
-            .parse::<TokenStream>()?;
+impl KeyMapper {
+    /// Wraps the closure rewriting the keys
+    pub fn new(mapper: impl Fn(&str) -> String + 'static) -> Self {
+        Self(Rc::new(mapper))
+    }
 
-        if let Some(bindings_name) = variable_name_ident {
-            value = quote! {
-                pub static #bindings_name : #var_type = #value;
-            };
-        }
+    /// The rewritten key
+    pub fn map(&self, key: &str) -> String {
+        (self.0)(key)
+    }
+}
 
-        Ok(value)
+impl std::fmt::Debug for KeyMapper {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("KeyMapper(..)")
     }
 }
 
+impl PartialEq for KeyMapper {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for KeyMapper {}
+
+/// A key grouping the [NameMapping]s, see [MappingsCodegen::partition_by]
+///
+/// Two classifiers are only equal if they are the same closure
+#[derive(Clone)]
+pub struct MappingClassifier(Rc<dyn Fn(&NameMapping) -> String>);
+
+impl MappingClassifier {
+    /// Wraps the closure giving the keys
+    pub fn new(classifier: impl Fn(&NameMapping) -> String + 'static) -> Self {
+        Self(Rc::new(classifier))
+    }
+
+    /// The key of the mapping
+    pub fn classify(&self, mapping: &NameMapping) -> String {
+        (self.0)(mapping)
+    }
+}
+
+impl std::fmt::Debug for MappingClassifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MappingClassifier(..)")
+    }
+}
+
+impl PartialEq for MappingClassifier {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MappingClassifier {}
+
+/// What to do with the anonymous types that have no alias to be named after in the rename rules
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum UnnamedTypePolicy {
+    /// Leave the type out of the rename rules with a warning
+    #[default]
+    Skip,
+
+    /// Fail with [Error::UnnamedType]
+    Error,
+
+    /// Name the type with the given prefix followed by the number bindgen gave to the anonymous type
+    /// e.g.: `_bindgen_ty_1` is named `anon_1` with the `anon_` prefix
+    Synthesize(String),
+}
+
+/// bindgen does not tell C++ classes and structs apart (both are reported as [DiscoveredItem::Struct])
+/// and does not report enums yet, so only [CompositeKind::Struct] and [CompositeKind::Union] are produced
+impl TryFrom<&DiscoveredItem> for CompositeKind {
+    type Error = ();
+
+    fn try_from(value: &DiscoveredItem) -> std::result::Result<Self, Self::Error> {
+        match value {
+            DiscoveredItem::Struct { .. } => Ok(Self::Struct),
+            DiscoveredItem::Union { .. } => Ok(Self::Union),
+            // aliases, and the kinds of items bindgen may report in the future
+            _ => Err(()),
+        }
+    }
+}
+
+/// One mapping between a type's C name, Rust name, and C aliases
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameMapping {
+    /// The kind of composite type (struct or union)
+    pub kind: CompositeKind,
+
+    /// Name of the imported type from C
+    /// This is optional because of anonymous types
+    pub c_name: Option<CName>,
+
+    /// Name of the type in Rust after import by bindgen
+    pub rust_name: String,
+
+    /// List of known aliases for the type
+    pub aliases: BTreeSet<String>,
+
+    /// The [NameMapping::aliases] in the order they were declared, see [AliasSelector::FirstDeclared]
+    ///
+    /// Aliases missing from it (e.g.: added by hand) come after the listed ones
+    pub alias_order: Vec<String>,
+
+    /// Position of the type among the discovered items (see [NameMappings::discovered_items]),
+    /// used to keep the declaration order, see [crate::export::RenameOrder::Declaration]
+    pub order: usize,
+
+    /// Path of C++ namespaces the type is declared in, outermost first
+    /// Anonymous namespaces are not part of the path. Always empty for C types
+    pub namespace: Vec<String>,
+
+    /// Documentation comment of the type in C, see [DiscoveryOptions::capture_docs]
+    pub doc: Option<String>,
+
+    /// Header the type comes from, see [DiscoveryOptions::capture_sources]
+    pub source: Option<PathBuf>,
+
+    /// Template the C++ type is an instantiation of, e.g.: `Box` for `Box<int>`, see [NameMapping::is_monomorphization]
+    pub generic_base: Option<String>,
+}
+
+impl NameMapping {
+    /// Figures out the original name in the C code based on the type and its name
+    ///
+    /// the name of a struct named A is "struct A"
+    /// the name of an union named B is "union B"
+    ///
+    /// If the passed name is an alias, keep it that way
+    pub fn validated_original_name(c_name: Option<&CName>, kind: CompositeKind) -> Option<String> {
+        Self::validated_original_name_in(c_name, kind, Language::C)
+    }
+
+    /// Same as [NameMapping::validated_original_name] but for the given [Language]
+    ///
+    /// In [Language::Cxx], the name is left as-is since C++ types can be used without their tag
+    pub fn validated_original_name_in(
+        c_name: Option<&CName>,
+        kind: CompositeKind,
+        language: Language,
+    ) -> Option<String> {
+        Self::validated_original_name_with(c_name, kind, &language.into())
+    }
+
+    /// Same as [NameMapping::validated_original_name] but with custom [TagPrefixes]
+    pub fn validated_original_name_with(
+        c_name: Option<&CName>,
+        kind: CompositeKind,
+        prefixes: &TagPrefixes,
+    ) -> Option<String> {
+        let original_name = &c_name?.identifier;
+
+        // do not prepend the prefix to an aliased type, unless asked to
+        if c_name?.aliased && !prefixes.tag_aliased_names {
+            return Some(original_name.clone());
+        }
+
+        // drop whatever tag the name already has so that only the one of the actual kind remains,
+        // followed by any whitespace run (`struct  Foo`, `struct\tFoo`) when the prefix ends with a space
+        let prefix = prefixes.get(kind);
+        let keyword = prefix.trim_end();
+        let original_name = original_name.trim();
+        let ignore_case = prefixes.case_insensitive_tags;
+        let untagged = match strip_keyword(original_name, keyword, ignore_case) {
+            Some(rest)
+                if !keyword.is_empty()
+                    && (keyword == prefix || rest.starts_with(char::is_whitespace)) =>
+            {
+                rest
+            }
+            _ => original_name,
+        };
+
+        Some(format!(
+            "{prefix}{}",
+            Self::strip_tag_keywords_with(untagged, ignore_case)
+        ))
+    }
+
+    /// Removes the leading `struct`, `union` and `enum` tag keywords of a C name
+    fn strip_tag_keywords(name: &str) -> &str {
+        Self::strip_tag_keywords_with(name, false)
+    }
+
+    /// Same as [NameMapping::strip_tag_keywords], optionally ignoring the case of the keywords
+    fn strip_tag_keywords_with(mut name: &str, ignore_case: bool) -> &str {
+        while let Some(rest) = TAG_KEYWORDS.iter().find_map(|keyword| {
+            strip_keyword(name.trim_start(), keyword, ignore_case)
+                .filter(|rest| rest.starts_with(char::is_whitespace))
+        }) {
+            name = rest;
+        }
+
+        name.trim_start()
+    }
+
+    /// [NameMapping::c_name] prefixed with the [NameMapping::namespace] path joined using the given [NamespaceStyle]
+    ///
+    /// Aliased names are left as-is
+    pub fn namespaced_c_name(&self, style: NamespaceStyle) -> Option<CName> {
+        let c_name = self.c_name.as_ref()?;
+        if c_name.aliased || self.namespace.is_empty() {
+            return Some(c_name.clone());
+        }
+
+        let separator = style.separator();
+        Some(CName {
+            identifier: format!(
+                "{}{separator}{}",
+                self.namespace.join(separator),
+                c_name.identifier
+            ),
+            aliased: false,
+        })
+    }
+
+    /// Only keep one of the aliases that only differ by their case
+    ///
+    /// The canonical casing is the one of the aliased [NameMapping::c_name] if any,
+    /// or the first one in the [BTreeSet] order otherwise (`FOO` before `Foo` before `foo`)
+    pub fn dedup_aliases_ignoring_case(&mut self) {
+        let mut seen: BTreeSet<String> = self
+            .c_name
+            .iter()
+            .filter(|c_name| c_name.aliased)
+            .map(|c_name| c_name.identifier.to_lowercase())
+            .collect();
+
+        self.aliases
+            .retain(|alias| seen.insert(alias.to_lowercase()));
+        self.alias_order
+            .retain(|alias| self.aliases.contains(alias));
+    }
+
+    /// The C name this type is renamed to in C
+    ///
+    /// One of the [NameMapping::aliases] (see [AliasSelector]) if the type is anonymous or if `force_aliases_use`
+    /// is set and it has aliases, its [NameMapping::validated_original_name] otherwise
+    pub fn resolved_c_name(
+        &self,
+        force_aliases_use: bool,
+        selector: &AliasSelector,
+    ) -> Option<String> {
+        self.resolved_c_name_with(
+            force_aliases_use,
+            selector,
+            NamespaceStyle::default(),
+            &TagPrefixes::default(),
+        )
+    }
+
+    /// Same as [NameMapping::resolved_c_name] with the given [NamespaceStyle] and [TagPrefixes]
+    pub fn resolved_c_name_with(
+        &self,
+        force_aliases_use: bool,
+        selector: &AliasSelector,
+        namespace_style: NamespaceStyle,
+        prefixes: &TagPrefixes,
+    ) -> Option<String> {
+        if self.c_name.is_none() || (force_aliases_use && !self.aliases.is_empty()) {
+            self.selected_alias(selector).cloned()
+        } else {
+            NameMapping::validated_original_name_with(
+                self.namespaced_c_name(namespace_style).as_ref(),
+                self.kind,
+                prefixes,
+            )
+        }
+    }
+
+    /// The C name given by the first of the `sources` yielding one, tried in order,
+    /// with the given [NamespaceStyle] and [TagPrefixes] for [NameSource::Original]
+    ///
+    /// `None` if none of them does, e.g.: for an anonymous type without alias
+    pub fn resolved_c_name_from(
+        &self,
+        sources: &[NameSource],
+        namespace_style: NamespaceStyle,
+        prefixes: &TagPrefixes,
+    ) -> Option<String> {
+        sources.iter().find_map(|source| match source {
+            NameSource::Original => NameMapping::validated_original_name_with(
+                self.namespaced_c_name(namespace_style).as_ref(),
+                self.kind,
+                prefixes,
+            ),
+            NameSource::Alias(selector) => self.selected_alias(selector).cloned(),
+            NameSource::AliasMatching(regex) => self
+                .aliases
+                .iter()
+                .find(|alias| regex.is_match(alias))
+                .cloned(),
+        })
+    }
+
+    /// Whether the type is an instantiation of a C++ template (`Box<int>`), whose name cbindgen can't rename,
+    /// see [NameMapping::generic_base] and [MappingsCodegen::keep_monomorphizations]
+    pub fn is_monomorphization(&self) -> bool {
+        self.generic_base.is_some()
+    }
+
+    /// Whether the type has no name of its own in C, e.g.: `typedef struct { .. } foo_t;`
+    ///
+    /// True for the anonymous types named after an alias (see [NameMapping::is_aliased_name])
+    /// as well as for the ones left without a name
+    pub fn is_anonymous(&self) -> bool {
+        !matches!(&self.c_name, Some(c_name) if !c_name.aliased)
+    }
+
+    /// Whether the [NameMapping::c_name] is an alias of the type rather than its own name, see [CName::aliased]
+    ///
+    /// False for the anonymous types left without a name
+    pub fn is_aliased_name(&self) -> bool {
+        self.c_name.as_ref().is_some_and(|c_name| c_name.aliased)
+    }
+
+    /// The alias selected by the `selector` among the [NameMapping::aliases]
+    ///
+    /// Same as [AliasSelector::select], but knows the declaration order for [AliasSelector::FirstDeclared]
+    pub fn selected_alias(&self, selector: &AliasSelector) -> Option<&String> {
+        match selector {
+            AliasSelector::FirstDeclared => self
+                .alias_order
+                .iter()
+                .find(|alias| self.aliases.contains(*alias))
+                .or_else(|| selector.select(&self.aliases)),
+            selector => selector.select(&self.aliases),
+        }
+    }
+
+    /// Every C spelling of the type, sorted and without duplicates: its [NameMapping::validated_original_name]
+    /// (`struct bmp_peer`, or the alias naming an anonymous type) and all its [NameMapping::aliases]
+    /// e.g.: `["bmp_peer_t", "peer_t", "struct bmp_peer"]`
+    pub fn all_c_names(&self) -> Vec<String> {
+        let original = NameMapping::validated_original_name(self.c_name.as_ref(), self.kind);
+        let names: BTreeSet<String> = original
+            .into_iter()
+            .chain(self.aliases.iter().cloned())
+            .collect();
+
+        names.into_iter().collect()
+    }
+
+    /// The key used in the rename rules for this type
+    ///
+    /// cbindgen expects the bare identifier, so raw identifiers (`r#type`) lose their `r#` prefix.
+    /// [NameMapping::rust_name] is left untouched
+    pub fn rename_key(&self) -> &str {
+        self.rust_name.strip_prefix("r#").unwrap_or(&self.rust_name)
+    }
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct NameMappings {
+    /// The discovered types and their mappings
+    pub types: HashMap<DiscoveredItemId, NameMapping>,
+
+    /// The known aliases without an associated type mappings, in discovery order
+    pub aliases: HashMap<DiscoveredItemId, Vec<String>>,
+
+    /// Language of the parsed headers, used to address the types in the generated rename rules
+    pub language: Language,
+
+    /// The item targeted by each discovered alias, used to follow typedef-of-typedef chains
+    pub alias_targets: HashMap<DiscoveredItemId, DiscoveredItemId>,
+
+    /// Number of ids minted by the crate for the types it added itself, see [SYNTHETIC_IDS_START]
+    pub minted_ids: usize,
+
+    /// The aliases found for two different types, see [DiscoveryOptions::alias_conflicts]
+    pub alias_conflicts: Vec<AliasConflict>,
+
+    /// Number of [ConfiguredNameMappingsCallback] made for these mappings, one per bindgen run
+    pub discovery_runs: usize,
+
+    /// The C names of the discovered functions by Rust name, see [DiscoveryOptions::capture_functions]
+    pub functions: BTreeMap<String, String>,
+
+    /// The C names of the discovered constants and global variables by Rust name,
+    /// see [DiscoveryOptions::capture_constants]
+    pub constants: BTreeMap<String, String>,
+
+    /// Number of items reported by bindgen to the callbacks, ignored ones included
+    pub discovered_items: usize,
+
+    /// Number of items ignored by the discovery for each kind the crate doesn't handle (yet),
+    /// named after their [DiscoveredItem] variant
+    pub ignored_items: BTreeMap<String, usize>,
+
+    /// The aliases dropped from each type during the discovery, see [DiscoveryOptions::record_rejected_aliases]
+    pub rejected_aliases: HashMap<DiscoveredItemId, BTreeSet<String>>,
+
+    /// The types used by the fields of each type, see [DiscoveryOptions::capture_dependencies]
+    pub dependencies: HashMap<DiscoveredItemId, BTreeSet<DiscoveredItemId>>,
+
+    /// The field references not resolved yet: the Rust name of the type holding
+    /// the field and the C name of the field's type, see [NameMappings::resolve_field_references]
+    pub pending_field_references: Vec<(String, String)>,
+
+    /// The types dropped by the allow and deny lists, see [DiscoveryOptions::allow],
+    /// whose aliases are dropped too even when they are found after the type
+    pub denied_types: BTreeSet<DiscoveredItemId>,
+
+    /// The type holding each alias, kept up to date by the discovery to find the alias conflicts
+    /// without scanning every type, see [AliasHolders]
+    pub alias_holders: AliasHolders,
+}
+
+/// Index of the type holding each alias, see [NameMappings::alias_holders]
+///
+/// This is a cache: it is ignored by the comparisons, its entries are checked against the mappings
+/// before use, and it is rebuilt on first use for the mappings made by hand or merged
+#[derive(Debug, Default, Clone)]
+pub struct AliasHolders {
+    holders: HashMap<String, DiscoveredItemId>,
+    synced: bool,
+}
+
+impl PartialEq for AliasHolders {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for AliasHolders {}
+
+/// What to do when an alias is found for two different types, see [DiscoveryOptions::alias_conflicts]
+/// e.g.: two headers each `typedef`ing `handle_t` to a different struct
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AliasConflictPolicy {
+    /// Keep the alias on the type it was found for first
+    #[default]
+    KeepFirst,
+
+    /// Move the alias to the type it was found for last
+    KeepLast,
+
+    /// Keep the alias on the first type, but fail the rename rules generation with [Error::AliasConflict]
+    Error,
+}
+
+/// An alias found for two different types, see [NameMappings::alias_conflicts]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AliasConflict {
+    /// The conflicting alias
+    pub alias: String,
+
+    /// The type the alias was found for first
+    pub first: DiscoveredItemId,
+
+    /// The type the alias was found for afterward
+    pub second: DiscoveredItemId,
+
+    /// The policy applied to the conflict
+    pub policy: AliasConflictPolicy,
+}
+
+/// A conflict found while building a static map, see [NameMappings::to_static_map_checked]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapConflict {
+    /// Several types have the same key, left out of the map
+    DuplicateKey {
+        /// The shared key
+        key: String,
+
+        /// The Rust names of the types, sorted
+        rust_names: Vec<String>,
+    },
+
+    /// Several keys are renamed to the same C name, kept in the map
+    DuplicateTarget {
+        /// The shared C name
+        c_name: String,
+
+        /// The keys renamed to it, sorted
+        keys: Vec<String>,
+    },
+}
+
+/// First [DiscoveredItemId] minted by the crate for the types it adds itself,
+/// e.g.: with [NameMappings::insert] or [NameMappings::from_static_map_source]
+///
+/// bindgen's ids are small increasing integers, so the crate's ones start high to never collide with them
+pub const SYNTHETIC_IDS_START: usize = (u32::MAX / 2) as usize;
+
+impl NameMappings {
+    /// Empty mappings with room for `expected_types` types (and as many aliases) without reallocating
+    ///
+    /// This is a best-effort performance hint for large headers, the mappings still grow past it if needed
+    pub fn with_capacity(expected_types: usize) -> Self {
+        Self {
+            types: HashMap::with_capacity(expected_types),
+            aliases: HashMap::with_capacity(expected_types),
+            alias_targets: HashMap::with_capacity(expected_types),
+            ..Default::default()
+        }
+    }
+
+    /// The aliases whose target was not discovered (yet), in discovery order,
+    /// e.g.: typedefs of types blocked from the bindings
+    ///
+    /// Unlike [NameMappings::forget_unused_aliases], the aliases are kept
+    pub fn pending_aliases(&self) -> &HashMap<DiscoveredItemId, Vec<String>> {
+        &self.aliases
+    }
+
+    /// Whether some aliases are still waiting for their target, see [NameMappings::pending_aliases]
+    pub fn has_pending_aliases(&self) -> bool {
+        self.aliases.values().any(|cached| !cached.is_empty())
+    }
+
+    /// Mints a new id for a type added by the crate, the `n`th one is `SYNTHETIC_IDS_START + n`
+    ///
+    /// This is the only place where such ids are made, see [SYNTHETIC_IDS_START]
+    fn mint_id(&mut self) -> DiscoveredItemId {
+        let id = DiscoveredItemId::new(SYNTHETIC_IDS_START + self.minted_ids);
+        self.minted_ids += 1;
+        id
+    }
+
+    /// Whether the id was minted by these mappings instead of discovered by bindgen
+    pub fn is_synthetic_id(&self, id: DiscoveredItemId) -> bool {
+        id >= DiscoveredItemId::new(SYNTHETIC_IDS_START)
+            && id < DiscoveredItemId::new(SYNTHETIC_IDS_START + self.minted_ids)
+    }
+
+    /// Adds a type that was not discovered by bindgen, under a new synthetic id which is returned
+    pub fn insert(&mut self, mapping: NameMapping) -> DiscoveredItemId {
+        let id = self.mint_id();
+        self.types.insert(id, mapping);
+        self.index_aliases(id);
+        id
+    }
+
+    /// Adds the types and aliases of `other` to these mappings
+    ///
+    /// The ids discovered by bindgen are kept as-is, so a type of `other` replaces the one with the same id.
+    /// The synthetic ids of `other` (see [NameMappings::insert]) are minted again by these mappings,
+    /// so they stay in the synthetic range without colliding with the ones already here
+    pub fn merge(&mut self, other: NameMappings) {
+        let mut synthetic: Vec<DiscoveredItemId> = other
+            .types
+            .keys()
+            .copied()
+            .filter(|id| other.is_synthetic_id(*id))
+            .collect();
+        synthetic.sort();
+        let remapped: HashMap<DiscoveredItemId, DiscoveredItemId> = synthetic
+            .into_iter()
+            .map(|id| (id, self.mint_id()))
+            .collect();
+        let remap = |id: DiscoveredItemId| remapped.get(&id).copied().unwrap_or(id);
+
+        for (id, mapping) in other.types {
+            self.types.insert(remap(id), mapping);
+        }
+
+        for (id, cached) in other.aliases {
+            for alias in cached {
+                self.attach_alias(remap(id), alias, AliasConflictPolicy::default());
+            }
+        }
+        self.alias_targets.extend(
+            other
+                .alias_targets
+                .into_iter()
+                .map(|(alias, target)| (alias, remap(target))),
+        );
+        self.functions.extend(other.functions);
+        self.constants.extend(other.constants);
+        for (id, rejected) in other.rejected_aliases {
+            self.rejected_aliases
+                .entry(remap(id))
+                .or_default()
+                .extend(rejected);
+        }
+        for (id, dependencies) in other.dependencies {
+            self.dependencies
+                .entry(remap(id))
+                .or_default()
+                .extend(dependencies.into_iter().map(remap));
+        }
+        self.pending_field_references
+            .extend(other.pending_field_references);
+        self.denied_types
+            .extend(other.denied_types.into_iter().map(remap));
+        self.alias_holders.synced = false;
+    }
+
+    /// The types used by the type `id`, directly or through other types, in id order,
+    /// see [DiscoveryOptions::capture_dependencies]
+    ///
+    /// The type itself is only part of them if it uses itself (e.g.: a linked list node)
+    pub fn dependencies_of(&self, id: DiscoveredItemId) -> BTreeSet<DiscoveredItemId> {
+        let mut reached = BTreeSet::new();
+        let mut queue = vec![id];
+        while let Some(current) = queue.pop() {
+            for dependency in self.dependencies.get(&current).into_iter().flatten() {
+                if reached.insert(*dependency) {
+                    queue.push(*dependency);
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// Turns the [NameMappings::pending_field_references] whose types are both discovered into
+    /// [NameMappings::dependencies], the other ones stay pending
+    ///
+    /// The type holding the field is found by its Rust name, and the type of the field by its C name,
+    /// one of its aliases or its Rust name. The lowest id is kept when several types share the name.
+    /// Called once the discovery is over, when the callback is dropped
+    pub fn resolve_field_references(&mut self) {
+        if self.pending_field_references.is_empty() {
+            return;
+        }
+
+        fn index<'a>(
+            names: &mut HashMap<&'a str, DiscoveredItemId>,
+            name: &'a str,
+            id: DiscoveredItemId,
+        ) {
+            names
+                .entry(name)
+                .and_modify(|known| *known = (*known).min(id))
+                .or_insert(id);
+        }
+
+        let mut by_rust_name = HashMap::new();
+        let mut by_c_name = HashMap::new();
+        for (id, mapping) in &self.types {
+            index(&mut by_rust_name, &mapping.rust_name, *id);
+            index(&mut by_c_name, &mapping.rust_name, *id);
+            if let Some(c_name) = &mapping.c_name {
+                index(&mut by_c_name, &c_name.identifier, *id);
+            }
+            for alias in &mapping.aliases {
+                index(&mut by_c_name, alias, *id);
+            }
+        }
+
+        let mut pending = Vec::new();
+        let mut resolved = Vec::new();
+        for (holder, field_type) in std::mem::take(&mut self.pending_field_references) {
+            match (
+                by_rust_name.get(holder.as_str()),
+                by_c_name.get(field_type.as_str()),
+            ) {
+                (Some(&holder), Some(&used)) => resolved.push((holder, used)),
+                _ => pending.push((holder, field_type)),
+            }
+        }
+
+        for (holder, used) in resolved {
+            self.dependencies.entry(holder).or_default().insert(used);
+        }
+        self.pending_field_references = pending;
+    }
+
+    /// Forget the type `id` dropped by the allow and deny lists, along with the aliases already found for it
+    fn deny_type(&mut self, id: DiscoveredItemId) {
+        self.types.remove(&id);
+        self.aliases.remove(&id);
+        self.denied_types.insert(id);
+    }
+
+    /// Moves what is known about the type `from` to the type `into`, when both turn out to be the same type
+    ///
+    /// The pending aliases of `from` are attached to `into`, and the conflicts between them are forgotten
+    fn fold_id(
+        &mut self,
+        from: DiscoveredItemId,
+        into: DiscoveredItemId,
+        policy: AliasConflictPolicy,
+    ) {
+        for target in self.alias_targets.values_mut() {
+            if *target == from {
+                *target = into;
+            }
+        }
+        self.alias_conflicts.retain(|conflict| {
+            let pair = (conflict.first, conflict.second);
+            pair != (from, into) && pair != (into, from)
+        });
+
+        for alias in self.aliases.remove(&from).unwrap_or_default() {
+            self.attach_alias(into, alias, policy);
+        }
+        if let Some(rejected) = self.rejected_aliases.remove(&from) {
+            self.rejected_aliases
+                .entry(into)
+                .or_default()
+                .extend(rejected);
+        }
+        if let Some(dependencies) = self.dependencies.remove(&from) {
+            self.dependencies
+                .entry(into)
+                .or_default()
+                .extend(dependencies);
+        }
+        for dependencies in self.dependencies.values_mut() {
+            if dependencies.remove(&from) {
+                dependencies.insert(into);
+            }
+        }
+    }
+
+    /// Turns the pending aliases (see [NameMappings::pending_aliases]) into [CompositeKind::Typedef] types
+    /// renamed to themselves, e.g.: `"as_t" = "as_t"` for `typedef uint32_t as_t;`.
+    /// Returns the number of typedefs added
+    ///
+    /// bindgen only reports the composite types, so the typedefs of primitive types are the aliases whose
+    /// target is never discovered. The pending aliases of the types dropped by the allow and deny lists
+    /// (see [NameMappings::denied_types]) are forgotten instead. Call this once the discovery is done,
+    /// since the typedefs of the composite types found later are pending until then
+    pub fn capture_pending_typedefs(&mut self) -> usize {
+        let mut pending: Vec<(DiscoveredItemId, Vec<String>)> = self.aliases.drain().collect();
+        pending.retain(|(id, _)| {
+            let target = self.resolve_alias_target(*id).unwrap_or(*id);
+            !self.denied_types.contains(&target)
+        });
+        pending.sort();
+
+        let mut count = 0;
+        for alias in pending.into_iter().flat_map(|(_, cached)| cached) {
+            self.insert(NameMapping {
+                kind: CompositeKind::Typedef,
+                c_name: Some(CName {
+                    identifier: alias.clone(),
+                    aliased: true,
+                }),
+                rust_name: alias,
+                aliases: BTreeSet::new(),
+                alias_order: Vec::new(),
+                // after the discovered types
+                order: self.discovered_items + count,
+                namespace: Vec::new(),
+                doc: None,
+                source: None,
+                generic_base: None,
+            });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Drain the temporary alias cache
+    pub fn forget_unused_aliases(&mut self) -> usize {
+        self.aliases.drain().map(|(_, cached)| cached.len()).sum()
+    }
+
+    /// Names the anonymous types having aliases after the one picked by the `selector`, as the discovery
+    /// does with the first alias it finds. Returns the number of types named
+    ///
+    /// For mappings loaded, merged or assembled by hand, whose anonymous types kept all their aliases
+    pub fn resolve_anonymous_names(&mut self, selector: &AliasSelector) -> usize {
+        let mut count = 0;
+        for mapping in self.types.values_mut() {
+            if mapping.c_name.is_some() {
+                continue;
+            }
+            let Some(alias) = mapping.selected_alias(selector).cloned() else {
+                continue;
+            };
+
+            mapping.aliases.remove(&alias);
+            mapping.alias_order.retain(|declared| *declared != alias);
+            mapping.c_name = Some(CName {
+                identifier: alias,
+                aliased: true,
+            });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Follows the chain of aliases starting at `id` up to the aliased item
+    ///
+    /// e.g.: with `typedef struct Foo Bar; typedef Bar Baz;`, `Baz` resolves to `struct Foo`.
+    /// Returns `None` if the chain loops
+    pub fn resolve_alias_target(&self, id: DiscoveredItemId) -> Option<DiscoveredItemId> {
+        let mut visited = BTreeSet::new();
+        let mut current = id;
+
+        while let Some(target) = self.alias_targets.get(&current) {
+            if !visited.insert(current) {
+                return None;
+            }
+            current = *target;
+        }
+
+        Some(current)
+    }
+
+    /// Whether the type `id` holds the alias, as an alias or as its aliased name, or as a pending alias
+    fn holds_alias(&self, id: DiscoveredItemId, alias: &str) -> bool {
+        match self.types.get(&id) {
+            Some(mapping) => {
+                mapping.aliases.contains(alias)
+                    || mapping
+                        .c_name
+                        .as_ref()
+                        .is_some_and(|c_name| c_name.aliased && c_name.identifier == alias)
+            }
+            None => self
+                .aliases
+                .get(&id)
+                .is_some_and(|cached| cached.iter().any(|cached| cached == alias)),
+        }
+    }
+
+    /// Index the aliases held by the type `id`, see [NameMappings::alias_holders]
+    fn index_aliases(&mut self, id: DiscoveredItemId) {
+        let held: Vec<String> = match self.types.get(&id) {
+            Some(mapping) => mapping
+                .c_name
+                .iter()
+                .filter(|c_name| c_name.aliased)
+                .map(|c_name| c_name.identifier.clone())
+                .chain(mapping.aliases.iter().cloned())
+                .collect(),
+            None => self.aliases.get(&id).cloned().unwrap_or_default(),
+        };
+        for alias in held {
+            self.alias_holders.holders.insert(alias, id);
+        }
+    }
+
+    /// The type holding the alias, among the known types (as an alias or as its aliased name)
+    /// and the pending aliases
+    fn alias_holder(&mut self, alias: &str) -> Option<DiscoveredItemId> {
+        if !self.alias_holders.synced {
+            self.alias_holders.holders.clear();
+            // the known types win over the pending aliases
+            let ids: Vec<DiscoveredItemId> = self
+                .aliases
+                .keys()
+                .chain(self.types.keys())
+                .copied()
+                .collect();
+            for id in ids {
+                self.index_aliases(id);
+            }
+            self.alias_holders.synced = true;
+        }
+
+        // the removed aliases are left in the index, hence the check
+        self.alias_holders
+            .holders
+            .get(alias)
+            .copied()
+            .filter(|id| self.holds_alias(*id, alias))
+    }
+
+    /// Forget the alias of the type `holder_id`, an anonymous type named after it takes its first declared
+    /// remaining alias, like the discovery names it after its first found alias
+    fn detach_alias(&mut self, holder_id: DiscoveredItemId, alias: &str) {
+        if let Some(mapping) = self.types.get_mut(&holder_id) {
+            mapping.aliases.remove(alias);
+            mapping.alias_order.retain(|declared| declared != alias);
+            if mapping
+                .c_name
+                .as_ref()
+                .is_some_and(|c_name| c_name.aliased && c_name.identifier == alias)
+            {
+                let promoted = mapping
+                    .selected_alias(&AliasSelector::FirstDeclared)
+                    .cloned();
+                if let Some(promoted) = &promoted {
+                    mapping.aliases.remove(promoted);
+                    mapping.alias_order.retain(|declared| declared != promoted);
+                }
+                mapping.c_name = promoted.map(|identifier| CName {
+                    identifier,
+                    aliased: true,
+                });
+            }
+        } else if let Some(cached) = self.aliases.get_mut(&holder_id) {
+            cached.retain(|cached| cached != alias);
+        }
+    }
+
+    /// Remember an alias of the type `target_id`, or keep it for later if the type is not known yet
+    ///
+    /// An alias already held by another type is a conflict, handled according to the `policy` and returned
+    fn attach_alias(
+        &mut self,
+        target_id: DiscoveredItemId,
+        aliased_name: String,
+        policy: AliasConflictPolicy,
+    ) -> Option<AliasConflict> {
+        let mut conflict = None;
+        if let Some(holder_id) = self
+            .alias_holder(&aliased_name)
+            .filter(|holder_id| *holder_id != target_id)
+        {
+            let found = AliasConflict {
+                alias: aliased_name.clone(),
+                first: holder_id,
+                second: target_id,
+                policy,
+            };
+            self.alias_conflicts.push(found.clone());
+
+            match policy {
+                AliasConflictPolicy::KeepFirst | AliasConflictPolicy::Error => return Some(found),
+                AliasConflictPolicy::KeepLast => self.detach_alias(holder_id, &aliased_name),
+            }
+            conflict = Some(found);
+        }
+
+        if let Some(mapping) = self.types.get_mut(&target_id) {
+            // if the type was anonymous let's use one of its aliases as a name
+            if mapping.c_name.is_none() {
+                mapping.c_name = Some(CName {
+                    identifier: aliased_name.clone(),
+                    aliased: true,
+                });
+            }
+            // if it wasn't, remember the alias
+            else if mapping.aliases.insert(aliased_name.clone()) {
+                mapping.alias_order.push(aliased_name.clone());
+            }
+        } else {
+            let cached = self.aliases.entry(target_id).or_default();
+            if !cached.contains(&aliased_name) {
+                cached.push(aliased_name.clone());
+            }
+        };
+        self.alias_holders.holders.insert(aliased_name, target_id);
+
+        conflict
+    }
+
+    /// The [TagPrefixes] to use: none with [RenameOptions::no_tag_prefix],
+    /// the ones of the [RenameOptions] if set, or the ones of [NameMappings::language]
+    fn tag_prefixes(&self, options: &RenameOptions) -> TagPrefixes {
+        if options.no_tag_prefix {
+            return TagPrefixes::none();
+        }
+
+        options
+            .tag_prefixes
+            .clone()
+            .unwrap_or_else(|| self.language.into())
+    }
+
+    /// The rename rules of the mappings: each type with the C name it is renamed to, sorted by key
+    ///
+    /// Types without a valid name are handled according to [RenameOptions::unnamed_types].
+    /// Fails with [Error::RenameKeyCollision] if two types end up with the same key,
+    /// or with [Error::AliasConflict] if an alias conflict was found with [AliasConflictPolicy::Error]
+    pub(crate) fn rename_rules(&self, options: &RenameOptions) -> Result<Vec<RenameRule<'_>>> {
+        self.rename_rules_reporting(options, None)
+    }
+
+    /// Stable names for the types that bindgen named `_bindgen_ty_N`, see [MappingsCodegen::stable_anonymous_names]
+    /// and [NameMappings::to_stable_names_toml]
+    ///
+    /// Each type is named `<prefix><kind>_<index>` (`anon_struct_0`, `anon_enum_class_1`), with the index of the type
+    /// among the ones of its kind in declaration order (see [NameMapping::order]), starting from 0
+    pub fn stable_anonymous_names(&self, prefix: &str) -> HashMap<DiscoveredItemId, String> {
+        let mut anonymous: Vec<(&DiscoveredItemId, &NameMapping)> = self
+            .types
+            .iter()
+            .filter(|(_, mapping)| mapping.rust_name.starts_with("_bindgen_ty_"))
+            .collect();
+        anonymous.sort_by_key(|(id, mapping)| (mapping.kind, mapping.order, **id));
+
+        let mut counts: BTreeMap<CompositeKind, usize> = BTreeMap::new();
+        anonymous
+            .into_iter()
+            .map(|(id, mapping)| {
+                let index = counts.entry(mapping.kind).or_default();
+                let kind = mapping.kind.to_string().replace(' ', "_");
+                let name = format!("{prefix}{kind}_{index}");
+                *index += 1;
+                (*id, name)
+            })
+            .collect()
+    }
+
+    /// The `"<stable name>" = "_bindgen_ty_N"` lookup table of the [NameMappings::stable_anonymous_names],
+    /// sorted by stable name, e.g.: `"anon_struct_0" = "_bindgen_ty_3"`
+    pub fn to_stable_names_toml(&self, prefix: &str) -> Result<String> {
+        let stable_names = self.stable_anonymous_names(prefix);
+        value_renames(
+            stable_names
+                .iter()
+                .map(|(id, name)| (name.as_str(), self.types[id].rust_name.as_str())),
+        )
+    }
+
+    /// Same as [NameMappings::rename_rules], but the ids of the types left out by [UnnamedTypePolicy::Skip]
+    /// are pushed to `skipped` (if given) instead of being warned about
+    fn rename_rules_reporting(
+        &self,
+        options: &RenameOptions,
+        skipped: Option<&mut Vec<DiscoveredItemId>>,
+    ) -> Result<Vec<RenameRule<'_>>> {
+        let rules = self.unchecked_rename_rules(options, skipped)?;
+
+        // the keys may collide once stripped
+        if let Some(pair) = rules.windows(2).find(|pair| pair[0].key == pair[1].key) {
+            return Err(Error::RenameKeyCollision {
+                key: pair[0].key.clone(),
+                rust_names: [
+                    pair[0].mapping.rust_name.clone(),
+                    pair[1].mapping.rust_name.clone(),
+                ],
+            });
+        }
+
+        Ok(rules)
+    }
+
+    /// Same as [NameMappings::rename_rules_reporting], without failing on the colliding keys
+    fn unchecked_rename_rules(
+        &self,
+        options: &RenameOptions,
+        mut skipped: Option<&mut Vec<DiscoveredItemId>>,
+    ) -> Result<Vec<RenameRule<'_>>> {
+        if let Some(conflict) = self
+            .alias_conflicts
+            .iter()
+            .find(|conflict| conflict.policy == AliasConflictPolicy::Error)
+        {
+            return Err(Error::AliasConflict(conflict.alias.clone()));
+        }
+
+        let prefixes = self.tag_prefixes(options);
+        let mut rules = Vec::with_capacity(self.types.len());
+
+        for (id, mapping) in &self.types {
+            if options
+                .filter
+                .as_ref()
+                .is_some_and(|filter| !filter.test(mapping))
+            {
+                continue;
+            }
+            if mapping.is_monomorphization() && !options.keep_monomorphizations {
+                continue;
+            }
+
+            let force_aliases_use = match &options.force_aliases_when {
+                Some(predicate) => predicate.test(mapping),
+                None => options.force_aliases_use,
+            };
+            let use_name = if options.name_sources.is_empty() {
+                mapping.resolved_c_name_with(
+                    force_aliases_use,
+                    &options.alias_selector,
+                    options.namespace_style,
+                    &prefixes,
+                )
+            } else {
+                mapping.resolved_c_name_from(
+                    &options.name_sources,
+                    options.namespace_style,
+                    &prefixes,
+                )
+            };
+
+            if let Some(use_name) = use_name {
+                let use_name = match options.case_style {
+                    // convert the name but not its tag: `struct MyType` is `struct my_type`
+                    Some(style) => {
+                        let prefix = prefixes.get(mapping.kind);
+                        match use_name.strip_prefix(prefix) {
+                            Some(name) if !prefix.is_empty() => {
+                                format!("{prefix}{}", style.convert(name))
+                            }
+                            _ => style.convert(&use_name),
+                        }
+                    }
+                    None => use_name,
+                };
+                rules.push(RenameRule::new(mapping, options, use_name));
+                continue;
+            }
+
+            match &options.unnamed_types {
+                UnnamedTypePolicy::Skip => match skipped.as_deref_mut() {
+                    Some(skipped) => skipped.push(*id),
+                    None => eprintln!(
+                        "Warn: type with no valid name during rename export! id={:#?} info={:#?}",
+                        id, mapping
+                    ),
+                },
+                UnnamedTypePolicy::Error => {
+                    return Err(Error::UnnamedType(mapping.rust_name.clone()));
+                }
+                UnnamedTypePolicy::Synthesize(prefix) => {
+                    let number = mapping
+                        .rust_name
+                        .strip_prefix("_bindgen_ty_")
+                        .unwrap_or(&mapping.rust_name);
+                    let use_name = format!("{prefix}{number}");
+                    rules.push(RenameRule::new(mapping, options, use_name));
+                }
+            }
+        }
+
+        rules.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(rules)
+    }
+
+    /// The types whose C name changes with [RenameOptions::force_aliases_use], to review the impact of the setting
+    /// before flipping it: `(rust_name, name_without_force, name_with_force)` sorted by Rust name,
+    /// e.g.: `("bmp_peer", "struct bmp_peer", "bmp_peer_t")`
+    ///
+    /// The names are resolved with the default [RenameOptions], see [NameMappings::alias_use_impact_with]
+    pub fn alias_use_impact(&self) -> Vec<(String, String, String)> {
+        self.alias_use_impact_with(&RenameOptions::default())
+    }
+
+    /// Same as [NameMappings::alias_use_impact], with the names resolved using the alias selector, namespace style
+    /// and tag prefixes of the `options`, whatever their [RenameOptions::force_aliases_use]
+    pub fn alias_use_impact_with(&self, options: &RenameOptions) -> Vec<(String, String, String)> {
+        let prefixes = self.tag_prefixes(options);
+        let resolve = |mapping: &NameMapping, force_aliases_use| {
+            mapping.resolved_c_name_with(
+                force_aliases_use,
+                &options.alias_selector,
+                options.namespace_style,
+                &prefixes,
+            )
+        };
+
+        let mut impact: Vec<(String, String, String)> = self
+            .types
+            .values()
+            .filter_map(|mapping| {
+                let without_force = resolve(mapping, false)?;
+                let with_force = resolve(mapping, true)?;
+                (without_force != with_force)
+                    .then(|| (mapping.rust_name.clone(), without_force, with_force))
+            })
+            .collect();
+        impact.sort();
+
+        impact
+    }
+
+    /// The keys of the rename rules in the declaration order of their types (see [NameMapping::order]),
+    /// e.g.: for [crate::export::Template::declaration_order]
+    ///
+    /// Fails like [NameMappings::rename_rules]
+    pub fn declaration_order(&self, options: &RenameOptions) -> Result<Vec<String>> {
+        let mut rules = self.rename_rules(options)?;
+        rules.sort_by_key(|rule| rule.mapping.order);
+
+        Ok(rules.into_iter().map(|rule| rule.key).collect())
+    }
+
+    /// Generate a cbindgen.toml [export.rename] section, without the section header
+    ///
+    /// The documentation of the types ([NameMapping::doc]) is written as comments above their rule
+    ///
+    /// Fails with [Error::RenameKeyCollision] if two types have the same Rust name,
+    /// since the last rule would silently win
+    pub fn to_cbindgen_toml_renames(&self, force_aliases_use: bool) -> Result<String> {
+        self.to_cbindgen_toml_renames_with(&RenameOptions {
+            force_aliases_use,
+            ..Default::default()
+        })
+    }
+
+    /// Same as [NameMappings::to_cbindgen_toml_renames] with all the [RenameOptions]
+    pub fn to_cbindgen_toml_renames_with(&self, options: &RenameOptions) -> Result<String> {
+        toml_renames(&self.rename_rules(options)?)
+    }
+
+    /// Generate the cbindgen.toml [export.rename] rules of the functions, without the section header
+    ///
+    /// Only the functions renamed by bindgen (Rust name different from the C name) are written, sorted by key.
+    /// Like for the types, raw identifiers (`r#type`) lose their `r#` prefix in the keys.
+    /// Empty unless the functions were captured, see [DiscoveryOptions::capture_functions]
+    pub fn to_cbindgen_fn_renames(&self) -> Result<String> {
+        value_renames(
+            self.functions
+                .iter()
+                .map(|(rust_name, c_name)| (rust_name.as_str(), c_name.as_str())),
+        )
+    }
+
+    /// Generate the cbindgen.toml [export.rename] rules of the constants and global variables,
+    /// without the section header, cbindgen renames them like the types
+    ///
+    /// Same rules as [NameMappings::to_cbindgen_fn_renames]: only the renamed ones are written, sorted by key.
+    /// Empty unless the constants were captured, see [DiscoveryOptions::capture_constants]
+    pub fn to_cbindgen_constant_renames(&self) -> Result<String> {
+        value_renames(
+            self.constants
+                .iter()
+                .map(|(rust_name, c_name)| (rust_name.as_str(), c_name.as_str())),
+        )
+    }
+
+    /// Generates a [phf_codegen] static map from the mappings
+    ///
+    /// The documentation of the types ([NameMapping::doc]) can't be kept in the map
+    ///
+    /// With `force_aliases_use`, the types are renamed to their alias picked by [AliasSelector::First],
+    /// see [NameMapping::resolved_c_name]
+    ///
+    /// Fails with [Error::RenameKeyCollision] if two types have the same Rust name,
+    /// instead of building a map with duplicate keys
+    pub fn to_static_map(&self, force_aliases_use: bool) -> Result<Map<String>> {
+        self.to_static_map_with(&RenameOptions {
+            force_aliases_use,
+            ..Default::default()
+        })
+    }
+
+    /// Same as [NameMappings::to_static_map] with all the [RenameOptions]
+    pub fn to_static_map_with(&self, options: &RenameOptions) -> Result<Map<String>> {
+        Ok(static_map(&self.rename_rules(options)?))
+    }
+
+    /// Same as [NameMappings::to_static_map_with], also returning the ids of the anonymous types
+    /// left out of the map because they have no name, instead of warning about them
+    ///
+    /// The ids are sorted, and only reported with [UnnamedTypePolicy::Skip]
+    pub fn to_static_map_reporting(
+        &self,
+        options: &RenameOptions,
+    ) -> Result<(Map<String>, Vec<DiscoveredItemId>)> {
+        let mut skipped = Vec::new();
+        let rules = self.rename_rules_reporting(options, Some(&mut skipped))?;
+        skipped.sort();
+
+        Ok((static_map(&rules), skipped))
+    }
+
+    /// Same as [NameMappings::to_static_map_with], but the conflicts are reported instead of failing,
+    /// so that the map can still be built with a warning
+    ///
+    /// The types sharing a key ([MapConflict::DuplicateKey]) are all left out of the map, since none of them
+    /// can be picked over the others. The types renamed to the same C name ([MapConflict::DuplicateTarget])
+    /// are kept, they only prevent building the inverse map (see [MappingsCodegen::bidirectional])
+    pub fn to_static_map_checked(
+        &self,
+        options: &RenameOptions,
+    ) -> Result<(Map<String>, Vec<MapConflict>)> {
+        let rules = self.unchecked_rename_rules(options, None)?;
+
+        let mut by_key: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for rule in &rules {
+            by_key
+                .entry(&rule.key)
+                .or_default()
+                .push(&rule.mapping.rust_name);
+        }
+        let mut conflicts: Vec<MapConflict> = by_key
+            .into_iter()
+            .filter(|(_, rust_names)| rust_names.len() > 1)
+            .map(|(key, mut rust_names)| {
+                rust_names.sort();
+                MapConflict::DuplicateKey {
+                    key: key.to_string(),
+                    rust_names: rust_names.into_iter().map(str::to_string).collect(),
+                }
+            })
+            .collect();
+
+        let kept: Vec<RenameRule> = rules
+            .iter()
+            .filter(|rule| {
+                !conflicts.iter().any(|conflict| {
+                    matches!(conflict, MapConflict::DuplicateKey { key, .. } if *key == rule.key)
+                })
+            })
+            .cloned()
+            .collect();
+
+        let mut by_target: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for rule in &kept {
+            by_target
+                .entry(&rule.c_name)
+                .or_default()
+                .push(rule.key.clone());
+        }
+        conflicts.extend(
+            by_target
+                .into_iter()
+                .filter(|(_, keys)| keys.len() > 1)
+                .map(|(c_name, keys)| MapConflict::DuplicateTarget {
+                    c_name: c_name.to_string(),
+                    keys,
+                }),
+        );
+
+        Ok((static_map(&kept), conflicts))
+    }
+
+    /// Same as [NameMappings::to_static_map_with] but the values also hold the kind of the types:
+    /// `("struct foo", CKind::Struct)`, see [MappingsCodegen::with_kind]
+    pub fn to_static_kind_map_with(&self, options: &RenameOptions) -> Result<Map<String>> {
+        Ok(static_kind_map(&self.rename_rules(options)?))
+    }
+
+    /// Export the mappings as CSV with a `rust_name,c_name,kind,aliases` header
+    ///
+    /// Rows are sorted by Rust name, the C name is empty for anonymous types
+    /// and the aliases are separated by semicolons
+    pub fn to_csv(&self) -> String {
+        let mut mappings: Vec<&NameMapping> = self.types.values().collect();
+        mappings.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+
+        let mut result = String::from("rust_name,c_name,kind,aliases\n");
+        for mapping in mappings {
+            let c_name = NameMapping::validated_original_name_in(
+                mapping.c_name.as_ref(),
+                mapping.kind,
+                self.language,
+            )
+            .unwrap_or_default();
+            let aliases = mapping
+                .aliases
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(";");
+
+            let row = [
+                mapping.rust_name.as_str(),
+                &c_name,
+                &mapping.kind.to_string(),
+                &aliases,
+            ]
+            .map(csv_field)
+            .join(",");
+
+            result.push_str(&row);
+            result.push('\n');
+        }
+
+        result
+    }
+
+    /// C typedefs re-declaring every alias of the types, e.g.: `typedef struct Foo AliasOfFoo;`
+    ///
+    /// cbindgen's rename can only map a Rust name to one C name, these declarations can be added to
+    /// cbindgen's `after_includes` to keep the other aliases available.
+    /// One line per alias, sorted by Rust name then alias. Types without a C name are left out
+    pub fn to_typedef_declarations(&self) -> String {
+        let mut mappings: Vec<&NameMapping> = self.types.values().collect();
+        mappings.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+
+        let mut result = String::new();
+        for mapping in mappings {
+            let Some(c_name) = NameMapping::validated_original_name_in(
+                mapping.c_name.as_ref(),
+                mapping.kind,
+                self.language,
+            ) else {
+                continue;
+            };
+
+            for alias in &mapping.aliases {
+                result.push_str(&format!("typedef {c_name} {alias};\n"));
+            }
+        }
+
+        result
+    }
+
+    /// A standalone C header giving the Rust names to a pure C consumer, guarded by `BINDGEN_BRIDGE_RENAMES_H`
+    ///
+    /// One `typedef <C name> <Rust name>;` per type (e.g.: `typedef struct bmp_peer BmpPeer;`) sorted by Rust name,
+    /// then one `#define <Rust name> <C name>` per function renamed by bindgen. Raw identifiers lose their `r#`,
+    /// and the types without a C name or already known by their Rust name are left out.
+    /// See [NameMappings::to_typedef_declarations] for the aliases
+    pub fn to_c_header(&self) -> String {
+        const GUARD: &str = "BINDGEN_BRIDGE_RENAMES_H";
+
+        let mut typedefs: Vec<(&str, String)> = self
+            .types
+            .values()
+            .filter_map(|mapping| {
+                let c_name = NameMapping::validated_original_name_in(
+                    mapping.c_name.as_ref(),
+                    mapping.kind,
+                    self.language,
+                )?;
+                let rust_name = mapping.rename_key();
+                (c_name != rust_name).then_some((rust_name, c_name))
+            })
+            .collect();
+        typedefs.sort();
+
+        let mut defines: Vec<(&str, &str)> = self
+            .functions
+            .iter()
+            .map(|(rust_name, c_name)| {
+                (
+                    rust_name.strip_prefix("r#").unwrap_or(rust_name),
+                    c_name.as_str(),
+                )
+            })
+            .filter(|(rust_name, c_name)| rust_name != c_name)
+            .collect();
+        defines.sort();
+
+        let mut result = format!("#ifndef {GUARD}\n#define {GUARD}\n\n");
+        for (rust_name, c_name) in &typedefs {
+            result.push_str(&format!("typedef {c_name} {rust_name};\n"));
+        }
+        if !typedefs.is_empty() && !defines.is_empty() {
+            result.push('\n');
+        }
+        for (rust_name, c_name) in &defines {
+            result.push_str(&format!("#define {rust_name} {c_name}\n"));
+        }
+        result.push_str(&format!("\n#endif /* {GUARD} */\n"));
+
+        result
+    }
+
+    /// The types grouped by [NameMapping::source], the types without a known source are left out
+    pub fn by_source(&self) -> HashMap<PathBuf, Vec<&NameMapping>> {
+        let mut result: HashMap<PathBuf, Vec<&NameMapping>> = HashMap::new();
+        for mapping in self.types.values() {
+            if let Some(source) = &mapping.source {
+                result.entry(source.clone()).or_default().push(mapping);
+            }
+        }
+
+        result
+    }
+
+    /// Whether these mappings hold the same types as `other`, whatever their ids
+    /// e.g.: the same headers discovered by two bindgen runs, or mappings reloaded from a static map
+    ///
+    /// The types, functions and pending aliases are compared by value along with the [NameMappings::language].
+    /// Everything tied to the ids (alias targets, alias conflicts) and the discovery counters are ignored
+    pub fn semantically_eq(&self, other: &NameMappings) -> bool {
+        fn types(mappings: &NameMappings) -> Vec<&NameMapping> {
+            let mut types: Vec<&NameMapping> = mappings.types.values().collect();
+            types.sort_by(|a, b| {
+                let key = |mapping: &NameMapping| {
+                    let c_name = mapping
+                        .c_name
+                        .as_ref()
+                        .map(|c_name| c_name.identifier.clone());
+                    (mapping.rust_name.clone(), c_name, mapping.kind)
+                };
+                key(a).cmp(&key(b))
+            });
+            types
+        }
+        fn functions(mappings: &NameMappings) -> Vec<(&str, &str)> {
+            mappings
+                .functions
+                .iter()
+                .map(|(rust_name, c_name)| (rust_name.as_str(), c_name.as_str()))
+                .collect()
+        }
+        fn pending(mappings: &NameMappings) -> Vec<&Vec<String>> {
+            let mut pending: Vec<&Vec<String>> = mappings.aliases.values().collect();
+            pending.sort();
+            pending
+        }
+
+        self.language == other.language
+            && types(self) == types(other)
+            && functions(self) == functions(other)
+            && pending(self) == pending(other)
+    }
+
+    /// Compares these mappings with `other`, matching the types by their Rust name
+    ///
+    /// Types only present in `other` are added, types only present in `self` are removed,
+    /// and types whose kind, C name or aliases differ are changed. Each list is sorted by Rust name
+    pub fn diff(&self, other: &NameMappings) -> MappingDiff {
+        let by_rust_name = |mappings: &NameMappings| {
+            mappings
+                .types
+                .values()
+                .map(|mapping| (mapping.rust_name.clone(), mapping.clone()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let before = by_rust_name(self);
+        let mut after = by_rust_name(other);
+
+        let mut diff = MappingDiff::default();
+        for (rust_name, old) in before {
+            match after.remove(&rust_name) {
+                None => diff.removed.push(old),
+                Some(new) => {
+                    if old.kind != new.kind
+                        || old.c_name != new.c_name
+                        || old.aliases != new.aliases
+                    {
+                        diff.changed.push(MappingChange { old, new });
+                    }
+                }
+            }
+        }
+        diff.added.extend(after.into_values());
+
+        diff
+    }
+
+    /// Loads the mappings from the JSON Lines written by a [StreamingNameMappingsCallback]
+    ///
+    /// The items are discovered again in the order of the lines, so the aliases found before their type
+    /// are resolved the same way. The ids are the numbers of the file, not bindgen's.
+    /// Fails with [Error::Jsonl] on a line that isn't an item
+    #[cfg(feature = "json")]
+    pub fn from_jsonl<R: std::io::BufRead>(reader: R) -> Result<NameMappings> {
+        use bindgen::callbacks::ParseCallbacks;
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (id, item) = match serde_json::from_str(&line) {
+                Ok(JsonlItem::Struct {
+                    id,
+                    original_name,
+                    final_name,
+                }) => (
+                    id,
+                    DiscoveredItem::Struct {
+                        original_name,
+                        final_name,
+                    },
+                ),
+                Ok(JsonlItem::Union {
+                    id,
+                    original_name,
+                    final_name,
+                }) => (
+                    id,
+                    DiscoveredItem::Union {
+                        original_name,
+                        final_name,
+                    },
+                ),
+                Ok(JsonlItem::Alias {
+                    id,
+                    alias_name,
+                    alias_for,
+                }) => (
+                    id,
+                    DiscoveredItem::Alias {
+                        alias_name,
+                        alias_for: DiscoveredItemId::new(alias_for),
+                    },
+                ),
+                Err(error) => {
+                    return Err(Error::Jsonl {
+                        line: index + 1,
+                        reason: error.to_string(),
+                    })
+                }
+            };
+            callback.new_item_found(DiscoveredItemId::new(id), item);
+        }
+
+        Ok(mappings.take())
+    }
+
+    /// Same as [NameMappings::from_jsonl], from the file at `path`
+    #[cfg(feature = "json")]
+    pub fn from_jsonl_file<P: AsRef<Path>>(path: P) -> Result<NameMappings> {
+        Self::from_jsonl(std::io::BufReader::new(std::fs::File::open(path)?))
+    }
+
+    /// The mappings as JSON, see [NameMappings::from_json] to load them
+    ///
+    /// The ids are written as-is, along with [NameMappings::minted_ids], so the synthetic ids
+    /// (see [SYNTHETIC_IDS_START]) are kept by the loaded mappings and the next ones they mint don't collide.
+    /// Only the types, the aliases, the functions, the constants, the dependencies and the language are written,
+    /// not what the discovery reported about itself (e.g.: [NameMappings::alias_conflicts]).
+    /// Available with the `json` feature
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        let number = |id: &DiscoveredItemId| id_number(*id);
+        let json = JsonMappings {
+            language: self.language,
+            minted_ids: self.minted_ids,
+            discovery_runs: self.discovery_runs,
+            types: self
+                .types
+                .iter()
+                .map(|(id, mapping)| (number(id), mapping.clone()))
+                .collect(),
+            aliases: self
+                .aliases
+                .iter()
+                .map(|(id, aliases)| (number(id), aliases.clone()))
+                .collect(),
+            alias_targets: self
+                .alias_targets
+                .iter()
+                .map(|(alias, target)| (number(alias), number(target)))
+                .collect(),
+            functions: self.functions.clone(),
+            constants: self.constants.clone(),
+            dependencies: self
+                .dependencies
+                .iter()
+                .map(|(id, used)| (number(id), used.iter().map(number).collect()))
+                .collect(),
+        };
+
+        Ok(serde_json::to_string(&json).map_err(std::io::Error::from)?)
+    }
+
+    /// Loads the mappings written by [NameMappings::to_json], under the same ids
+    ///
+    /// Fails with [Error::Io] on invalid JSON
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<NameMappings> {
+        let json: JsonMappings = serde_json::from_str(json).map_err(std::io::Error::from)?;
+        let id = DiscoveredItemId::new;
+
+        Ok(NameMappings {
+            language: json.language,
+            minted_ids: json.minted_ids,
+            discovery_runs: json.discovery_runs,
+            types: json
+                .types
+                .into_iter()
+                .map(|(number, mapping)| (id(number), mapping))
+                .collect(),
+            aliases: json
+                .aliases
+                .into_iter()
+                .map(|(number, aliases)| (id(number), aliases))
+                .collect(),
+            alias_targets: json
+                .alias_targets
+                .into_iter()
+                .map(|(alias, target)| (id(alias), id(target)))
+                .collect(),
+            functions: json.functions,
+            constants: json.constants,
+            dependencies: json
+                .dependencies
+                .into_iter()
+                .map(|(number, used)| (id(number), used.into_iter().map(id).collect()))
+                .collect(),
+            // the alias holders are rebuilt on first use
+            ..Default::default()
+        })
+    }
+
+    /// Rebuilds minimal mappings from the source of a generated static map,
+    /// either the [NameMappings::to_static_map] / [CodegenOutput::StaticMap] code or a `phf_map!` invocation
+    ///
+    /// Only the Rust and C names are known: the kind is inferred from the tag of the C name
+    /// (`struct `, `union ` or `enum `), and C names without a tag are kept as-is as [CName::aliased] structs.
+    /// Maps generated [MappingsCodegen::with_kind] are not supported.
+    /// The types are ordered like the entries, see [NameMapping::order]
+    pub fn from_static_map_source(source: &str) -> Result<NameMappings> {
+        const STRING: &str = r#""((?:[^"\\]|\\.)*)""#;
+        let entry = Regex::new(&format!(
+            r"\(\s*{STRING}\s*,\s*{STRING}\s*\)|{STRING}\s*=>\s*{STRING}"
+        ))?;
+
+        let mut mappings = NameMappings::default();
+        for captures in entry.captures_iter(source) {
+            let mut strings = captures
+                .iter()
+                .skip(1)
+                .flatten()
+                .map(|m| unescape(m.as_str()));
+            let (Some(rust_name), Some(c_name)) = (strings.next(), strings.next()) else {
+                continue;
+            };
+
+            let tagged = [
+                (CompositeKind::Struct, "struct "),
+                (CompositeKind::Union, "union "),
+                (CompositeKind::Enum, "enum "),
+            ]
+            .into_iter()
+            .find_map(|(kind, tag)| Some((kind, c_name.strip_prefix(tag)?.to_string())));
+            let (kind, c_name) = match tagged {
+                Some((kind, identifier)) => (
+                    kind,
+                    CName {
+                        identifier,
+                        aliased: false,
+                    },
+                ),
+                None => (
+                    CompositeKind::Struct,
+                    CName {
+                        identifier: c_name,
+                        aliased: true,
+                    },
+                ),
+            };
+
+            mappings.insert(NameMapping {
+                kind,
+                c_name: Some(c_name),
+                rust_name,
+                aliases: BTreeSet::new(),
+                alias_order: Vec::new(),
+                order: mappings.types.len(),
+                namespace: Vec::new(),
+                doc: None,
+                source: None,
+                generic_base: None,
+            });
+        }
+
+        Ok(mappings)
+    }
+
+    /// Hash of the mappings as a hex string, see [MappingsCodegen::with_source_hash]
+    ///
+    /// Computed with FNV-1a over the types sorted by Rust name, so it is the same across runs,
+    /// platforms and compiler versions for identical mappings. The alias cache is not hashed
+    pub fn source_hash(&self) -> String {
+        let mut mappings: Vec<&NameMapping> = self.types.values().collect();
+        mappings.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |text: &str| {
+            // the separator keeps ("ab", "c") and ("a", "bc") apart
+            for byte in text.bytes().chain([0]) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+
+        write(&format!("{:?}", self.language));
+        for mapping in mappings {
+            write(&mapping.rust_name);
+            write(&mapping.kind.to_string());
+            match &mapping.c_name {
+                Some(c_name) => write(&format!("{}:{}", c_name.identifier, c_name.aliased)),
+                None => write(""),
+            }
+            mapping.aliases.iter().for_each(|alias| write(alias));
+            write(&mapping.namespace.join("::"));
+            write(mapping.doc.as_deref().unwrap_or(""));
+        }
+
+        format!("{hash:016x}")
+    }
+
+    /// Wraps these mappings in a [MappingsCodegen] builder to export the mappings as static code
+    ///
+    /// Reversible with [MappingsCodegen::mappings], see [MappingsCodegen::borrowed] to keep using the mappings
+    pub fn codegen<'a>(self) -> MappingsCodegen<'a> {
+        self.into()
+    }
+}
+
+/// Differences between two [NameMappings], see [NameMappings::diff]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MappingDiff {
+    /// Types that were not present before
+    pub added: Vec<NameMapping>,
+
+    /// Types that are not present anymore
+    pub removed: Vec<NameMapping>,
+
+    /// Types whose kind, C name or aliases changed
+    pub changed: Vec<MappingChange>,
+}
+
+/// A type present in both compared [NameMappings] with a different mapping
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingChange {
+    /// The mapping before the change
+    pub old: NameMapping,
+
+    /// The mapping after the change
+    pub new: NameMapping,
+}
+
+impl MappingDiff {
+    /// `true` if no type was added, removed or changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for MappingDiff {
+    /// One line per difference: `+` for added types, `-` for removed ones and `~` for changed ones
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for mapping in &self.added {
+            writeln!(f, "+ {} ({})", mapping.rust_name, mapping.kind)?;
+        }
+        for mapping in &self.removed {
+            writeln!(f, "- {} ({})", mapping.rust_name, mapping.kind)?;
+        }
+        for MappingChange { old, new } in &self.changed {
+            writeln!(
+                f,
+                "~ {} ({} {:?} -> {} {:?})",
+                new.rust_name, old.kind, old.aliases, new.kind, new.aliases
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The callback to include with [bindgen::Builder::parse_callbacks] in your `build.rs`
+/// to discover types and aliases during the C header parsing.
+#[derive(Debug)]
+pub struct NameMappingsCallback(pub Rc<RefCell<NameMappings>>);
+
+impl NameMappingsCallback {
+    /// Customize the discovery with [DiscoveryOptions]
+    ///
+    /// Each call starts a new discovery run, see [ConfiguredNameMappingsCallback] to discover
+    /// several bindgen runs into the same mappings
+    pub fn with_options(self, options: DiscoveryOptions) -> ConfiguredNameMappingsCallback {
+        let run = {
+            let mut mappings = self.0.borrow_mut();
+            mappings.discovery_runs += 1;
+            // the mappings may have been edited by hand since the last run
+            mappings.alias_holders.synced = false;
+            mappings.discovery_runs - 1
+        };
+
+        ConfiguredNameMappingsCallback {
+            mappings: self.0,
+            options,
+            pending_doc: RefCell::new(None),
+            headers: RefCell::new(Vec::new()),
+            run,
+            run_ids: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Called when a type reference (`id`) spelled `name` resolves to the type `target_id`
+    ///
+    /// A reference using another name than the one of its type is saved as an alias of the type.
+    /// bindgen has no callback for resolved type references yet, so this has to be forwarded manually
+    pub fn resolved_type_ref_found(
+        &self,
+        id: DiscoveredItemId,
+        name: &str,
+        target_id: DiscoveredItemId,
+    ) {
+        Discovery {
+            mappings: &self.0,
+            options: &DiscoveryOptions::default(),
+            doc: None,
+            source: None,
+        }
+        .new_type_ref_found(id, name, target_id)
+    }
+}
+
+impl ConfiguredNameMappingsCallback {
+    /// The header the discovered types come from, see [DiscoveryOptions::capture_sources]
+    fn source(&self) -> Option<PathBuf> {
+        let headers = self.headers.borrow();
+        match headers.as_slice() {
+            [header] if self.options.capture_sources => Some(header.clone()),
+            _ => None,
+        }
+    }
+
+    /// see [NameMappingsCallback::resolved_type_ref_found]
+    pub fn resolved_type_ref_found(
+        &self,
+        id: DiscoveredItemId,
+        name: &str,
+        target_id: DiscoveredItemId,
+    ) {
+        let (id, target_id) = (self.run_id(id), self.run_id(target_id));
+        Discovery {
+            mappings: &self.mappings,
+            options: &self.options,
+            doc: None,
+            source: None,
+        }
+        .new_type_ref_found(id, name, target_id)
+    }
+
+    /// Called when a function named `c_name` in C is imported as `rust_name`
+    ///
+    /// Saved in [NameMappings::functions] if [DiscoveryOptions::capture_functions] is set.
+    /// The functions bindgen renames itself are forwarded by the callback, this is for the other renames
+    /// e.g.: the ones made by another callback's [bindgen::callbacks::ParseCallbacks::generated_name_override]
+    pub fn function_found(&self, c_name: &str, rust_name: &str) {
+        if !self.options.capture_functions {
+            return;
+        }
+
+        self.mappings
+            .borrow_mut()
+            .functions
+            .insert(rust_name.to_string(), c_name.to_string());
+    }
+
+    /// Called when a constant or global variable named `c_name` in C is imported as `rust_name`
+    ///
+    /// Saved in [NameMappings::constants] if [DiscoveryOptions::capture_constants] is set.
+    /// The constants bindgen renames itself are forwarded by the callback, this is for the other renames
+    pub fn constant_found(&self, c_name: &str, rust_name: &str) {
+        if !self.options.capture_constants {
+            return;
+        }
+
+        self.mappings
+            .borrow_mut()
+            .constants
+            .insert(rust_name.to_string(), c_name.to_string());
+    }
+
+    /// Saves the constant `c_name` if bindgen renames it, see [DiscoveryOptions::capture_constants]
+    fn renamed_constant_found(&self, c_name: &str) {
+        if let Some(rust_name) = bindgen_renamed(c_name) {
+            self.constant_found(c_name, &rust_name);
+        }
+    }
+
+    /// Called when a field of the type `type_name` (its Rust name) uses the type `field_type_name` (its C name)
+    ///
+    /// Saved in [NameMappings::dependencies] if [DiscoveryOptions::capture_dependencies] is set,
+    /// see [NameMappings::resolve_field_references]
+    fn field_found(&self, type_name: &str, field_type_name: &str) {
+        if !self.options.capture_dependencies {
+            return;
+        }
+
+        self.mappings
+            .borrow_mut()
+            .pending_field_references
+            .push((type_name.to_string(), field_type_name.to_string()));
+    }
+
+    /// The id under which the item `id` of this run is stored in the mappings
+    ///
+    /// bindgen's ids start over at each run, so the first run keeps them and the later ones get minted ids
+    fn run_id(&self, id: DiscoveredItemId) -> DiscoveredItemId {
+        if self.run == 0 {
+            return id;
+        }
+
+        *self
+            .run_ids
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| self.mappings.borrow_mut().mint_id())
+    }
+
+    /// The id of the type an earlier run already discovered with the same kind and names as the `item`
+    fn rediscovered(&self, item: &DiscoveredItem) -> Option<DiscoveredItemId> {
+        let (original_name, final_name) = match item {
+            DiscoveredItem::Struct {
+                original_name: Some(original_name),
+                final_name,
+            }
+            | DiscoveredItem::Union {
+                original_name: Some(original_name),
+                final_name,
+            } => (original_name, final_name),
+            _ => return None,
+        };
+        let kind = CompositeKind::try_from(item).ok()?;
+        let (namespace, name) = split_namespace(original_name);
+        let c_name = Some(CName {
+            identifier: name.to_string(),
+            aliased: false,
+        });
+
+        let run_ids = self.run_ids.borrow();
+        let mappings = self.mappings.borrow();
+        let found = mappings.types.iter().find(|(id, mapping)| {
+            mapping.kind == kind
+                && mapping.rust_name == *final_name
+                && mapping.c_name == c_name
+                && mapping.namespace == namespace
+                && !run_ids.values().any(|run_id| run_id == *id)
+        });
+
+        found.map(|(id, _)| *id)
+    }
+}
+
+/// A [NameMappingsCallback] customized with [DiscoveryOptions]
+///
+/// Include it with [bindgen::Builder::parse_callbacks] just like [NameMappingsCallback]
+///
+/// Several bindgen runs (one [bindgen::Builder::generate] each) can be discovered into the same mappings
+/// by giving each run its own callback. bindgen's ids start over at each run, so the ones of the first run
+/// are kept while the later runs get minted ids (see [SYNTHETIC_IDS_START]) and never collide with them.
+/// A named type already discovered by an earlier run (e.g.: from a header included by both runs) is kept
+/// once, along with its aliases. The plain [NameMappingsCallback] is unaware of the runs, so it is
+/// only suited to a single one
+#[derive(Debug)]
+pub struct ConfiguredNameMappingsCallback {
+    /// The mappings populated during the discovery
+    pub mappings: Rc<RefCell<NameMappings>>,
+
+    /// Settings of the discovery
+    pub options: DiscoveryOptions,
+
+    /// Last comment processed by bindgen, waiting for the type it documents
+    pending_doc: RefCell<Option<String>>,
+
+    /// Headers given to bindgen, see [DiscoveryOptions::capture_sources]
+    headers: RefCell<Vec<PathBuf>>,
+
+    /// Index of the run, see [NameMappings::discovery_runs]
+    run: usize,
+
+    /// The ids of the items of this run, if they are not stored under bindgen's ones
+    run_ids: RefCell<HashMap<DiscoveredItemId, DiscoveredItemId>>,
+}
+
+/// Configures the discovery in one place and builds the callback along with the shared mappings
+///
+/// [NameMappingsCallback] stays the simple path when no setting is needed
+///
+/// ```
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// use bindgen::callbacks::{DiscoveredItem, DiscoveredItemId, ParseCallbacks};
+/// use bindgen_bridge::import::NameMappingsCallbackBuilder;
+///
+/// let warnings = Rc::new(RefCell::new(Vec::new()));
+/// let sink = warnings.clone();
+///
+/// let (callback, mappings) = NameMappingsCallbackBuilder::default()
+///     .deny("^internal_")?
+///     .diagnostics(move |warning| sink.borrow_mut().push(warning.to_string()))
+///     .build();
+///
+/// // bindgen::Builder::default().parse_callbacks(Box::new(callback)) ...
+/// let item = |name: &str| DiscoveredItem::Struct {
+///     original_name: Some(name.to_string()),
+///     final_name: name.to_string(),
+/// };
+/// callback.new_item_found(DiscoveredItemId::new(0), item("internal_state"));
+/// callback.new_item_found(DiscoveredItemId::new(1), item("point"));
+/// callback.new_item_found(DiscoveredItemId::new(1), item("point"));
+///
+/// assert_eq!(mappings.borrow().types.len(), 1);
+/// assert!(warnings.borrow()[0].contains("duplicated definition"));
+/// # Ok::<(), bindgen_bridge::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct NameMappingsCallbackBuilder {
+    /// Settings of the discovery
+    options: DiscoveryOptions,
+
+    /// see [NameMappingsCallbackBuilder::capacity]
+    capacity: usize,
+}
+
+impl NameMappingsCallbackBuilder {
+    /// see [DiscoveryOptions::allow]
+    pub fn allow(&mut self, pattern: &str) -> Result<&mut Self> {
+        self.options.allow(pattern)?;
+        Ok(self)
+    }
+
+    /// see [DiscoveryOptions::deny]
+    pub fn deny(&mut self, pattern: &str) -> Result<&mut Self> {
+        self.options.deny(pattern)?;
+        Ok(self)
+    }
+
+    /// see [DiscoveryOptions::skip_system_headers]
+    pub fn skip_system_headers(&mut self, will: bool) -> &mut Self {
+        self.options.skip_system_headers(will);
+        self
+    }
+
+    /// see [DiscoveryOptions::keep_prefix]
+    pub fn keep_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.options.keep_prefix(prefix);
+        self
+    }
+
+    /// see [DiscoveryOptions::name_anonymous_types]
+    pub fn name_anonymous_types(
+        &mut self,
+        namer: impl Fn(DiscoveredItemId, CompositeKind) -> Option<String> + 'static,
+    ) -> &mut Self {
+        self.options.name_anonymous_types(namer);
+        self
+    }
+
+    /// see [DiscoveryOptions::diagnostics]
+    pub fn diagnostics(&mut self, sink: impl Fn(&str) + 'static) -> &mut Self {
+        self.options.diagnostics(sink);
+        self
+    }
+
+    /// Number of types expected to be discovered, see [NameMappings::with_capacity]
+    ///
+    /// default: 0
+    pub fn capacity(&mut self, expected_types: usize) -> &mut Self {
+        self.capacity = expected_types;
+        self
+    }
+
+    /// The other settings of the discovery, see [DiscoveryOptions]
+    pub fn options(&mut self) -> &mut DiscoveryOptions {
+        &mut self.options
+    }
+
+    /// The callback to give to [bindgen::Builder::parse_callbacks] and the mappings it populates
+    pub fn build(&self) -> (ConfiguredNameMappingsCallback, Rc<RefCell<NameMappings>>) {
+        let mappings = Rc::new(RefCell::new(NameMappings::with_capacity(self.capacity)));
+        let callback = NameMappingsCallback(mappings.clone()).with_options(self.options.clone());
+
+        (callback, mappings)
+    }
+}
+
+/// Names the anonymous types without alias, see [DiscoveryOptions::name_anonymous_types]
+#[derive(Clone)]
+pub struct AnonymousNamer(Rc<dyn Fn(DiscoveredItemId, CompositeKind) -> Option<String>>);
+
+impl AnonymousNamer {
+    /// Wraps the closure naming the types
+    pub fn new(
+        namer: impl Fn(DiscoveredItemId, CompositeKind) -> Option<String> + 'static,
+    ) -> Self {
+        Self(Rc::new(namer))
+    }
+
+    /// The name given to the anonymous type, if any
+    pub fn name(&self, id: DiscoveredItemId, kind: CompositeKind) -> Option<String> {
+        (self.0)(id, kind)
+    }
+}
+
+impl std::fmt::Debug for AnonymousNamer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AnonymousNamer(..)")
+    }
+}
+
+/// Receives the warnings of the discovery, see [DiscoveryOptions::diagnostics]
+#[derive(Clone)]
+pub struct DiagnosticsSink(Rc<dyn Fn(&str)>);
+
+impl DiagnosticsSink {
+    /// Wraps the closure receiving the warnings
+    pub fn new(sink: impl Fn(&str) + 'static) -> Self {
+        Self(Rc::new(sink))
+    }
+
+    /// Sends a warning to the sink
+    pub fn warn(&self, message: &str) {
+        (self.0)(message)
+    }
+}
+
+impl std::fmt::Debug for DiagnosticsSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DiagnosticsSink(..)")
+    }
+}
+
+/// Settings of the discovery done by [ConfiguredNameMappingsCallback]
+#[derive(Debug, Default, Clone)]
+pub struct DiscoveryOptions {
+    /// see [DiscoveryOptions::allow]
+    allow: Vec<Regex>,
+
+    /// see [DiscoveryOptions::deny]
+    deny: Vec<Regex>,
+
+    /// see [DiscoveryOptions::skip_system_headers]
+    skip_system_headers: bool,
+
+    /// see [DiscoveryOptions::keep_prefix]
+    own_prefixes: Vec<String>,
+
+    /// see [DiscoveryOptions::capture_docs]
+    capture_docs: bool,
+
+    /// see [DiscoveryOptions::case_insensitive_aliases]
+    case_insensitive_aliases: bool,
+
+    /// see [DiscoveryOptions::record_rejected_aliases]
+    record_rejected_aliases: bool,
+
+    /// see [DiscoveryOptions::capture_sources]
+    capture_sources: bool,
+
+    /// see [DiscoveryOptions::capture_functions]
+    capture_functions: bool,
+
+    /// see [DiscoveryOptions::capture_constants]
+    capture_constants: bool,
+
+    /// see [DiscoveryOptions::capture_dependencies]
+    capture_dependencies: bool,
+
+    /// see [DiscoveryOptions::name_anonymous_types]
+    anonymous_namer: Option<AnonymousNamer>,
+
+    /// see [DiscoveryOptions::alias_conflicts]
+    alias_conflicts: AliasConflictPolicy,
+
+    /// see [DiscoveryOptions::diagnostics]
+    diagnostics: Option<DiagnosticsSink>,
+}
+
+impl DiscoveryOptions {
+    /// Only keep the types whose C name matches one of the allowed patterns
+    /// Anonymous types are matched by their aliases, e.g.: `bgp_peer_t` for `typedef struct { .. } bgp_peer_t;`,
+    /// and decided when their first alias is found if it comes after them
+    ///
+    /// default: every type is allowed
+    pub fn allow(&mut self, pattern: &str) -> Result<&mut Self> {
+        self.allow.push(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Drop the types whose C name matches one of the denied patterns, even if they are allowed
+    /// Dropped types are never stored and their aliases are forgotten, see [NameMappings::denied_types]
+    ///
+    /// default: no type is denied
+    pub fn deny(&mut self, pattern: &str) -> Result<&mut Self> {
+        self.deny.push(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    /// Drop the types coming from system headers (libc, etc.)
+    ///
+    /// bindgen does not report where a discovered type is declared, so the system types are
+    /// recognized by their name instead: names reserved to the implementation (`__name`, `_Name`)
+    /// and the C++ `std` namespace. Use [DiscoveryOptions::deny] for the system types using regular names
+    ///
+    /// default: false
+    pub fn skip_system_headers(&mut self, will: bool) -> &mut Self {
+        self.skip_system_headers = will;
+        self
+    }
+
+    /// C name prefix of your own types, which are never taken for system types by
+    /// [DiscoveryOptions::skip_system_headers] (e.g. `__mylib_`)
+    pub fn keep_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.own_prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Save the documentation comment of the types in [NameMapping::doc]
+    ///
+    /// bindgen does not attach comments to the discovered items, so they are matched by order:
+    /// a type gets the last comment bindgen processed before reporting it, the comments of the fields,
+    /// functions, variables, enums and typedefs excluded.
+    /// This is best effort, and needs the callback to be the last one given to
+    /// [bindgen::Builder::parse_callbacks] since bindgen only shows comments to the last callback
+    ///
+    /// default: false
+    pub fn capture_docs(&mut self, will: bool) -> &mut Self {
+        self.capture_docs = will;
+        self
+    }
+
+    /// Save the header each type comes from in [NameMapping::source]
+    ///
+    /// bindgen does not report where a discovered type is declared, only the headers it was given
+    /// (with [bindgen::Builder::header]), which are parsed together. So the source is only known when
+    /// a single header is given: it is the header the type was reached from, which may include the
+    /// file actually declaring it. With several headers, or with [bindgen::Builder::header_contents],
+    /// the source is left to `None`
+    ///
+    /// default: false
+    pub fn capture_sources(&mut self, will: bool) -> &mut Self {
+        self.capture_sources = will;
+        self
+    }
+
+    /// Save the functions in [NameMappings::functions], see [NameMappings::to_cbindgen_fn_renames]
+    ///
+    /// Only the functions renamed by bindgen are saved, i.e. the ones named after a Rust keyword
+    /// (`type` becomes `type_`). The ones renamed otherwise have to be forwarded with
+    /// [ConfiguredNameMappingsCallback::function_found]
+    ///
+    /// default: false
+    pub fn capture_functions(&mut self, will: bool) -> &mut Self {
+        self.capture_functions = will;
+        self
+    }
+
+    /// Save the constants (`#define`d integers and strings) and the global variables in [NameMappings::constants],
+    /// see [NameMappings::to_cbindgen_constant_renames]
+    ///
+    /// Only the constants renamed by bindgen are saved, i.e. the ones named after a Rust keyword
+    /// (`type` becomes `type_`), like for [DiscoveryOptions::capture_functions]. The ones renamed otherwise
+    /// have to be forwarded with [ConfiguredNameMappingsCallback::constant_found]
+    ///
+    /// default: false
+    pub fn capture_constants(&mut self, will: bool) -> &mut Self {
+        self.capture_constants = will;
+        self
+    }
+
+    /// Save which types the fields of each type use in [NameMappings::dependencies],
+    /// e.g.: to find all the types needed by a type with [NameMappings::dependencies_of]
+    ///
+    /// bindgen only reports the names of the fields' types, before the type holding them is discovered,
+    /// so they are kept in [NameMappings::pending_field_references] until the callback is dropped,
+    /// or [NameMappings::resolve_field_references] is called.
+    /// Pointers to a type are not reported by bindgen, only the fields holding the type or one of its aliases
+    ///
+    /// default: false
+    pub fn capture_dependencies(&mut self, will: bool) -> &mut Self {
+        self.capture_dependencies = will;
+        self
+    }
+
+    /// Name the anonymous types that have no alias yet with the given closure, instead of leaving them unnamed
+    /// e.g.: `|_, kind| Some(format!("anonymous_{kind}"))`, returning `None` keeps the type anonymous
+    ///
+    /// The name is used as-is, without tag keyword, like an alias (see [CName::aliased]).
+    /// The typedefs of the type found afterward are kept as its aliases
+    ///
+    /// default: None, see [UnnamedTypePolicy] for the unnamed types
+    pub fn name_anonymous_types(
+        &mut self,
+        namer: impl Fn(DiscoveredItemId, CompositeKind) -> Option<String> + 'static,
+    ) -> &mut Self {
+        self.anonymous_namer = Some(AnonymousNamer::new(namer));
+        self
+    }
+
+    /// What to do when an alias is found for two different types, see [AliasConflictPolicy]
+    ///
+    /// The conflicts are detected among the aliases of the known types and the pending ones,
+    /// and are all reported in [NameMappings::alias_conflicts]
+    ///
+    /// default: [AliasConflictPolicy::KeepFirst]
+    pub fn alias_conflicts(&mut self, policy: AliasConflictPolicy) -> &mut Self {
+        self.alias_conflicts = policy;
+        self
+    }
+
+    /// Send the warnings of the discovery (duplicated definitions, alias cycles and conflicts)
+    /// to the given closure, e.g.: to forward them to `cargo:warning=`
+    ///
+    /// default: None, the warnings are printed to stdout
+    pub fn diagnostics(&mut self, sink: impl Fn(&str) + 'static) -> &mut Self {
+        self.diagnostics = Some(DiagnosticsSink::new(sink));
+        self
+    }
+
+    /// Collapse the aliases of a type that only differ by their case (`Foo` and `FOO`)
+    /// see [NameMapping::dedup_aliases_ignoring_case]
+    ///
+    /// C is case-sensitive, so these are different aliases for the compiler: only enable this if
+    /// you don't need every alias to be known
+    ///
+    /// default: false
+    pub fn case_insensitive_aliases(&mut self, will: bool) -> &mut Self {
+        self.case_insensitive_aliases = will;
+        self
+    }
+
+    /// Should the aliases dropped from the types be kept in [NameMappings::rejected_aliases],
+    /// to audit why a name was picked for a type
+    /// e.g.: the `struct Foo` self-reference of a named `struct Foo`, or the aliases collapsed by
+    /// [DiscoveryOptions::case_insensitive_aliases]
+    ///
+    /// default: false
+    pub fn record_rejected_aliases(&mut self, will: bool) -> &mut Self {
+        self.record_rejected_aliases = will;
+        self
+    }
+
+    /// Records the aliases dropped from the type if asked to, see [DiscoveryOptions::record_rejected_aliases]
+    fn reject_aliases(
+        &self,
+        mappings: &mut NameMappings,
+        id: DiscoveredItemId,
+        rejected: impl IntoIterator<Item = String>,
+    ) {
+        if !self.record_rejected_aliases {
+            return;
+        }
+
+        let mut rejected = rejected.into_iter().peekable();
+        if rejected.peek().is_some() {
+            mappings
+                .rejected_aliases
+                .entry(id)
+                .or_default()
+                .extend(rejected);
+        }
+    }
+
+    /// Reports a warning to the [DiscoveryOptions::diagnostics] sink, or prints it if there is none
+    fn warn(&self, message: String) {
+        match &self.diagnostics {
+            Some(sink) => sink.warn(&message),
+            None => println!("{}", message),
+        }
+    }
+
+    /// Whether a type with this C name looks like it comes from a system header
+    fn is_system_name(&self, original_name: &str) -> bool {
+        if self
+            .own_prefixes
+            .iter()
+            .any(|prefix| original_name.starts_with(prefix))
+        {
+            return false;
+        }
+
+        let mut chars = original_name.chars();
+        let reserved = match (chars.next(), chars.next()) {
+            (Some('_'), Some(second)) => second == '_' || second.is_ascii_uppercase(),
+            _ => false,
+        };
+
+        reserved || original_name.starts_with("std::")
+    }
+
+    /// Whether a type with this C name is kept by the allow and deny lists and the system headers filter
+    ///
+    /// Anonymous types (`None`) are allowed until they get a name, see [DiscoveryOptions::allow]
+    pub fn is_allowed(&self, original_name: Option<&str>) -> bool {
+        let Some(original_name) = original_name else {
+            return true;
+        };
+
+        if self.skip_system_headers && self.is_system_name(original_name) {
+            return false;
+        }
+
+        let allowed =
+            self.allow.is_empty() || self.allow.iter().any(|regex| regex.is_match(original_name));
+
+        allowed && !self.deny.iter().any(|regex| regex.is_match(original_name))
+    }
+}
+
+// callback behaviour pseudo code
+// types: Map ItemId => Info { canonical_ident (final rust name), original_name(item.kind.type.name), HashSetAlias> }
+// found_aliases: Map ItemId => Alias
+//
+// on new type/item: call new composite callback => insert to map, check found_aliases
+// on new alias: call new alias callback => if alias.type in types types.get(alias.type.id).push_alias(alias) else found_aliases.push(alias)
+// on resolvedtyperef: call new alias callback => ^ + typeref.name != original_name
+//                    (not called by bindgen yet, see NameMappingsCallback::resolved_type_ref_found)
+impl bindgen::callbacks::ParseCallbacks for NameMappingsCallback {
+    fn new_item_found(&self, id: DiscoveredItemId, item: DiscoveredItem) {
+        Discovery {
+            mappings: &self.0,
+            options: &DiscoveryOptions::default(),
+            doc: None,
+            source: None,
+        }
+        .new_item_found(id, item)
+    }
+}
+
+/// [bindgen::callbacks::ParseCallbacks] appending each discovered item to a JSON Lines file as soon as it is found,
+/// instead of keeping the mappings in memory, see [NameMappings::from_jsonl] to load them
+///
+/// Each line is one item, in the order bindgen reports them:
+/// - `{"item":"struct","id":0,"original_name":"Foo","final_name":"Foo"}`, `null` for an anonymous type
+/// - `{"item":"union",...}` likewise
+/// - `{"item":"alias","id":1,"alias_name":"foo_t","alias_for":0}`
+///
+/// The ids are numbered in the order they first show up, since bindgen's ones can't be written.
+/// The aliases found before their type are written as-is, and resolved when loading.
+///
+/// The clones share the same file: give one to bindgen and keep one to [StreamingNameMappingsCallback::finish]
+/// the writing once bindgen is done. Available with the `json` feature
+///
+/// ```no_run
+/// use bindgen_bridge::import::{NameMappings, StreamingNameMappingsCallback};
+///
+/// let callback = StreamingNameMappingsCallback::create("mappings.jsonl")?;
+/// // bindgen::Builder::default().parse_callbacks(Box::new(callback.clone())) ...
+/// callback.finish()?;
+///
+/// let mappings = NameMappings::from_jsonl_file("mappings.jsonl")?;
+/// # Ok::<(), bindgen_bridge::Error>(())
+/// ```
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+pub struct StreamingNameMappingsCallback {
+    writer: Rc<RefCell<std::io::LineWriter<std::fs::File>>>,
+
+    /// The number given to each id found so far
+    ids: Rc<RefCell<HashMap<DiscoveredItemId, usize>>>,
+
+    /// The first failed write, after which nothing more is written, see [StreamingNameMappingsCallback::finish]
+    error: Rc<RefCell<Option<std::io::Error>>>,
+}
+
+#[cfg(feature = "json")]
+impl StreamingNameMappingsCallback {
+    /// Creates the file at `path` (replaced if it exists) that the items are written to
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            writer: Rc::new(RefCell::new(std::io::LineWriter::new(
+                std::fs::File::create(path)?,
+            ))),
+            ids: Rc::default(),
+            error: Rc::default(),
+        })
+    }
+
+    /// Flushes the file, and fails with the first write that failed during the discovery if any
+    pub fn finish(self) -> Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error.into());
+        }
+
+        std::io::Write::flush(&mut *self.writer.borrow_mut())?;
+        Ok(())
+    }
+
+    /// The number of the id, given in the order the ids are found
+    fn number(&self, id: DiscoveredItemId) -> usize {
+        let mut ids = self.ids.borrow_mut();
+        let next = ids.len();
+        *ids.entry(id).or_insert(next)
+    }
+}
+
+#[cfg(feature = "json")]
+impl bindgen::callbacks::ParseCallbacks for StreamingNameMappingsCallback {
+    fn new_item_found(&self, id: DiscoveredItemId, item: DiscoveredItem) {
+        if self.error.borrow().is_some() {
+            return;
+        }
+
+        let id = self.number(id);
+        let item = match item {
+            DiscoveredItem::Struct {
+                original_name,
+                final_name,
+            } => JsonlItem::Struct {
+                id,
+                original_name,
+                final_name,
+            },
+            DiscoveredItem::Union {
+                original_name,
+                final_name,
+            } => JsonlItem::Union {
+                id,
+                original_name,
+                final_name,
+            },
+            DiscoveredItem::Alias {
+                alias_name,
+                alias_for,
+            } => JsonlItem::Alias {
+                id,
+                alias_name,
+                alias_for: self.number(alias_for),
+            },
+            // the kinds of items bindgen may report in the future
+            #[allow(unreachable_patterns)]
+            _ => return,
+        };
+
+        let mut writer = self.writer.borrow_mut();
+        let written = serde_json::to_writer(&mut *writer, &item)
+            .map_err(std::io::Error::from)
+            .and_then(|()| std::io::Write::write_all(&mut *writer, b"\n"));
+        if let Err(error) = written {
+            self.error.replace(Some(error));
+        }
+    }
+}
+
+impl bindgen::callbacks::ParseCallbacks for ConfiguredNameMappingsCallback {
+    fn process_comment(&self, comment: &str) -> Option<String> {
+        if self.options.capture_docs {
+            self.pending_doc.replace(Some(comment.trim().to_string()));
+        }
+
+        // leave the comment as-is
+        None
+    }
+
+    fn int_macro(&self, name: &str, _value: i64) -> Option<IntKind> {
+        self.renamed_constant_found(name);
+        None
+    }
+
+    fn str_macro(&self, name: &str, _value: &[u8]) {
+        self.renamed_constant_found(name);
+    }
+
+    fn generated_name_override(&self, info: ItemInfo<'_>) -> Option<String> {
+        // functions and variables are never reported as items, their comment isn't the next type's
+        self.pending_doc.take();
+        match info.kind {
+            ItemKind::Var => self.renamed_constant_found(info.name),
+            ItemKind::Function => {
+                if let Some(rust_name) = bindgen_renamed(info.name) {
+                    self.function_found(info.name, &rust_name);
+                }
+            }
+            _ => {}
+        }
+
+        // leave the name as-is
+        None
+    }
+
+    fn enum_variant_behavior(
+        &self,
+        _enum_name: Option<&str>,
+        _variant_name: &str,
+        _variant_value: EnumVariantValue,
+    ) -> Option<EnumVariantCustomBehavior> {
+        // neither are the enums
+        self.pending_doc.take();
+        None
+    }
+
+    fn add_derives(&self, _info: &DeriveInfo<'_>) -> Vec<String> {
+        // asked after the comment of the enums and the typedefs bindgen turns into new types,
+        // and after the structs and unions are reported
+        self.pending_doc.take();
+        Vec::new()
+    }
+
+    fn field_visibility(&self, info: FieldInfo<'_>) -> Option<FieldVisibilityKind> {
+        // the last comment was the field's, not its type's
+        self.pending_doc.take();
+        if let Some(field_type_name) = info.field_type_name {
+            self.field_found(info.type_name, field_type_name);
+        }
+        None
+    }
+
+    fn new_item_found(&self, id: DiscoveredItemId, item: DiscoveredItem) {
+        // an alias takes the pending comment too, it was not the one of the next type
+        let doc = self.pending_doc.take();
+
+        if self.run > 0 {
+            // the type is already known, only its aliases found before it are moved to it
+            if let Some(known_id) = self.rediscovered(&item) {
+                if let Some(run_id) = self.run_ids.borrow_mut().insert(id, known_id) {
+                    self.mappings.borrow_mut().fold_id(
+                        run_id,
+                        known_id,
+                        self.options.alias_conflicts,
+                    );
+                }
+                return;
+            }
+        }
+
+        let id = self.run_id(id);
+        let item = match item {
+            DiscoveredItem::Alias {
+                alias_name,
+                alias_for,
+            } => DiscoveredItem::Alias {
+                alias_name,
+                alias_for: self.run_id(alias_for),
+            },
+            item => item,
+        };
+
+        Discovery {
+            mappings: &self.mappings,
+            options: &self.options,
+            doc,
+            source: self.source(),
+        }
+        .new_item_found(id, item);
+    }
+
+    fn header_file(&self, filename: &str) {
+        self.headers.borrow_mut().push(PathBuf::from(filename));
+    }
+}
+
+impl Drop for ConfiguredNameMappingsCallback {
+    fn drop(&mut self) {
+        // bindgen drops the callbacks along with the bindings, once every item has been reported
+        if self.options.capture_dependencies {
+            if let Ok(mut mappings) = self.mappings.try_borrow_mut() {
+                mappings.resolve_field_references();
+            }
+        }
+    }
+}
+
+/// The discovery logic shared by [NameMappingsCallback] and [ConfiguredNameMappingsCallback]
+struct Discovery<'a> {
+    mappings: &'a RefCell<NameMappings>,
+    options: &'a DiscoveryOptions,
+
+    /// Documentation comment of the discovered item, if known
+    doc: Option<String>,
+
+    /// Header the discovered item comes from, if known
+    source: Option<PathBuf>,
+}
+
+impl Discovery<'_> {
+    fn new_item_found(self, id: DiscoveredItemId, item: DiscoveredItem) {
+        let mappings = self.mappings;
+        match &item {
+            DiscoveredItem::Struct { original_name, final_name }
+            | DiscoveredItem::Union { original_name, final_name } => {
+                self.new_composite_found(id, CompositeKind::try_from(&item).unwrap(), original_name.as_ref().map(String::as_str), final_name)
+            }
+            DiscoveredItem::Alias { alias_name, alias_for } => {
+                self.new_alias_found(id, alias_name, *alias_for)
+            }
+            // the kinds of items bindgen may report in the future
+            #[allow(unreachable_patterns)]
+            _ => self.item_ignored(&item),
+        }
+
+        // counted after the item, whose order is the previous count
+        mappings.borrow_mut().discovered_items += 1;
+    }
+
+    /// Called when an item of a kind the crate doesn't handle is found
+    ///
+    /// Counts it in [NameMappings::ignored_items] and reports it to the [DiscoveryOptions::diagnostics]
+    fn item_ignored(&self, item: &DiscoveredItem) {
+        // the name of the variant, e.g.: `Struct` for `Struct { .. }`
+        let debug = format!("{:?}", item);
+        let kind = debug
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        *self
+            .mappings
+            .borrow_mut()
+            .ignored_items
+            .entry(kind)
+            .or_default() += 1;
+        self.options
+            .warn(format!("Warn: ignored discovered item {:?}", item));
+    }
+
+    /// Called when a new composite type is found (struct / union / enum)
+    ///
+    /// Saves the type, its name, its aliases
+    ///
+    /// Anonymous types of every kind are handled the same way, e.g. the anonymous enums of
+    /// `typedef enum { X } my_flags;` flag sets are named after their typedef (see [CName::aliased]).
+    /// Anonymous types that never get an alias are left unnamed, see [UnnamedTypePolicy]
+    fn new_composite_found(
+        self,
+        id: DiscoveredItemId,
+        kind: CompositeKind,
+        original_name: Option<&str>,
+        final_ident: &str,
+    ) {
+        let mut mappings = self.mappings.borrow_mut();
+
+        // forget everything about the types we don't want to keep
+        if !self.options.is_allowed(original_name) {
+            mappings.deny_type(id);
+            return;
+        }
+
+        // C++ names may come qualified with their namespaces
+        let (namespace, original_name) = match original_name {
+            Some(original_name) => {
+                let (namespace, name) = split_namespace(original_name);
+                (namespace, Some(name))
+            }
+            None => (Vec::new(), None),
+        };
+
+        let mut cached_aliases = mappings.aliases.remove(&id).unwrap_or_default();
+
+        // if the struct is not anonymous
+        let c_name = if original_name.is_some() {
+            // build a non-aliased CName since we know the type's actual name
+            let c_name = original_name.map(|name| CName {
+                identifier: name.to_string(),
+                aliased: false,
+            });
+
+            // remove all aliases with the same name and any tag keyword, even the one of another kind
+            // this takes out "struct my_struct" (or a stale "union my_struct") while keeping "my_struct"
+            // as an alias for the typedef struct my_struct {..} my_struct; pattern
+            if let Some(original_name) = original_name {
+                let untagged = NameMapping::strip_tag_keywords(original_name.trim());
+                let rejected;
+                (cached_aliases, rejected) = cached_aliases.into_iter().partition(|value| {
+                    let value = value.trim();
+                    value == untagged || NameMapping::strip_tag_keywords(value) != untagged
+                });
+                self.options.reject_aliases(&mut mappings, id, rejected);
+            }
+
+            c_name
+        }
+        // if the type is anonymous (struct, union or enum) and we already know an alias for it
+        // we use use the first one found as the new name, but remember that it was aliased
+        else if !cached_aliases.is_empty() {
+            Some(CName {
+                identifier: cached_aliases.remove(0),
+                aliased: true,
+            })
+            // for an unknown anonymous type without aliases we can't invent a name, unless asked to
+        } else {
+            self.options
+                .anonymous_namer
+                .as_ref()
+                .and_then(|namer| namer.name(id, kind))
+                .map(|name| CName {
+                    identifier: name,
+                    aliased: true,
+                })
+        };
+
+        // an anonymous type is kept if one of the names it got is allowed
+        if original_name.is_none()
+            && c_name.is_some()
+            && !c_name
+                .iter()
+                .map(|c_name| &c_name.identifier)
+                .chain(&cached_aliases)
+                .any(|name| self.options.is_allowed(Some(name)))
+        {
+            mappings.deny_type(id);
+            return;
+        }
+
+        let aliases: BTreeSet<String> = cached_aliases.iter().cloned().collect();
+        // bindgen names the instantiations of C++ templates with their arguments, e.g.: `Box<int>`
+        let generic_base = original_name
+            .and_then(|name| name.split_once('<'))
+            .map(|(base, _)| base.trim().to_string());
+
+        let mut mapping = NameMapping {
+            kind,
+            c_name: c_name.clone(), // may still be unknown in case of anonymous struct without known aliases
+            rust_name: final_ident.to_string(),
+            aliases,
+            alias_order: cached_aliases,
+            order: mappings.discovered_items,
+            namespace,
+            doc: self.doc,
+            source: self.source,
+            generic_base,
+        };
+        if self.options.case_insensitive_aliases {
+            let aliases = mapping.aliases.clone();
+            mapping.dedup_aliases_ignoring_case();
+            self.options.reject_aliases(
+                &mut mappings,
+                id,
+                aliases.difference(&mapping.aliases).cloned(),
+            );
+        }
+
+        if let Some(duplicate) = mappings.types.insert(id, mapping) {
+            self.options.warn(format!(
+                "Warn: duplicated definition for {{ id={:?} name={:?} }}! previous: {:?}",
+                id, c_name, duplicate
+            ))
+        }
+        mappings.index_aliases(id);
+    }
+
+    /// Called when a type reference is resolved
+    ///
+    /// Saves the reference as an alias if its name differs from the name of the referenced type
+    fn new_type_ref_found(self, id: DiscoveredItemId, name: &str, target_id: DiscoveredItemId) {
+        {
+            let mappings = self.mappings.borrow();
+            let resolved_id = mappings
+                .resolve_alias_target(target_id)
+                .unwrap_or(target_id);
+
+            if let Some(mapping) = mappings.types.get(&resolved_id) {
+                let same_name = mapping.c_name.as_ref().is_some_and(|c_name| {
+                    c_name.identifier == name
+                        || NameMapping::validated_original_name(Some(c_name), mapping.kind)
+                            .is_some_and(|tagged| tagged == name)
+                });
+
+                if same_name {
+                    return;
+                }
+            }
+        }
+
+        self.new_alias_found(id, name, target_id)
+    }
+
+    /// Called when a new alias is found
+    ///
+    /// Saves the alias either as an alias or the base name (if none is known yet) for known types.
+    /// The alias is saved for later when the type is not known yet
+    ///
+    /// Aliases of aliases are resolved to the aliased type
+    fn new_alias_found(self, id: DiscoveredItemId, alias_name: &str, target_id: DiscoveredItemId) {
+        let mut mappings = self.mappings.borrow_mut();
+
+        mappings.alias_targets.insert(id, target_id);
+        let resolved_id = mappings.resolve_alias_target(target_id).unwrap_or_else(|| {
+            self.options.warn(format!(
+                "Warn: alias {{ id={:?} name={:?} }} is part of an alias cycle",
+                id, alias_name
+            ));
+            target_id
+        });
+
+        // the aliases of a dropped type are dropped with the aliases chained to them,
+        // and an anonymous type is dropped if its first alias isn't allowed
+        let unnamed = mappings
+            .types
+            .get(&resolved_id)
+            .is_some_and(|mapping| mapping.c_name.is_none());
+        if unnamed && !self.options.is_allowed(Some(alias_name)) {
+            mappings.deny_type(resolved_id);
+        }
+        if mappings.denied_types.contains(&resolved_id) {
+            mappings.aliases.remove(&id);
+            return;
+        }
+
+        let mut conflicts = Vec::new();
+        conflicts.extend(mappings.attach_alias(
+            resolved_id,
+            alias_name.to_string(),
+            self.options.alias_conflicts,
+        ));
+
+        // aliases of this alias found before it now know which type they alias
+        if let Some(chained_aliases) = mappings.aliases.remove(&id) {
+            for chained_alias in chained_aliases {
+                conflicts.extend(mappings.attach_alias(
+                    resolved_id,
+                    chained_alias,
+                    self.options.alias_conflicts,
+                ));
+            }
+        }
+
+        for conflict in conflicts {
+            self.options.warn(format!(
+                "Warn: alias {:?} found for {{ id={:?} }} and {{ id={:?} }}, applying {:?}",
+                conflict.alias, conflict.first, conflict.second, conflict.policy
+            ));
+        }
+
+        if self.options.case_insensitive_aliases {
+            if let Some(mapping) = mappings.types.get_mut(&resolved_id) {
+                let aliases = mapping.aliases.clone();
+                mapping.dedup_aliases_ignoring_case();
+                let rejected: Vec<String> = aliases.difference(&mapping.aliases).cloned().collect();
+                self.options
+                    .reject_aliases(&mut mappings, resolved_id, rejected);
+            }
+        }
+    }
+}
+
+/// One rule of the rename rules, see [NameMappings::rename_rules]
+#[derive(Clone)]
+pub(crate) struct RenameRule<'a> {
+    /// The renamed type
+    pub(crate) mapping: &'a NameMapping,
+
+    /// The Rust name of the type used as the key of the rule, see [NameMapping::rename_key]
+    pub(crate) key: String,
+
+    /// The C name the type is renamed to
+    pub(crate) c_name: String,
+}
+
+impl<'a> RenameRule<'a> {
+    /// The rule renaming the type to `c_name`, with its key stripped according to the [RenameOptions]
+    fn new(mapping: &'a NameMapping, options: &RenameOptions, c_name: String) -> Self {
+        let key = mapping.rename_key();
+        let stripped = key.strip_prefix(&options.strip_rust_prefix).unwrap_or(key);
+        let stripped = stripped
+            .strip_suffix(&options.strip_rust_suffix)
+            .unwrap_or(stripped);
+        // don't strip the whole name away
+        let key = if stripped.is_empty() { key } else { stripped };
+
+        Self {
+            mapping,
+            key: match &options.map_rust_name {
+                Some(mapper) => mapper.map(key),
+                None => key.to_string(),
+            },
+            c_name: match &options.prefix_awareness {
+                Some(prefix) => unprefixed_c_name(&c_name, prefix),
+                None => c_name,
+            },
+        }
+    }
+}
+
+/// Writes the rename rules in the toml format, without the section header
+///
+/// The documentation of the types ([NameMapping::doc]) is written as comments above their rule
+fn toml_renames(rules: &[RenameRule]) -> Result<String> {
+    let mut result = String::with_capacity(rules.len() * 16); // rough approximate of the capacity
+
+    for rule in rules {
+        // keep the documentation of the type as toml comments
+        for line in rule.mapping.doc.iter().flat_map(|doc| doc.lines()) {
+            writeln!(&mut result, "# {}", line.trim())?;
+        }
+
+        writeln!(
+            &mut result,
+            "{} = {}",
+            toml_key(&rule.key),
+            Formatted::new(rule.c_name.clone()).display_repr()
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// The key quoted for toml, escaped if needed
+///
+/// Keys containing a `.` or other special characters (e.g.: module paths) would be ambiguous bare
+fn toml_key(key: &str) -> String {
+    let repr = Key::new(key).display_repr().into_owned();
+    if repr.starts_with('"') {
+        repr
+    } else {
+        // bare key, quoted anyway like the other rename rules
+        format!("\"{}\"", repr)
+    }
+}
+
+/// Puts the rename rules in a [phf_codegen] static map
+fn static_map(rules: &[RenameRule]) -> Map<String> {
+    let mut result = Map::new();
+
+    for rule in rules {
+        result.entry(rule.key.clone(), &format!("\"{}\"", rule.c_name));
+    }
+
+    result
+}
+
+/// The Rust name bindgen gives to the function or constant `c_name`, if it renames it:
+/// the names clashing with a Rust keyword or primitive type get a `_` suffix, e.g.: `type_` for `type`
+fn bindgen_renamed(c_name: &str) -> Option<String> {
+    // bindgen's own list, see `BindgenContext::rust_mangle`
+    const RESERVED: &[&str] = &[
+        "abstract", "alignof", "as", "async", "await", "become", "box", "break", "const",
+        "continue", "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for",
+        "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "offsetof",
+        "override", "priv", "proc", "pub", "pure", "ref", "return", "Self", "self", "sizeof",
+        "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+        "use", "virtual", "where", "while", "yield", "str", "bool", "f32", "f64", "usize", "isize",
+        "u128", "i128", "u64", "i64", "u32", "i32", "u16", "i16", "u8", "i8", "_",
+    ];
+
+    (c_name.contains(['@', '?', '$']) || RESERVED.contains(&c_name))
+        .then(|| format!("{}_", c_name.replace(['@', '?', '$'], "_")))
+}
+
+/// The toml rename rules of the `(rust_name, c_name)` pairs of functions or constants, sorted by key
+///
+/// The pairs with the same names are left out, and raw identifiers (`r#type`) lose their `r#` prefix
+fn value_renames<'a>(names: impl Iterator<Item = (&'a str, &'a str)>) -> Result<String> {
+    let mut renames: Vec<(&str, &str)> = names
+        .map(|(rust_name, c_name)| (rust_name.strip_prefix("r#").unwrap_or(rust_name), c_name))
+        .filter(|(key, c_name)| key != c_name)
+        .collect();
+    renames.sort();
+
+    let mut result = String::with_capacity(renames.len() * 16);
+    for (key, c_name) in renames {
+        writeln!(
+            &mut result,
+            "{} = {}",
+            toml_key(key),
+            Formatted::new(c_name.to_string()).display_repr()
+        )?;
+    }
+
+    Ok(result)
+}
+
+/// A `const` item failing to compile if two rename rules have the same C name,
+/// see [MappingsCodegen::assert_unique_targets]
+///
+/// The C names are sorted so that only the neighbours are compared
+fn unique_targets_assertion(rules: &[RenameRule]) -> TokenStream {
+    let mut targets: Vec<&str> = rules.iter().map(|rule| rule.c_name.as_str()).collect();
+    targets.sort();
+
+    quote! {
+        const _: () = {
+            const TARGETS: &[&str] = &[#(#targets),*];
+            let mut i = 1;
+            while i < TARGETS.len() {
+                let (a, b) = (TARGETS[i - 1].as_bytes(), TARGETS[i].as_bytes());
+                let mut same = a.len() == b.len();
+                let mut j = 0;
+                while same && j < a.len() {
+                    same = a[j] == b[j];
+                    j += 1;
+                }
+                assert!(!same, "two Rust names are renamed to the same C name");
+                i += 1;
+            }
+        };
+    }
+}
+
+/// Whether the tokens are a cfg predicate: `name`, `name = "value"` or `name(predicate, ...)`,
+/// see [MappingsCodegen::cfg]
+fn is_cfg_predicate(predicate: TokenStream) -> bool {
+    let tokens: Vec<TokenTree> = predicate.into_iter().collect();
+    match tokens.as_slice() {
+        [TokenTree::Ident(_)] => true,
+        [TokenTree::Ident(_), TokenTree::Punct(equal), TokenTree::Literal(value)] => {
+            equal.as_char() == '=' && value.to_string().starts_with('"')
+        }
+        [TokenTree::Ident(_), TokenTree::Group(group)]
+            if group.delimiter() == Delimiter::Parenthesis =>
+        {
+            let mut predicates = vec![TokenStream::new()];
+            for token in group.stream() {
+                match token {
+                    TokenTree::Punct(comma) if comma.as_char() == ',' => {
+                        predicates.push(TokenStream::new())
+                    }
+                    token => predicates.last_mut().unwrap().extend([token]),
+                }
+            }
+            // `all()` and the trailing comma of `all(unix,)`
+            if predicates.last().is_some_and(TokenStream::is_empty) {
+                predicates.pop();
+            }
+            predicates.into_iter().all(is_cfg_predicate)
+        }
+        _ => false,
+    }
+}
+
+/// Puts the inverse of the rename rules in a [phf_codegen] static map, see [MappingsCodegen::bidirectional]
+///
+/// Both the C name and the aliases of each type are keys of the map, a C name or alias shared by
+/// two types fails with [Error::RenameKeyCollision]
+fn reverse_map(rules: &[RenameRule]) -> Result<Map<String>> {
+    // sorted to generate the same code every time
+    let mut keys: BTreeMap<&str, &RenameRule> = BTreeMap::new();
+
+    for rule in rules {
+        let names = std::iter::once(&rule.c_name).chain(&rule.mapping.aliases);
+        for name in names {
+            match keys.get(name.as_str()) {
+                Some(previous) if previous.key != rule.key => {
+                    return Err(Error::RenameKeyCollision {
+                        key: name.clone(),
+                        rust_names: [
+                            previous.mapping.rust_name.clone(),
+                            rule.mapping.rust_name.clone(),
+                        ],
+                    });
+                }
+                // an alias may also be the C name of its own type
+                Some(_) => {}
+                None => {
+                    keys.insert(name, rule);
+                }
+            }
+        }
+    }
+
+    let mut result = Map::new();
+    for (name, rule) in keys {
+        result.entry(name.to_string(), &format!("\"{}\"", rule.key));
+    }
+
+    Ok(result)
+}
+
+/// Puts the rename rules in a [phf_codegen] static map with the kind of the types, see [MappingsCodegen::with_kind]
+fn static_kind_map(rules: &[RenameRule]) -> Map<String> {
+    let mut result = Map::new();
+
+    for rule in rules {
+        let kind = match rule.mapping.kind {
+            CompositeKind::Struct => "Struct",
+            CompositeKind::Union => "Union",
+            CompositeKind::Class => "Class",
+            CompositeKind::Enum => "Enum",
+            CompositeKind::ScopedEnum => "ScopedEnum",
+            CompositeKind::Typedef => "Typedef",
+        };
+        result.entry(
+            rule.key.clone(),
+            &format!("(\"{}\", CKind::{})", rule.c_name, kind),
+        );
+    }
+
+    result
+}
+
+/// The lines removed from `old` (`- line`) and added in `new` (`+ line`), based on their longest common subsequence
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+            let _ = writeln!(result, "- {}", old[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(result, "+ {}", new[j]);
+            j += 1;
+        }
+    }
+
+    result
+}
+
+/// The JSON written by [NameMappings::to_json], with the ids as numbers
+#[cfg(feature = "json")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct JsonMappings {
+    language: Language,
+    minted_ids: usize,
+    discovery_runs: usize,
+    types: BTreeMap<usize, NameMapping>,
+    aliases: BTreeMap<usize, Vec<String>>,
+    alias_targets: BTreeMap<usize, usize>,
+    functions: BTreeMap<String, String>,
+    constants: BTreeMap<String, String>,
+    dependencies: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+/// The number of the id, which bindgen doesn't expose, found by comparing the id to the ones made from numbers
+#[cfg(feature = "json")]
+fn id_number(id: DiscoveredItemId) -> usize {
+    let (mut low, mut high) = (0, usize::MAX);
+    while low < high {
+        let middle = low + (high - low) / 2 + 1;
+        if DiscoveredItemId::new(middle) <= id {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+
+    low
+}
+
+/// A line of the JSON Lines written by [StreamingNameMappingsCallback]
+#[cfg(feature = "json")]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "item", rename_all = "lowercase")]
+enum JsonlItem {
+    Struct {
+        id: usize,
+        original_name: Option<String>,
+        final_name: String,
+    },
+    Union {
+        id: usize,
+        original_name: Option<String>,
+        final_name: String,
+    },
+    Alias {
+        id: usize,
+        alias_name: String,
+        alias_for: usize,
+    },
+}
+
+/// The C and C++ tag keywords of the types
+pub(crate) const TAG_KEYWORDS: [&str; 4] = ["struct", "union", "enum", "class"];
+
+/// The strict and reserved keywords of Rust, which can't name the generated items
+const RUST_KEYWORDS: [&str; 51] = [
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
+/// The `name` without its leading `keyword`, optionally compared regardless of its ASCII case
+fn strip_keyword<'n>(name: &'n str, keyword: &str, ignore_case: bool) -> Option<&'n str> {
+    if !ignore_case {
+        return name.strip_prefix(keyword);
+    }
+
+    name.get(..keyword.len())
+        .filter(|start| start.eq_ignore_ascii_case(keyword))
+        .map(|_| &name[keyword.len()..])
+}
+
+/// Splits a C name into its first tag keyword and its identifier, whatever the case of the keywords
+/// and the whitespace after them, e.g.: `("struct", "foo")` for `struct foo`, `Struct\tfoo` or `struct  foo`,
+/// and `("enum", "foo")` for `enum class foo`
+///
+/// None for the untagged names, e.g.: the aliases
+pub(crate) fn split_tag(c_name: &str) -> Option<(&'static str, &str)> {
+    let c_name = c_name.trim_start();
+    let tag = TAG_KEYWORDS.iter().find(|keyword| {
+        strip_keyword(c_name, keyword, true)
+            .is_some_and(|rest| rest.starts_with(char::is_whitespace))
+    })?;
+
+    Some((tag, NameMapping::strip_tag_keywords_with(c_name, true)))
+}
+
+/// Removes the cbindgen `prefix` from the identifier of the C name, after its tag keywords if any
+/// e.g.: `struct my_foo` is `struct foo` with the `my_` prefix, see [split_tag]
+///
+/// The name is kept as-is if it doesn't start with the prefix or if nothing would be left
+pub(crate) fn unprefixed_c_name(c_name: &str, prefix: &str) -> String {
+    let identifier = split_tag(c_name).map_or(c_name, |(_, identifier)| identifier);
+    let tag = &c_name[..c_name.len() - identifier.len()];
+
+    match identifier.strip_prefix(prefix) {
+        Some(rest) if !rest.is_empty() && !prefix.is_empty() => format!("{tag}{rest}"),
+        _ => c_name.to_string(),
+    }
+}
+
+/// Splits an identifier into its words, at underscores and case changes
+/// e.g.: `HTTPServer_config2` is `HTTP`, `Server` and `config2`
+fn split_words(identifier: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+
+    for part in identifier.split('_').filter(|part| !part.is_empty()) {
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+        for window in 1..chars.len() {
+            let (index, current) = chars[window];
+            let previous = chars[window - 1].1;
+            let next_is_lower = chars
+                .get(window + 1)
+                .is_some_and(|(_, next)| next.is_lowercase());
+
+            // myType | HTTPServer
+            if current.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next_is_lower))
+            {
+                words.push(&part[start..index]);
+                start = index;
+            }
+        }
+        words.push(&part[start..]);
+    }
+
+    words
+}
+
+/// Resolves the escape sequences of a Rust string literal's content (`\"`, `\\`, ...)
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('0') => result.push('\0'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+
+    result
+}
+
+/// Quotes a CSV field if needed
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits a qualified C++ name (`foo::bar::Baz`) into its namespace path and its name
+///
+/// Anonymous namespaces are dropped from the path since they can't be named
+fn split_namespace(qualified_name: &str) -> (Vec<String>, &str) {
+    // the template arguments (`Box<ns::Bar>`) are part of the name
+    let base_end = qualified_name.find('<').unwrap_or(qualified_name.len());
+    let name_start = qualified_name[..base_end]
+        .rfind("::")
+        .map_or(0, |separator| separator + 2);
+    let name = &qualified_name[name_start..];
+
+    let namespace = qualified_name[..name_start]
+        .split("::")
+        .filter(|segment| !segment.is_empty() && !segment.starts_with("(anonymous"))
+        .map(str::to_string)
+        .collect();
+
+    (namespace, name)
+}
+
+/// Shape of the code generated by [MappingsCodegen::generate]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CodegenOutput {
+    /// A raw str in the toml format, without the section header
+    #[default]
+    TomlString,
+
+    /// A [phf::Map] from the Rust names to the C names
+    StaticMap,
+
+    /// A `fn(rust_name: &str) -> Option<&'static str>` implemented with a `match`,
+    /// which needs no dependency in the crate using it
+    MatchFn,
+
+    /// A `&[(&str, &str)]` static sorted by Rust name, with a `lookup` function doing a binary search in it
+    ///
+    /// Needs neither [phf] nor `alloc`, so it also works in `no_std` crates.
+    /// The static is named after [MappingsCodegen::variable_name] (`RENAMES` if `None`),
+    /// the function is `lookup` or `<variable_name in lower case>_lookup`
+    SortedSlice,
+}
+
+/// Code generated by [MappingsCodegen::generate_all]
+#[derive(Debug, Clone)]
+pub struct GeneratedArtifacts {
+    /// The [CodegenOutput::StaticMap] code
+    pub static_map: TokenStream,
+
+    /// The raw toml rename rules, without the section header
+    pub toml: String,
+
+    /// The lookup table of the stable names of the anonymous types,
+    /// empty unless asked for with [MappingsCodegen::stable_anonymous_names]
+    pub stable_names: String,
+}
+
+/// Code builder used to export mappings by generating [TokenStream]s
+///
+/// `'a` is the lifetime of the [MappingsCodegen::variable_name] and of the mappings when they are borrowed,
+/// see [MappingsCodegen::borrowed]
+#[derive(Debug, Clone)]
+pub struct MappingsCodegen<'a> {
+    /// Mappings used to generate code, owned or borrowed
+    mappings: Cow<'a, NameMappings>,
+
+    /// Settings used to pick the C name of the types
+    /// see [MappingsCodegen::force_aliases_use] and [MappingsCodegen::namespace_style]
+    options: RenameOptions,
+
+    /// see [MappingsCodegen::output]
+    output: CodegenOutput,
+
+    /// see [MappingsCodegen::with_kind]
+    with_kind: bool,
+
+    /// see [MappingsCodegen::bidirectional]
+    bidirectional: bool,
+
+    /// see [MappingsCodegen::assert_unique_targets]
+    assert_unique_targets: bool,
+
+    /// see [MappingsCodegen::split_by_kind]
+    split_by_kind: bool,
+
+    /// see [MappingsCodegen::partition_by]
+    partition_by: Option<MappingClassifier>,
+
+    /// see [MappingsCodegen::roots]
+    roots: Option<Vec<String>>,
+
+    /// see [MappingsCodegen::static_map_filter]
+    static_map_filter: Option<MappingPredicate>,
+
+    /// see [MappingsCodegen::toml_filter]
+    toml_filter: Option<MappingPredicate>,
+
+    /// see [MappingsCodegen::with_source_hash]
+    with_source_hash: bool,
+
+    /// see [MappingsCodegen::annotate_original_names]
+    annotate_original_names: bool,
+
+    /// see [MappingsCodegen::phf_crate_path]
+    phf_crate_path: Option<String>,
+
+    /// see [MappingsCodegen::output_format]
+    output_format: OutputFormat,
+
+    /// see [MappingsCodegen::cfg]
+    cfg: Option<TokenStream>,
+
+    /// see [MappingsCodegen::stable_anonymous_names]
+    stable_anonymous_names: Option<String>,
+
+    /// see [MappingsCodegen::variable_name]
+    variable_name: Option<&'a str>,
+}
+
+impl From<NameMappings> for MappingsCodegen<'_> {
+    fn from(value: NameMappings) -> Self {
+        MappingsCodegen::new(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<&'a NameMappings> for MappingsCodegen<'a> {
+    fn from(value: &'a NameMappings) -> Self {
+        MappingsCodegen::new(Cow::Borrowed(value))
+    }
+}
+
+impl From<MappingsCodegen<'_>> for NameMappings {
+    fn from(value: MappingsCodegen) -> Self {
+        value.mappings.into_owned()
+    }
+}
+
+impl<'a> MappingsCodegen<'a> {
+    /// The builder with the default settings
+    fn new(mappings: Cow<'a, NameMappings>) -> Self {
+        Self {
+            mappings,
+            options: RenameOptions::default(),
+            output: CodegenOutput::default(),
+            with_kind: false,
+            bidirectional: false,
+            assert_unique_targets: false,
+            split_by_kind: false,
+            partition_by: None,
+            roots: None,
+            static_map_filter: None,
+            toml_filter: None,
+            with_source_hash: false,
+            annotate_original_names: false,
+            phf_crate_path: None,
+            output_format: OutputFormat::default(),
+            cfg: None,
+            stable_anonymous_names: None,
+            variable_name: None,
+        }
+    }
+
+    /// Borrows the mappings instead of consuming them like [NameMappings::codegen],
+    /// e.g.: to also export them with [NameMappings::to_cbindgen_toml_renames] or a [crate::export::Template]
+    pub fn borrowed(mappings: &'a NameMappings) -> Self {
+        mappings.into()
+    }
+
+    /// Unwrap back into a [NameMappings], loses the settings of the [MappingsCodegen]
+    /// see [MappingsCodegen::with_mappings] to keep them
+    ///
+    /// Borrowed mappings (see [MappingsCodegen::borrowed]) are cloned
+    pub fn mappings(self) -> NameMappings {
+        self.into()
+    }
+
+    /// Swap the mappings used to generate code, keeping all the settings of the [MappingsCodegen]
+    /// e.g.: to re-wrap mappings tweaked after [MappingsCodegen::mappings] without configuring the builder again
+    pub fn with_mappings(mut self, mappings: NameMappings) -> Self {
+        self.mappings = Cow::Owned(mappings);
+        self
+    }
+
+    /// Should we use the first (by [`BTreeSet<String>`] ordering) known alias of the types
+    /// as the C name of the types
+    /// e.g.: `struct MyStruct` with a `typedef struct MyStruct AliasOfMyStruct` will be known as `AliasOfMyStruct`
+    ///
+    /// default: false
+    pub fn force_aliases_use(&mut self, will: bool) -> &mut Self {
+        self.options.force_aliases_use = will;
+        self
+    }
+
+    /// Decide for each type whether it uses one of its aliases as its C name, like [MappingsCodegen::force_aliases_use]
+    /// e.g.: `|mapping| mapping.kind == CompositeKind::Struct` to only rename the structs to their typedef
+    ///
+    /// Overrides [MappingsCodegen::force_aliases_use], the alias is picked by the [MappingsCodegen::alias_selector]
+    ///
+    /// default: None, [MappingsCodegen::force_aliases_use] is used for every type
+    pub fn force_aliases_when(
+        &mut self,
+        predicate: impl Fn(&NameMapping) -> bool + 'static,
+    ) -> &mut Self {
+        self.options.force_aliases_when = Some(MappingPredicate::new(predicate));
+        self
+    }
+
+    /// Which alias is used when a type is renamed to one of its aliases, see [AliasSelector]
+    ///
+    /// default: [AliasSelector::First]
+    pub fn alias_selector(&mut self, selector: AliasSelector) -> &mut Self {
+        self.options.alias_selector = selector;
+        self
+    }
+
+    /// Should the instantiations of C++ templates (see [NameMapping::is_monomorphization]) get rename rules
+    /// cbindgen rejects the names with template arguments (`struct Box<int>`), so they are left out by default.
+    /// Keep them when they are named after an alias, e.g.: with [MappingsCodegen::force_aliases_use]
+    /// and a `typedef Box<int> IntBox;`
+    ///
+    /// default: false
+    pub fn keep_monomorphizations(&mut self, will: bool) -> &mut Self {
+        self.options.keep_monomorphizations = will;
+        self
+    }
+
+    /// Where the C name of each type comes from, as a chain of [NameSource]s tried in order until one yields a name
+    /// e.g.: an alias matching `_t$`, else the original name, else any alias:
+    /// `&[NameSource::AliasMatching(Regex::new("_t$")?), NameSource::Original, NameSource::Alias(AliasSelector::First)]`
+    ///
+    /// The types none of them names are handled by [MappingsCodegen::unnamed_types].
+    /// Overrides [MappingsCodegen::force_aliases_use], [MappingsCodegen::force_aliases_when] and
+    /// [MappingsCodegen::alias_selector], which are the same as `&[Original, Alias(selector)]`,
+    /// or `&[Alias(selector), Original]` when the aliases are forced
+    ///
+    /// default: empty, the chain of [MappingsCodegen::force_aliases_use] is used
+    pub fn name_sources(&mut self, sources: &[NameSource]) -> &mut Self {
+        self.options.name_sources = sources.to_vec();
+        self
+    }
+
+    /// How the namespaces of C++ types are joined to their name
+    ///
+    /// default: [NamespaceStyle::Qualified]
+    pub fn namespace_style(&mut self, style: NamespaceStyle) -> &mut Self {
+        self.options.namespace_style = style;
+        self
+    }
+
+    /// Override the tag keywords prepended to the C names (`struct `, `union `, ...)
+    /// e.g.: an empty struct prefix to address structs by their bare name
+    ///
+    /// default: None, the keywords of the [NameMappings::language] are used
+    pub fn tag_prefixes(&mut self, prefixes: Option<TagPrefixes>) -> &mut Self {
+        self.options.tag_prefixes = prefixes;
+        self
+    }
+
+    /// Never prepend a tag keyword to the C names, even the ones of named types without alias
+    /// e.g.: `Foo` instead of `struct Foo`, for the C APIs only using typedefs
+    ///
+    /// The tag keywords already in the names are still removed (`union Foo` is `Foo`).
+    /// Overrides [MappingsCodegen::tag_prefixes], same as [TagPrefixes::none]
+    ///
+    /// default: false
+    pub fn no_tag_prefix(&mut self, will: bool) -> &mut Self {
+        self.options.no_tag_prefix = will;
+        self
+    }
+
+    /// Case convention applied to the C names of the rename rules, after their tag keyword
+    /// e.g.: `struct MyType` is renamed to `struct my_type` with [CaseStyle::Snake]
+    ///
+    /// default: None, the C names are kept as-is
+    pub fn case_style(&mut self, style: Option<CaseStyle>) -> &mut Self {
+        self.options.case_style = style;
+        self
+    }
+
+    /// Prefix removed from the Rust names used as keys of the rename rules, the mappings are left untouched
+    /// e.g.: `ffi_bgp_peer` is the `bgp_peer` key with the `ffi_` prefix
+    ///
+    /// Fails the generation with [Error::RenameKeyCollision] if two keys become the same once stripped
+    ///
+    /// default: "", nothing is stripped
+    pub fn strip_rust_prefix(&mut self, prefix: &str) -> &mut Self {
+        self.options.strip_rust_prefix = prefix.to_string();
+        self
+    }
+
+    /// Suffix removed from the Rust names used as keys of the rename rules,
+    /// same as [MappingsCodegen::strip_rust_prefix]
+    ///
+    /// default: "", nothing is stripped
+    pub fn strip_rust_suffix(&mut self, suffix: &str) -> &mut Self {
+        self.options.strip_rust_suffix = suffix.to_string();
+        self
+    }
+
+    /// Rewrite the Rust names used as keys of the rename rules, after [MappingsCodegen::strip_rust_prefix]
+    /// and [MappingsCodegen::strip_rust_suffix], e.g.: to match the paths cbindgen expects
+    /// `|key| key.trim_start_matches("ffi::").to_string()` turns the `ffi::Foo` key into `Foo`
+    ///
+    /// Only the keys are rewritten, the C names and the mappings are left untouched.
+    /// Fails the generation with [Error::RenameKeyCollision] if two keys become the same once rewritten
+    ///
+    /// default: None, the keys are kept as-is
+    pub fn map_rust_name(&mut self, mapper: impl Fn(&str) -> String + 'static) -> &mut Self {
+        self.options.map_rust_name = Some(KeyMapper::new(mapper));
+        self
+    }
+
+    /// Also generate a lookup table from stable names to the types that bindgen named `_bindgen_ty_N`,
+    /// in [GeneratedArtifacts::stable_names], see [NameMappings::to_stable_names_toml]
+    ///
+    /// bindgen numbers its anonymous types across all the items, so the numbers change with its version or with
+    /// an edit anywhere in the headers. The stable names only depend on the header: `<prefix><kind>_<index>`,
+    /// e.g.: `anon_struct_0` with the `anon_` prefix, with the position of the type among the anonymous types
+    /// of the same kind in declaration order (see [NameMapping::order]).
+    /// The rename rules keep bindgen's names, which are the ones of the Rust types
+    ///
+    /// default: None, no lookup table
+    pub fn stable_anonymous_names(&mut self, prefix: Option<&str>) -> &mut Self {
+        self.stable_anonymous_names = prefix.map(str::to_string);
+        self
+    }
+
+    /// The `prefix` configured in cbindgen's `[export]` table, removed from the C names of the rename rules
+    /// so that cbindgen doesn't apply it twice
+    /// e.g.: with `prefix = "my_"`, `struct my_foo` is renamed to `struct foo` and cbindgen outputs `struct my_foo`
+    ///
+    /// The names that don't start with the prefix are left as-is.
+    /// Not needed if cbindgen's `renaming_overrides_prefixing` is set
+    ///
+    /// default: None, the C names are kept as-is
+    pub fn prefix_awareness(&mut self, prefix: Option<String>) -> &mut Self {
+        self.options.prefix_awareness = prefix;
+        self
+    }
+
+    /// What to do with the anonymous types that can't be named, see [UnnamedTypePolicy]
+    /// e.g.: an anonymous struct used in a function signature still needs a name for cbindgen to export it
+    ///
+    /// default: [UnnamedTypePolicy::Skip]
+    pub fn unnamed_types(&mut self, policy: UnnamedTypePolicy) -> &mut Self {
+        self.options.unnamed_types = policy;
+        self
+    }
+
+    /// Only keep the types matching the predicate in the generated code, whatever the output
+    /// e.g.: `|mapping| mapping.source.is_some()` to leave out the types of unknown headers
+    ///
+    /// Applied before [MappingsCodegen::static_map_filter] and [MappingsCodegen::toml_filter]:
+    /// a type must pass both the general filter and the one of the output to be kept.
+    /// The filtered out types can't cause an [Error::RenameKeyCollision]
+    ///
+    /// default: None, all the types are kept
+    pub fn filter(&mut self, predicate: impl Fn(&NameMapping) -> bool + 'static) -> &mut Self {
+        self.options.filter = Some(MappingPredicate::new(predicate));
+        self
+    }
+
+    /// Only keep the types matching the predicate in the runtime lookups:
+    /// the [CodegenOutput::StaticMap], [CodegenOutput::MatchFn] and [CodegenOutput::SortedSlice] outputs
+    /// e.g.: to leave out of the map the types handled specially at runtime, but still rename them in the toml
+    ///
+    /// Narrows [MappingsCodegen::filter], also applies to the map of [MappingsCodegen::generate_all]
+    ///
+    /// default: None, all the types kept by [MappingsCodegen::filter]
+    pub fn static_map_filter(
+        &mut self,
+        predicate: impl Fn(&NameMapping) -> bool + 'static,
+    ) -> &mut Self {
+        self.static_map_filter = Some(MappingPredicate::new(predicate));
+        self
+    }
+
+    /// Only keep the types matching the predicate in the [CodegenOutput::TomlString] output,
+    /// same as [MappingsCodegen::static_map_filter]
+    ///
+    /// default: None, all the types kept by [MappingsCodegen::filter]
+    pub fn toml_filter(&mut self, predicate: impl Fn(&NameMapping) -> bool + 'static) -> &mut Self {
+        self.toml_filter = Some(MappingPredicate::new(predicate));
+        self
+    }
+
+    /// Should we export the code as a [Map]
+    /// if `false` (by default) the code generated is a static raw str in a toml format
+    /// without the section header to let you use it where you want
+    ///
+    /// default: false
+    pub fn as_static_map(&mut self, will: bool) -> &mut Self {
+        self.output = if will {
+            CodegenOutput::StaticMap
+        } else {
+            CodegenOutput::TomlString
+        };
+        self
+    }
+
+    /// Shape of the generated code, see [CodegenOutput]
+    /// With [CodegenOutput::MatchFn], [MappingsCodegen::variable_name] is the name of the function (`rename` if `None`)
+    ///
+    /// default: [CodegenOutput::TomlString]
+    pub fn output(&mut self, output: CodegenOutput) -> &mut Self {
+        self.output = output;
+        self
+    }
+
+    /// Should the values of the [CodegenOutput::StaticMap] also hold the kind of the types
+    /// e.g.: `("struct foo", CKind::Struct)` instead of `"struct foo"`, to let you render the tag yourself
+    ///
+    /// The `CKind` enum (see [MappingsCodegen::kind_enum]) is generated along the
+    /// [MappingsCodegen::variable_name] static, and must be in scope if there is none.
+    /// Ignored by the other [CodegenOutput]s
+    ///
+    /// default: false
+    pub fn with_kind(&mut self, will: bool) -> &mut Self {
+        self.with_kind = will;
+        self
+    }
+
+    /// Should the [CodegenOutput::StaticMap] come with its inverse, from the C names and aliases to the Rust names
+    ///
+    /// The inverse map is the `<variable_name>_REVERSE` static, or the second element of a
+    /// `(map, reverse)` tuple without a [MappingsCodegen::variable_name].
+    /// Ignored by the other [CodegenOutput]s
+    ///
+    /// default: false
+    pub fn bidirectional(&mut self, will: bool) -> &mut Self {
+        self.bidirectional = will;
+        self
+    }
+
+    /// Should the [CodegenOutput::StaticMap] come with a compile-time assertion that no two Rust names
+    /// are renamed to the same C name, e.g.: for the maps used for reverse translations
+    ///
+    /// The assertion is a `const _: () = { .. };` item checking the sorted C names, so it fails the build
+    /// of the crate including the code whenever a duplicate C name is added to it:
+    /// ```compile_fail
+    /// const _: () = {
+    ///     const TARGETS: &[&str] = &["peer_t", "peer_t"];
+    ///     let mut i = 1;
+    ///     while i < TARGETS.len() {
+    ///         let (a, b) = (TARGETS[i - 1].as_bytes(), TARGETS[i].as_bytes());
+    ///         let mut same = a.len() == b.len();
+    ///         let mut j = 0;
+    ///         while same && j < a.len() {
+    ///             same = a[j] == b[j];
+    ///             j += 1;
+    ///         }
+    ///         assert!(!same, "two Rust names are renamed to the same C name");
+    ///         i += 1;
+    ///     }
+    /// };
+    /// ```
+    /// Needs a [MappingsCodegen::variable_name] since it is an item, ignored by the other [CodegenOutput]s
+    ///
+    /// default: false
+    pub fn assert_unique_targets(&mut self, will: bool) -> &mut Self {
+        self.assert_unique_targets = will;
+        self
+    }
+
+    /// Should the types of each [CompositeKind] be generated apart, in their own static (or function)
+    ///
+    /// The statics are named after the [MappingsCodegen::variable_name] (`RENAMES` if `None`) with the
+    /// plural of their kind: `<NAME>_STRUCTS`, `<NAME>_UNIONS`, `<NAME>_ENUMS`, etc.
+    /// The functions of [CodegenOutput::MatchFn] are suffixed the same way in lower case (`rename_structs`).
+    /// Only the kinds present in the mappings are generated
+    ///
+    /// default: false
+    pub fn split_by_kind(&mut self, will: bool) -> &mut Self {
+        self.split_by_kind = will;
+        self
+    }
+
+    /// Should the types be generated apart by the key the `classifier` gives them, in one static (or function) per key
+    /// e.g.: to group them by originating header or by naming convention
+    ///
+    /// The statics are named after the [MappingsCodegen::variable_name] (`RENAMES` if `None`) with the upper-cased key,
+    /// whose characters that can't be in an identifier are replaced by `_`: `<NAME>_<KEY>` (`RENAMES_BGP_H` for `bgp.h`).
+    /// The functions of [CodegenOutput::MatchFn] are suffixed the same way in lower case (`rename_bgp_h`).
+    /// The keys giving the same suffix are generated together, in the order of the suffixes.
+    /// Overrides [MappingsCodegen::split_by_kind]
+    ///
+    /// default: None
+    pub fn partition_by(
+        &mut self,
+        classifier: impl Fn(&NameMapping) -> String + 'static,
+    ) -> &mut Self {
+        self.partition_by = Some(MappingClassifier::new(classifier));
+        self
+    }
+
+    /// Only generate the types named (by their Rust name) in `rust_names`, and the ones they use directly
+    /// or through other types, e.g.: to only rename the types of a public API and not the whole system headers
+    ///
+    /// The types used are read from [NameMappings::dependencies], see [DiscoveryOptions::capture_dependencies].
+    /// Applies to every output, on top of the filters. The names that aren't of any type are ignored
+    ///
+    /// default: None, every type is generated
+    pub fn roots(&mut self, rust_names: &[&str]) -> &mut Self {
+        self.roots = Some(rust_names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// The types reachable from the [MappingsCodegen::roots], or `None` if there are no roots
+    fn reachable_types(&self) -> Option<Vec<&NameMapping>> {
+        let roots = self.roots.as_ref()?;
+        let mut reachable = BTreeSet::new();
+        for (id, mapping) in &self.mappings.types {
+            if roots.contains(&mapping.rust_name) {
+                reachable.insert(*id);
+                reachable.extend(self.mappings.dependencies_of(*id));
+            }
+        }
+
+        Some(
+            reachable
+                .iter()
+                .filter_map(|id| self.mappings.types.get(id))
+                .collect(),
+        )
+    }
+
+    /// Path of the [phf] crate in the generated [CodegenOutput::StaticMap] code, for its type and its value
+    /// e.g.: `crate::reexports::phf` if the crate using the code re-exports phf
+    ///
+    /// default: `phf` (the values are built with `::phf`)
+    pub fn phf_crate_path(&mut self, path: &str) -> &mut Self {
+        self.phf_crate_path = Some(path.to_string());
+        self
+    }
+
+    /// Line endings and trailing newline of the code written by [MappingsCodegen::write_to]
+    ///
+    /// default: [OutputFormat::default], LF with a single trailing newline
+    pub fn output_format(&mut self, format: OutputFormat) -> &mut Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Should the generated code start with a `pub const SOURCE_HASH: &str` holding the
+    /// [NameMappings::source_hash] of the mappings, documented with a `source-hash: <hex>` comment
+    /// e.g.: to let the CI compare it with a freshly computed hash and fail if the committed file is outdated
+    ///
+    /// A [TokenStream] can't hold `//` comments, so the comment is only written by the methods writing text,
+    /// e.g.: [MappingsCodegen::write_to], where a CI check can grep it. Only added when the generated code is made of items (a [MappingsCodegen::variable_name] is set,
+    /// with [CodegenOutput::MatchFn], [CodegenOutput::SortedSlice], [MappingsCodegen::split_by_kind]
+    /// or [MappingsCodegen::partition_by]), since a bare value can't be preceded by a const
+    ///
+    /// default: false
+    pub fn with_source_hash(&mut self, will: bool) -> &mut Self {
+        self.with_source_hash = will;
+        self
+    }
+
+    /// Should the entries of the [CodegenOutput::MatchFn] and [CodegenOutput::SortedSlice] outputs whose C name
+    /// is not the original tagged name of their type (e.g.: an alias picked by [MappingsCodegen::force_aliases_use])
+    /// be followed by a `// <original name>` comment, e.g.: `"bmp_peer" => Some("bmp_peer_t"), // struct bmp_peer`
+    ///
+    /// A [TokenStream] can't hold `//` comments, so they are only in the text written by [MappingsCodegen::write_to],
+    /// [MappingsCodegen::write_to_file] and [MappingsCodegen::write_region_to_file], and compared by
+    /// [MappingsCodegen::diff_against_file]. The other outputs are left as-is
+    ///
+    /// default: false
+    pub fn annotate_original_names(&mut self, will: bool) -> &mut Self {
+        self.annotate_original_names = will;
+        self
+    }
+
+    /// Name of the static variable used to store the exported value in the generated code
+    /// If `None`, the generated code will just be the value, without a variable assignment
+    /// It must be a Rust identifier and not a keyword, or the generation fails with [Error::InvalidVariableName]
+    ///
+    /// default: None
+    pub fn variable_name(&mut self, variable_name: Option<&'a str>) -> &mut Self {
+        self.variable_name = if variable_name.is_some() && variable_name.unwrap() == "" {
+            None
+        } else {
+            variable_name
+        };
+
+        self
+    }
+
+    /// Condition of a `#[cfg(..)]` attribute put on each generated item, e.g.: `quote! { feature = "ffi" }`
+    /// to only have the rename map with the `ffi` feature of the crate
+    ///
+    /// It must be a cfg predicate (`unix`, `feature = "ffi"`, `all(unix, not(test))`), or the generation fails with
+    /// [Error::InvalidCfg]. Only applied when the generated code is made of items (see [MappingsCodegen::with_source_hash]),
+    /// since a bare value can't hold an attribute
+    ///
+    /// default: None
+    pub fn cfg(&mut self, predicate: Option<TokenStream>) -> &mut Self {
+        self.cfg = predicate;
+        self
+    }
+
+    /// The `#[cfg(..)]` attribute of the generated items, see [MappingsCodegen::cfg]
+    fn cfg_attribute(&self) -> Option<TokenStream> {
+        self.cfg
+            .as_ref()
+            .map(|predicate| quote! { #[cfg(#predicate)] })
+    }
+
+    /// Fails with [Error::InvalidCfg] if the [MappingsCodegen::cfg] isn't a cfg predicate
+    fn check_cfg(&self) -> Result<()> {
+        match &self.cfg {
+            Some(predicate) if !is_cfg_predicate(predicate.clone()) => {
+                Err(Error::InvalidCfg(predicate.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails with [Error::InvalidVariableName] if the [MappingsCodegen::variable_name] can't name an item:
+    /// not a Rust identifier (`my-var`, `2nd`) or a keyword (`static`)
+    fn check_variable_name(&self) -> Result<()> {
+        let Some(name) = self.variable_name else {
+            return Ok(());
+        };
+
+        let mut chars = name.chars();
+        let is_identifier = chars
+            .next()
+            .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+            && name != "_";
+        if !is_identifier || RUST_KEYWORDS.contains(&name) {
+            return Err(Error::InvalidVariableName(name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Generate a [TokenStream] based on all the parameters set on [Self]
+    ///
+    /// Fails with [Error::InvalidVariableName] if the [MappingsCodegen::variable_name] can't name an item
+    pub fn generate(&self) -> Result<TokenStream> {
+        self.check_variable_name()?;
+        self.check_cfg()?;
+        let rules = self.output_rules(self.output, self.mappings.rename_rules(&self.options)?);
+        if !self.split_by_kind && self.partition_by.is_none() {
+            let source_hash = self.source_hash_const(self.generates_items());
+            let kind_enum = self.kind_enum_for(self.output, self.variable_name);
+            let value = self.generate_from_rules(self.output, &rules, self.variable_name)?;
+            return Ok(quote! {
+                #source_hash
+                #kind_enum
+                #value
+            });
+        }
+
+        let partitions: Vec<(String, Vec<RenameRule>)> = match &self.partition_by {
+            Some(classifier) => {
+                let mut by_key: BTreeMap<String, Vec<RenameRule>> = BTreeMap::new();
+                for rule in rules {
+                    let suffix: String = classifier
+                        .classify(rule.mapping)
+                        .chars()
+                        .map(|c| match c.is_alphanumeric() {
+                            true => c.to_ascii_uppercase(),
+                            false => '_',
+                        })
+                        .collect();
+                    by_key.entry(suffix).or_default().push(rule);
+                }
+                by_key.into_iter().collect()
+            }
+            None => {
+                let mut by_kind: BTreeMap<CompositeKind, Vec<RenameRule>> = BTreeMap::new();
+                for rule in rules {
+                    by_kind.entry(rule.mapping.kind).or_default().push(rule);
+                }
+                by_kind
+                    .into_iter()
+                    .map(|(kind, rules)| {
+                        let suffix = match kind {
+                            CompositeKind::Struct => "STRUCTS",
+                            CompositeKind::Union => "UNIONS",
+                            CompositeKind::Class => "CLASSES",
+                            CompositeKind::Enum => "ENUMS",
+                            CompositeKind::ScopedEnum => "SCOPED_ENUMS",
+                            CompositeKind::Typedef => "TYPEDEFS",
+                        };
+                        (suffix.to_string(), rules)
+                    })
+                    .collect()
+            }
+        };
+
+        let match_fn = self.output == CodegenOutput::MatchFn;
+        let base_name = self
+            .variable_name
+            .unwrap_or(if match_fn { "rename" } else { "RENAMES" });
+
+        let mut code = self
+            .source_hash_const(self.generates_items())
+            .unwrap_or_default();
+        code.extend(self.kind_enum_for(self.output, Some(base_name)));
+        for (suffix, rules) in partitions {
+            let name = if match_fn {
+                format!("{base_name}_{}", suffix.to_lowercase())
+            } else {
+                format!("{base_name}_{suffix}")
+            };
+
+            code.extend(self.generate_from_rules(self.output, &rules, Some(&name))?);
+        }
+
+        Ok(code)
+    }
+
+    /// The text of the code of [MappingsCodegen::generate], with the [MappingsCodegen::annotate_original_names] comments
+    fn generate_text(&self) -> Result<String> {
+        let mut code = self.generate()?.to_string();
+        if self.annotate_original_names
+            && matches!(
+                self.output,
+                CodegenOutput::MatchFn | CodegenOutput::SortedSlice
+            )
+        {
+            let rules = self.output_rules(self.output, self.mappings.rename_rules(&self.options)?);
+            for rule in &rules {
+                let original = NameMapping::validated_original_name(
+                    rule.mapping.c_name.as_ref(),
+                    rule.mapping.kind,
+                );
+                let Some(original) = original.filter(|original| *original != rule.c_name) else {
+                    continue;
+                };
+
+                // the keys are unique, so is the text of their entry
+                let entry = Self::entry(self.output, rule).to_string();
+                code = code.replacen(&entry, &format!("{entry} // {original}\n"), 1);
+            }
+        }
+
+        if self.with_source_hash && self.generates_items() {
+            code.insert_str(
+                0,
+                &format!("// source-hash: {}\n", self.mappings.source_hash()),
+            );
+        }
+
+        Ok(code)
+    }
+
+    /// Write the code of [MappingsCodegen::generate] with the [MappingsCodegen::output_format]
+    pub fn write_to<W: std::io::Write>(&self, mut writer: W) -> Result<()> {
+        let code = self.output_format.apply(&self.generate_text()?);
+        writer.write_all(code.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Same as [MappingsCodegen::write_to], to the file at `path` (replaced if it exists)
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.write_to(std::fs::File::create(path)?)
+    }
+
+    /// Write the code of [MappingsCodegen::generate] between the markers of the hand-maintained file at `path`,
+    /// leaving the rest of the file untouched, see [replace_region]
+    ///
+    /// The markers are added according to the `placement` if the file doesn't have them yet,
+    /// and the file is created if it doesn't exist. Only the generated code follows the [MappingsCodegen::output_format]
+    pub fn write_region_to_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        placement: &RegionPlacement,
+    ) -> Result<()> {
+        let current = match std::fs::read_to_string(&path) {
+            Ok(current) => current,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+        let code = self.output_format.apply(&self.generate_text()?);
+        std::fs::write(path, replace_region(&current, &code, placement)?)?;
+
+        Ok(())
+    }
+
+    /// Write the rename rules as cargo metadata lines, for the build scripts of the dependent crates
+    ///
+    /// The rules are the ones of the toml (see [MappingsCodegen::toml_filter]), sorted by key:
+    /// - `cargo:rename_<key>=<C name>` for each rule, e.g.: `cargo:rename_bmp_peer=struct bmp_peer`
+    /// - `cargo:renames=<key>,<key>,...` listing the keys, in their original case
+    ///
+    /// If the crate has a `links = "<name>"` key, its dependents read them as the environment variables
+    /// `DEP_<NAME>_RENAME_<KEY>` and `DEP_<NAME>_RENAMES`, upper-cased by cargo, see [MappingsCodegen::emit_cargo_metadata]
+    pub fn write_cargo_metadata<W: std::io::Write>(&self, mut writer: W) -> Result<()> {
+        let rules = self.output_rules(
+            CodegenOutput::TomlString,
+            self.mappings.rename_rules(&self.options)?,
+        );
+
+        for rule in &rules {
+            writeln!(writer, "cargo:rename_{}={}", rule.key, rule.c_name)?;
+        }
+        let keys: Vec<&str> = rules.iter().map(|rule| rule.key.as_str()).collect();
+        writeln!(writer, "cargo:renames={}", keys.join(","))?;
+
+        Ok(())
+    }
+
+    /// Prints the lines of [MappingsCodegen::write_cargo_metadata], so this must be called from a build script
+    /// (`build.rs`) of a crate with a `links` key
+    pub fn emit_cargo_metadata(&self) -> Result<()> {
+        self.write_cargo_metadata(std::io::stdout().lock())
+    }
+
+    /// Compares the code [MappingsCodegen::generate] would output with the content of the file at `path`,
+    /// without writing anything
+    /// e.g.: to fail a test when the committed generated file was not regenerated
+    ///
+    /// Returns `None` if they are identical (ignoring trailing whitespace), or a line diff otherwise:
+    /// `-` for the lines only in the file and `+` for the lines only in the generated code
+    pub fn diff_against_file<P: AsRef<Path>>(&self, path: P) -> Result<Option<String>> {
+        let generated = self.generate_text()?;
+        let current = std::fs::read_to_string(path)?;
+
+        if generated.trim_end() == current.trim_end() {
+            return Ok(None);
+        }
+
+        Ok(Some(line_diff(current.trim_end(), generated.trim_end())))
+    }
+
+    /// Generate both the [CodegenOutput::StaticMap] code and the raw toml rename rules,
+    /// ignoring [MappingsCodegen::output]
+    ///
+    /// The names are selected once for both, so they are guaranteed to agree.
+    /// Each one only holds the types kept by its own filter,
+    /// see [MappingsCodegen::static_map_filter] and [MappingsCodegen::toml_filter]
+    pub fn generate_all(&self) -> Result<GeneratedArtifacts> {
+        self.check_variable_name()?;
+        self.check_cfg()?;
+        let rules = self.mappings.rename_rules(&self.options)?;
+        let map_rules = self.output_rules(CodegenOutput::StaticMap, rules.clone());
+        let toml_rules = self.output_rules(CodegenOutput::TomlString, rules);
+
+        let source_hash = self.source_hash_const(self.variable_name.is_some());
+        let kind_enum = self.kind_enum_for(CodegenOutput::StaticMap, self.variable_name);
+        let static_map =
+            self.generate_from_rules(CodegenOutput::StaticMap, &map_rules, self.variable_name)?;
+
+        let stable_names = match &self.stable_anonymous_names {
+            Some(prefix) => self.mappings.to_stable_names_toml(prefix)?,
+            None => String::new(),
+        };
+
+        Ok(GeneratedArtifacts {
+            static_map: quote! {
+                #source_hash
+                #kind_enum
+                #static_map
+            },
+            toml: toml_renames(&toml_rules)?,
+            stable_names,
+        })
+    }
+
+    /// The rules kept by the filter of the given output and the [MappingsCodegen::roots],
+    /// see [MappingsCodegen::static_map_filter] and [MappingsCodegen::toml_filter]
+    fn output_rules<'rules>(
+        &self,
+        output: CodegenOutput,
+        mut rules: Vec<RenameRule<'rules>>,
+    ) -> Vec<RenameRule<'rules>> {
+        let filter = match output {
+            CodegenOutput::TomlString => &self.toml_filter,
+            CodegenOutput::StaticMap | CodegenOutput::MatchFn | CodegenOutput::SortedSlice => {
+                &self.static_map_filter
+            }
+        };
+
+        if let Some(filter) = filter {
+            rules.retain(|rule| filter.test(rule.mapping));
+        }
+        if let Some(reachable) = self.reachable_types() {
+            rules.retain(|rule| {
+                reachable
+                    .iter()
+                    .any(|mapping| std::ptr::eq(*mapping, rule.mapping))
+            });
+        }
+
+        rules
+    }
+
+    /// Generate the code of the given output for already selected rename rules
+    fn generate_from_rules(
+        &self,
+        output: CodegenOutput,
+        rules: &[RenameRule],
+        variable_name: Option<&str>,
+    ) -> Result<TokenStream> {
+        match output {
+            CodegenOutput::MatchFn => return Ok(self.generate_match_fn(rules, variable_name)),
+            CodegenOutput::SortedSlice => {
+                return Ok(self.generate_sorted_slice(rules, variable_name))
+            }
+            CodegenOutput::TomlString | CodegenOutput::StaticMap => {}
+        }
+
+        let variable_name_ident = variable_name.map(|name| format_ident!("{}", name));
+
+        let as_static_map = output == CodegenOutput::StaticMap;
+        let with_kind = as_static_map && self.with_kind;
+        let phf = self
+            .phf_crate_path
+            .as_deref()
+            .unwrap_or("phf")
+            .parse::<TokenStream>()?;
+        let var_type = if with_kind {
+            quote! {
+                #phf::Map<&'static str, (&'static str, CKind)>
+            }
+        } else if as_static_map {
+            quote! {
+                #phf::Map<&'static str, &'static str>
+            }
+        } else {
+            quote! {
+                &'static str
+            }
+        };
+
+        let mut value = if with_kind {
+            self.build_map(static_kind_map(rules))?
+        } else if as_static_map {
+            self.build_map(static_map(rules))?
+        } else {
+            // the toml text is the content of a str literal, not code
+            Literal::string(&toml_renames(rules)?).into_token_stream()
+        };
+
+        let reverse = if as_static_map && self.bidirectional {
+            Some(self.build_map(reverse_map(rules)?)?)
+        } else {
+            None
+        };
+
+        if let Some(bindings_name) = variable_name_ident {
+            let cfg = self.cfg_attribute();
+            let reverse = reverse.map(|reverse| {
+                let reverse_name = format_ident!("{}_REVERSE", bindings_name);
+                quote! {
+                    #cfg
+                    pub static #reverse_name : #phf::Map<&'static str, &'static str> = #reverse;
+                }
+            });
+            let assertion = (as_static_map && self.assert_unique_targets).then(|| {
+                let assertion = unique_targets_assertion(rules);
+                quote! {
+                    #cfg
+                    #assertion
+                }
+            });
+            value = quote! {
+                #cfg
+                pub static #bindings_name : #var_type = #value;
+                #reverse
+                #assertion
+            };
+        } else if let Some(reverse) = reverse {
+            value = quote! {
+                (#value, #reverse)
+            };
+        }
+
+        Ok(value)
+    }
+
+    /// The code of the map, built with the [MappingsCodegen::phf_crate_path]
+    fn build_map(&self, mut map: Map<String>) -> Result<TokenStream> {
+        if let Some(path) = &self.phf_crate_path {
+            map.phf_path(path);
+        }
+
+        Ok(map.build().to_string().parse::<TokenStream>()?)
+    }
+
+    /// Whether [MappingsCodegen::generate] makes items, which can be preceded by the `SOURCE_HASH` const
+    fn generates_items(&self) -> bool {
+        self.variable_name.is_some()
+            || self.split_by_kind
+            || self.partition_by.is_some()
+            || matches!(
+                self.output,
+                CodegenOutput::MatchFn | CodegenOutput::SortedSlice
+            )
+    }
+
+    /// The `SOURCE_HASH` const to generate before the items of the code, if needed
+    fn source_hash_const(&self, items: bool) -> Option<TokenStream> {
+        if !self.with_source_hash || !items {
+            return None;
+        }
+
+        let hash = self.mappings.source_hash();
+        let cfg = self.cfg_attribute();
+        Some(quote! {
+            #cfg
+            pub const SOURCE_HASH: &str = #hash;
+        })
+    }
+
+    /// The [MappingsCodegen::kind_enum] to generate along the statics of the given output, if needed
+    fn kind_enum_for(
+        &self,
+        output: CodegenOutput,
+        variable_name: Option<&str>,
+    ) -> Option<TokenStream> {
+        let cfg = self.cfg_attribute();
+        (output == CodegenOutput::StaticMap && self.with_kind && variable_name.is_some()).then(
+            || {
+                let kind_enum = Self::kind_enum();
+                quote! {
+                    #cfg
+                    #kind_enum
+                }
+            },
+        )
+    }
+
+    /// The `CKind` enum used in the values of the maps generated [MappingsCodegen::with_kind]
+    pub fn kind_enum() -> TokenStream {
+        quote! {
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub enum CKind {
+                Struct,
+                Union,
+                Class,
+                Enum,
+                ScopedEnum,
+                Typedef,
+            }
+        }
+    }
+
+    /// The entry of a rule in the [CodegenOutput::SortedSlice] static or the arm of the [CodegenOutput::MatchFn] function
+    fn entry(output: CodegenOutput, RenameRule { key, c_name, .. }: &RenameRule) -> TokenStream {
+        match output {
+            CodegenOutput::MatchFn => quote! { #key => Some(#c_name), },
+            _ => quote! { (#key, #c_name), },
+        }
+    }
+
+    /// Generate the [CodegenOutput::SortedSlice] static and its lookup function
+    fn generate_sorted_slice(
+        &self,
+        rules: &[RenameRule],
+        variable_name: Option<&str>,
+    ) -> TokenStream {
+        let slice_name = format_ident!("{}", variable_name.unwrap_or("RENAMES"));
+        let fn_name = match variable_name {
+            Some(name) => format_ident!("{}_lookup", name.to_lowercase()),
+            None => format_ident!("lookup"),
+        };
+
+        // the binary search needs the entries sorted by key, which the rules already are
+        debug_assert!(rules.windows(2).all(|pair| pair[0].key < pair[1].key));
+        let entries = rules
+            .iter()
+            .map(|rule| Self::entry(CodegenOutput::SortedSlice, rule));
+
+        let cfg = self.cfg_attribute();
+        quote! {
+            #cfg
+            pub static #slice_name: &[(&str, &str)] = &[#(#entries)*];
+
+            #cfg
+            pub fn #fn_name(rust_name: &str) -> Option<&'static str> {
+                #slice_name
+                    .binary_search_by_key(&rust_name, |(key, _)| *key)
+                    .ok()
+                    .map(|index| #slice_name[index].1)
+            }
+        }
+    }
+
+    /// Generate the [CodegenOutput::MatchFn] function
+    fn generate_match_fn(&self, rules: &[RenameRule], fn_name: Option<&str>) -> TokenStream {
+        let fn_name = format_ident!("{}", fn_name.unwrap_or("rename"));
+
+        let arms = rules
+            .iter()
+            .map(|rule| Self::entry(CodegenOutput::MatchFn, rule));
+
+        let cfg = self.cfg_attribute();
+        quote! {
+            #cfg
+            pub fn #fn_name(rust_name: &str) -> Option<&'static str> {
+                match rust_name {
+                    #(#arms)*
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+/// Discovers the types of each header of the directory `dir` (and its subdirectories) whose file name
+/// matches `glob` (`*` for any characters, `?` for one, e.g.: `*.h`), one bindgen run per header
+///
+/// `configure` customizes the [bindgen::Builder] of each run (allowlists, clang args, ...), the header and
+/// the callback are set by this function. Each [NameMapping::source] is set to its header.
+///
+/// Types reached from several headers through shared includes are only kept in the one defining them,
+/// see [attribute_shared_types]
+pub fn discover_dir<P: AsRef<Path>>(
+    dir: P,
+    glob: &str,
+    mut configure: impl FnMut(bindgen::Builder) -> bindgen::Builder,
+) -> Result<HashMap<PathBuf, NameMappings>> {
+    let pattern = Regex::new(&format!(
+        "^{}$",
+        regex::escape(glob).replace("\\*", ".*").replace("\\?", ".")
+    ))?;
+
+    let mut headers = Vec::new();
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| pattern.is_match(name))
+            {
+                headers.push(path);
+            }
+        }
+    }
+    headers.sort();
+
+    let mut discovered = Vec::with_capacity(headers.len());
+    for header in headers {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        configure(bindgen::Builder::default())
+            .header(header.to_string_lossy())
+            .parse_callbacks(Box::new(NameMappingsCallback(Rc::clone(&mappings))))
+            .generate()?;
+
+        let mut mappings = mappings.take();
+        for mapping in mappings.types.values_mut() {
+            mapping.source = Some(header.clone());
+        }
+        let text = std::fs::read_to_string(&header)?;
+        discovered.push((header, text, mappings));
+    }
+
+    Ok(attribute_shared_types(discovered))
+}
+
+/// Keeps each type found in several headers in a single one: the first header (in the given order)
+/// whose text looks like it defines it, or the first header it was found in if none does
+///
+/// A header defines a named type if it has its `tag name {` body, a typedef if it has its `typedef .. name;`
+/// declaration, and an anonymous type if it has its `} alias;` typedef.
+/// Types are the same if they have the same kind, C name and Rust name
+pub fn attribute_shared_types(
+    discovered: Vec<(PathBuf, String, NameMappings)>,
+) -> HashMap<PathBuf, NameMappings> {
+    type TypeKey = (CompositeKind, Option<String>, String);
+    let key = |mapping: &NameMapping| -> TypeKey {
+        let c_name = mapping
+            .c_name
+            .as_ref()
+            .map(|c_name| c_name.identifier.clone());
+        (mapping.kind, c_name, mapping.rust_name.clone())
+    };
+    // compiled once per shared type, and only once it is found in a second header
+    let mut definitions: BTreeMap<TypeKey, Option<Regex>> = BTreeMap::new();
+
+    // the header keeping each shared type
+    let mut owners: BTreeMap<TypeKey, usize> = BTreeMap::new();
+    for (index, (_, text, mappings)) in discovered.iter().enumerate() {
+        for mapping in mappings.types.values() {
+            let type_key = key(mapping);
+            let Some(&owner) = owners.get(&type_key) else {
+                owners.insert(type_key, index);
+                continue;
+            };
+
+            let definition = definitions
+                .entry(type_key.clone())
+                .or_insert_with(|| definition_pattern(mapping));
+            let defines = |text: &str| {
+                definition
+                    .as_ref()
+                    .is_some_and(|regex| regex.is_match(text))
+            };
+            if !defines(&discovered[owner].1) && defines(text) {
+                owners.insert(type_key, index);
+            }
+        }
+    }
+
+    discovered
+        .into_iter()
+        .enumerate()
+        .map(|(index, (header, _, mut mappings))| {
+            mappings
+                .types
+                .retain(|_, mapping| owners.get(&key(mapping)) == Some(&index));
+            (header, mappings)
+        })
+        .collect()
+}
+
+/// The pattern matching the definition of the type in a header, see [attribute_shared_types]
+fn definition_pattern(mapping: &NameMapping) -> Option<Regex> {
+    let c_name = mapping.c_name.as_ref()?;
+    let name = regex::escape(&c_name.identifier);
+    let definition = match mapping.kind {
+        _ if c_name.aliased => format!(r"\}}\s*{name}\s*;"),
+        // `typedef uint32_t name;`, `typedef int name[4];` or `typedef void (*name)(int);`
+        CompositeKind::Typedef => format!(r"\btypedef\b[^;]*\b{name}\s*[;)\[]"),
+        kind => format!(
+            r"\b{}\s+{name}\s*\{{",
+            kind.to_string().replace(' ', r"\s+")
+        ),
+    };
+
+    Regex::new(&definition).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use std::cell::RefCell;
-    use std::collections::{BTreeSet, HashMap};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use std::path::PathBuf;
     use std::rc::Rc;
 
-    use bindgen::Builder;
-    use bindgen::callbacks::DiscoveredItemId;
+    use bindgen::Builder;
+    use bindgen::callbacks::{DiscoveredItem, DiscoveredItemId, ParseCallbacks};
+    use proc_macro2::{Literal, TokenStream};
+    use quote::quote;
+    use regex::Regex;
+
+    use crate::Error;
+    use crate::output::{LineEnding, OutputFormat, RegionPlacement};
+    use crate::import::{
+        AliasSelector, CaseStyle, CName, CodegenOutput, Discovery, MappingsCodegen, DiscoveryOptions, GeneratedArtifacts, Language, MapConflict, MappingChange, NameMapping, NameMappings,
+        NameMappingsCallback, NameSource, NameMappingsCallbackBuilder, attribute_shared_types, discover_dir, NamespaceStyle, RenameOptions, TagPrefixes, UnnamedTypePolicy, AliasConflict, AliasConflictPolicy, line_diff, unprefixed_c_name, SYNTHETIC_IDS_START,
+    };
+    use crate::import::CompositeKind::{Class, Enum, ScopedEnum, Struct, Typedef, Union};
+    use crate::testing::{mapping, mapping_with_aliases};
+
+    /// Compiles the code expected from a generation in the `$module` module
+    /// and keeps it as the `$code` string to compare
+    macro_rules! compiled_code {
+        ($module:ident, $code:ident, $($tokens:tt)*) => {
+            #[allow(dead_code)]
+            mod $module {
+                $($tokens)*
+            }
+            const $code: &str = stringify!($($tokens)*);
+        };
+    }
+
+    /// Compares generated code with code from [compiled_code], ignoring whitespace
+    fn assert_same_code(generated: &TokenStream, expected: &str) {
+        let compact = |code: &str| code.split_whitespace().collect::<String>();
+        assert_eq!(compact(&generated.to_string()), compact(expected));
+    }
+
+    #[test]
+    fn pass() {}
+
+    #[test]
+    fn discover_header_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("bindgen-bridge-headers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, text) in [
+            ("common.h", "#pragma once\nstruct common { int z; };\n"),
+            (
+                "bgp.h",
+                "#include \"common.h\"\nstruct bgp_peer { struct common c; };\n",
+            ),
+            (
+                "bmp.h",
+                "#include \"common.h\"\ntypedef struct { struct common c; } bmp_peer_t;\n",
+            ),
+            ("notes.txt", "struct ignored { int i; };\n"),
+        ] {
+            std::fs::write(dir.join(name), text).unwrap();
+        }
+
+        let discovered = discover_dir(&dir, "*.h", |builder| builder).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names = |header: &str| {
+            let mut names: Vec<String> = discovered[&dir.join(header)]
+                .types
+                .values()
+                .map(|mapping| mapping.rust_name.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(discovered.len(), 3);
+        assert_eq!(names("common.h"), vec!["common"]);
+        assert_eq!(names("bgp.h"), vec!["bgp_peer"]);
+        assert_eq!(names("bmp.h"), vec!["bmp_peer_t"]);
+    }
+
+    #[test]
+    fn test_mappings() {
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        Builder::default()
+            .header_contents("sample_header.h","
+                // Unions
+                void function_using_anonymous_struct(struct {} arg0);
+
+                struct NamedStruct {
+                };
+
+                typedef struct NamedStruct AliasOfNamedStruct;
+
+
+                // Unions
+                void function_using_anonymous_union(union {} arg0);
+
+                union NamedUnion {
+                };
+
+                typedef union NamedUnion AliasOfNamedUnion;
+        ")
+            .parse_callbacks(Box::new(NameMappingsCallback(Rc::clone(&mappings))))
+            .generate()
+            .unwrap();
+
+        let expected =  NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1),
+                 NameMapping {
+                     c_name: None,
+                     ..mapping(Struct, "_bindgen_ty_1")
+                 }),
+                (DiscoveredItemId::new(10),
+                 NameMapping {
+                     c_name: None,
+                     order: 3,
+                     ..mapping(Union, "_bindgen_ty_2")
+                 }),
+                (DiscoveredItemId::new(16),
+                 NameMapping {
+                     order: 4,
+                     ..mapping_with_aliases(Union, "NamedUnion", &["AliasOfNamedUnion"])
+                 }),
+                (DiscoveredItemId::new(7),
+                 NameMapping {
+                     order: 1,
+                     ..mapping_with_aliases(Struct, "NamedStruct", &["AliasOfNamedStruct"])
+                 }),
+            ]),
+            aliases: HashMap::default(),
+            discovered_items: 6,
+            ..Default::default()
+        };
+        
+        // bindgen's ids of the typedefs aren't known in advance, but their targets are
+        let mappings = mappings.borrow();
+        let mut targets: Vec<DiscoveredItemId> = mappings.alias_targets.values().copied().collect();
+        targets.sort();
+        assert_eq!(targets, [DiscoveredItemId::new(7), DiscoveredItemId::new(16)]);
+        let expected = NameMappings {
+            alias_targets: mappings.alias_targets.clone(),
+            ..expected
+        };
+
+        assert!(expected.eq(&mappings));
+    }
+
+    #[test]
+    fn codegen() {
+        let mappings = NameMappings::default();
+        let code = mappings
+            .codegen()
+            .variable_name(Some("super_var"))
+            .generate()
+            .unwrap();
+
+        assert_eq!(
+            code.to_string(),
+            "pub static super_var : & 'static str = \"\" ;"
+        );
+
+        let code = NameMappings::default()
+            .codegen()
+            .as_static_map(true)
+            .variable_name(Some("SUPER_VAR"))
+            .generate()
+            .unwrap();
+        assert_same_code(&code, EMPTY_MAP_CODE);
+        assert_eq!(empty_map::SUPER_VAR.len(), 0);
+    }
+
+    compiled_code! { empty_map, EMPTY_MAP_CODE,
+        pub static SUPER_VAR: phf::Map<&'static str, &'static str> = ::phf::Map {
+            key: 12913932095322966823,
+            disps: &[],
+            entries: &[],
+        };
+    }
+
+    #[test]
+    fn raw_identifier_keys() {
+        let mappings = NameMappings {
+            types: HashMap::from([(
+                DiscoveredItemId::new(1),
+                NameMapping {
+                    c_name: Some(CName {
+                        identifier: "type".to_string(),
+                        aliased: false,
+                    }),
+                    ..mapping(Struct, "r#type")
+                },
+            )]),
+            aliases: HashMap::default(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(false).unwrap(),
+            "\"type\" = \"struct type\"\n"
+        );
+
+        let map = mappings.to_static_map(false).unwrap().build().to_string();
+        assert!(map.contains("(\"type\", \"struct type\")"));
+        assert!(!map.contains("r#type"));
+
+        // the stored mapping keeps the raw identifier
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].rust_name,
+            "r#type"
+        );
+    }
+
+    #[test]
+    fn language_tag_prefixes() {
+        let c_name = CName {
+            identifier: "Foo".to_string(),
+            aliased: false,
+        };
+
+        assert_eq!(
+            NameMapping::validated_original_name(Some(&c_name), Union),
+            Some("union Foo".to_string())
+        );
+        assert_eq!(
+            NameMapping::validated_original_name_in(Some(&c_name), Class, Language::C),
+            Some("struct Foo".to_string())
+        );
+        assert_eq!(
+            NameMapping::validated_original_name_in(Some(&c_name), ScopedEnum, Language::C),
+            Some("enum Foo".to_string())
+        );
+        assert_eq!(
+            NameMapping::validated_original_name_in(Some(&c_name), Class, Language::Cxx),
+            Some("Foo".to_string())
+        );
+        assert_eq!(
+            NameMapping::validated_original_name_in(Some(&c_name), Struct, Language::Cxx),
+            Some("Foo".to_string())
+        );
+    }
+
+    #[test]
+    fn pre_tagged_names() {
+        let c_name = |identifier: &str| CName {
+            identifier: identifier.to_string(),
+            aliased: false,
+        };
+
+        for (name, kind, expected) in [
+            ("struct Foo", Struct, "struct Foo"),
+            ("union Foo", Struct, "struct Foo"),
+            ("enum Foo", Union, "union Foo"),
+            ("struct  union Foo", Enum, "enum Foo"),
+            ("structure", Struct, "struct structure"),
+            ("union Foo", Class, "struct Foo"),
+        ] {
+            assert_eq!(
+                NameMapping::validated_original_name(Some(&c_name(name)), kind),
+                Some(expected.to_string()),
+                "{name} as {kind}"
+            );
+        }
+
+        assert_eq!(
+            NameMapping::validated_original_name_in(
+                Some(&c_name("struct Foo")),
+                Struct,
+                Language::Cxx
+            ),
+            Some("Foo".to_string())
+        );
+
+        // aliases are never touched
+        let alias = CName {
+            identifier: "union Foo".to_string(),
+            aliased: true,
+        };
+        assert_eq!(
+            NameMapping::validated_original_name(Some(&alias), Struct),
+            Some("union Foo".to_string())
+        );
+    }
+
+    #[test]
+    fn irregular_tag_whitespace() {
+        let c_name = |identifier: &str| CName {
+            identifier: identifier.to_string(),
+            aliased: false,
+        };
+
+        for (name, kind, expected) in [
+            ("struct  Foo", Struct, "struct Foo"),
+            ("struct\tFoo", Struct, "struct Foo"),
+            (" struct \t Foo ", Struct, "struct Foo"),
+            ("union\nFoo", Union, "union Foo"),
+            ("union \t Foo", Struct, "struct Foo"),
+            ("struct\t union  Foo", Enum, "enum Foo"),
+        ] {
+            assert_eq!(
+                NameMapping::validated_original_name(Some(&c_name(name)), kind),
+                Some(expected.to_string()),
+                "{name:?} as {kind}"
+            );
+        }
+
+        // prefixes without a trailing space are stripped as-is
+        let prefixes = TagPrefixes {
+            struct_prefix: "S_".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            NameMapping::validated_original_name_with(Some(&c_name("S_Foo")), Struct, &prefixes),
+            Some("S_Foo".to_string())
+        );
+    }
+
+    #[test]
+    fn cxx_renames_have_no_tag() {
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Class, "Foo"))]),
+            aliases: HashMap::default(),
+            language: Language::Cxx,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(false).unwrap(),
+            "\"Foo\" = \"Foo\"\n"
+        );
+    }
+
+    #[test]
+    fn cxx_namespaces() {
+        // what bindgen reports for `namespace foo { namespace bar { struct Baz {}; } }`
+        // and for `namespace foo { namespace { struct Hidden {}; } }`
+        let mappings = Rc::new(RefCell::new(NameMappings {
+            language: Language::Cxx,
+            ..Default::default()
+        }));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("foo::bar::Baz".to_string()),
+                final_name: "foo_bar_Baz".to_string(),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(2),
+            DiscoveredItem::Struct {
+                original_name: Some("foo::(anonymous namespace)::Hidden".to_string()),
+                final_name: "foo_Hidden".to_string(),
+            },
+        );
+
+        let mappings = mappings.take();
+        let baz = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(baz.namespace, vec!["foo".to_string(), "bar".to_string()]);
+        assert_eq!(baz.c_name.as_ref().unwrap().identifier, "Baz");
+
+        let hidden = &mappings.types[&DiscoveredItemId::new(2)];
+        assert_eq!(hidden.namespace, vec!["foo".to_string()]);
+        assert_eq!(hidden.c_name.as_ref().unwrap().identifier, "Hidden");
+
+        let qualified = mappings
+            .to_cbindgen_toml_renames_with(&RenameOptions::default())
+            .unwrap();
+        assert!(qualified.contains("\"foo_bar_Baz\" = \"foo::bar::Baz\"\n"));
+        assert!(qualified.contains("\"foo_Hidden\" = \"foo::Hidden\"\n"));
+
+        let flattened = mappings
+            .to_cbindgen_toml_renames_with(&RenameOptions {
+                namespace_style: NamespaceStyle::Flattened,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(flattened.contains("\"foo_bar_Baz\" = \"foo__bar__Baz\"\n"));
+    }
+
+    #[test]
+    fn allow_and_deny_lists() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.allow("^bgp_").unwrap().deny("_internal$").unwrap();
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+
+        // alias found before its denied struct
+        callback.new_item_found(
+            DiscoveredItemId::new(10),
+            DiscoveredItem::Alias {
+                alias_name: "bmp_peer_t".to_string(),
+                alias_for: DiscoveredItemId::new(2),
+            },
+        );
+
+        for (id, name) in [(1, "bgp_peer"), (2, "bmp_peer"), (3, "bgp_peer_internal")] {
+            callback.new_item_found(
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Struct {
+                    original_name: Some(name.to_string()),
+                    final_name: name.to_string(),
+                },
+            );
+        }
+        // typedef struct { .. } bgp_config_t;
+        // typedef struct { .. } bmp_stats_t;
+        // the anonymous structs are decided by the name of their alias
+        for (id, alias_id, alias) in [(4, 20, "bgp_config_t"), (5, 21, "bmp_stats_t")] {
+            callback.new_item_found(
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Struct {
+                    original_name: None,
+                    final_name: format!("_bindgen_ty_{id}"),
+                },
+            );
+            callback.new_item_found(
+                DiscoveredItemId::new(alias_id),
+                DiscoveredItem::Alias {
+                    alias_name: alias.to_string(),
+                    alias_for: DiscoveredItemId::new(id),
+                },
+            );
+        }
+
+        // aliases found after their denied struct, directly or chained
+        // typedef struct bmp_peer bmp_peer_alias; typedef bmp_peer_alias bmp_peer_alias_t;
+        callback.new_item_found(
+            DiscoveredItemId::new(11),
+            DiscoveredItem::Alias {
+                alias_name: "bmp_peer_alias".to_string(),
+                alias_for: DiscoveredItemId::new(2),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(12),
+            DiscoveredItem::Alias {
+                alias_name: "bmp_peer_alias_t".to_string(),
+                alias_for: DiscoveredItemId::new(11),
+            },
+        );
+
+        let mut mappings = mappings.take();
+        let mut kept: Vec<_> = mappings.types.keys().copied().collect();
+        kept.sort();
+        assert_eq!(kept, [DiscoveredItemId::new(1), DiscoveredItemId::new(4)]);
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].rust_name,
+            "bgp_peer"
+        );
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(4)].c_name,
+            Some(CName {
+                identifier: "bgp_config_t".to_string(),
+                aliased: true,
+            })
+        );
+        assert!(mappings.aliases.is_empty());
+        assert_eq!(
+            mappings.denied_types,
+            BTreeSet::from([2, 3, 5].map(DiscoveredItemId::new))
+        );
+        assert_eq!(mappings.capture_pending_typedefs(), 0);
+    }
+
+    #[test]
+    fn skip_system_headers() {
+        let mut options = DiscoveryOptions::default();
+        assert!(options.is_allowed(Some("__pthread_mutex_s")));
+
+        options.skip_system_headers(true).keep_prefix("__mylib_");
+        assert!(!options.is_allowed(Some("__pthread_mutex_s")));
+        assert!(!options.is_allowed(Some("_IO_FILE")));
+        assert!(!options.is_allowed(Some("std::vector")));
+        assert!(options.is_allowed(Some("__mylib_ctx")));
+        assert!(options.is_allowed(Some("_private_state")));
+        assert!(options.is_allowed(Some("bgp_peer")));
+        assert!(options.is_allowed(None));
+    }
+
+    #[test]
+    fn capture_docs() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.capture_docs(true);
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+
+        // what bindgen reports for `/** A BGP peer */ struct bgp_peer {}; struct bmp_peer {};`
+        assert_eq!(callback.process_comment("A BGP peer"), None);
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("bgp_peer".to_string()),
+                final_name: "bgp_peer".to_string(),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(2),
+            DiscoveredItem::Struct {
+                original_name: Some("bmp_peer".to_string()),
+                final_name: "bmp_peer".to_string(),
+            },
+        );
+
+        let mappings = mappings.take();
+        let bgp_peer = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(bgp_peer.doc.as_deref(), Some("A BGP peer"));
+        assert_eq!(mappings.types[&DiscoveredItemId::new(2)].doc, None);
+
+        let single = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), bgp_peer.clone())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            single.to_cbindgen_toml_renames(false).unwrap(),
+            "# A BGP peer\n\"bgp_peer\" = \"struct bgp_peer\"\n"
+        );
+    }
+
+    #[test]
+    fn capture_docs_skips_other_items() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.capture_docs(true);
+        Builder::default()
+            .header_contents("docs.h", "/** f */ void f(void); struct opaque;")
+            .parse_callbacks(Box::new(
+                NameMappingsCallback(Rc::clone(&mappings)).with_options(options),
+            ))
+            .generate()
+            .unwrap();
+
+        let mappings = mappings.take();
+        let opaque = mappings
+            .types
+            .values()
+            .find(|mapping| mapping.rust_name == "opaque")
+            .unwrap();
+        assert_eq!(opaque.doc, None);
+    }
+
+    #[test]
+    fn typedef_chains() {
+        // typedef struct Foo Bar;
+        // typedef Bar Baz;
+        let alias = |id, name: &str, target| {
+            (
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Alias {
+                    alias_name: name.to_string(),
+                    alias_for: DiscoveredItemId::new(target),
+                },
+            )
+        };
+        let foo = (
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("Foo".to_string()),
+                final_name: "Foo".to_string(),
+            },
+        );
+        let expected_aliases = BTreeSet::from(["Bar".to_string(), "Baz".to_string()]);
+
+        // in declaration order, and in reverse order
+        for items in [
+            vec![foo.clone(), alias(2, "Bar", 1), alias(3, "Baz", 2)],
+            vec![alias(3, "Baz", 2), alias(2, "Bar", 1), foo.clone()],
+        ] {
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let callback = NameMappingsCallback(Rc::clone(&mappings));
+            for (id, item) in items {
+                callback.new_item_found(id, item);
+            }
+
+            let mappings = mappings.take();
+            assert_eq!(
+                mappings.types[&DiscoveredItemId::new(1)].aliases,
+                expected_aliases
+            );
+            assert!(mappings.aliases.is_empty());
+        }
+
+        // cycles don't hang the discovery
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in [alias(5, "A", 6), alias(6, "B", 5)] {
+            callback.new_item_found(id, item);
+        }
+        assert_eq!(
+            mappings
+                .borrow()
+                .resolve_alias_target(DiscoveredItemId::new(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn resolved_type_refs() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("Foo".to_string()),
+                final_name: "Foo".to_string(),
+            },
+        );
+
+        // references using the type's own name are not aliases
+        callback.resolved_type_ref_found(DiscoveredItemId::new(2), "Foo", DiscoveredItemId::new(1));
+        callback.resolved_type_ref_found(
+            DiscoveredItemId::new(3),
+            "struct Foo",
+            DiscoveredItemId::new(1),
+        );
+        // a reference under another spelling is
+        callback.resolved_type_ref_found(
+            DiscoveredItemId::new(4),
+            "FooRef",
+            DiscoveredItemId::new(1),
+        );
+
+        assert_eq!(
+            mappings.borrow().types[&DiscoveredItemId::new(1)].aliases,
+            BTreeSet::from(["FooRef".to_string()])
+        );
+    }
+
+    #[test]
+    fn custom_tag_prefixes() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "Foo")),
+                (DiscoveredItemId::new(2), mapping(Union, "Bar")),
+            ]),
+            ..Default::default()
+        };
+
+        let map = mappings
+            .to_static_map_with(&RenameOptions {
+                tag_prefixes: Some(TagPrefixes {
+                    struct_prefix: String::new(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .unwrap()
+            .build()
+            .to_string();
+
+        assert!(map.contains("(\"Foo\", \"Foo\")"));
+        assert!(map.contains("(\"Bar\", \"union Bar\")"));
+    }
+
+    #[test]
+    fn tagged_aliased_names() {
+        let aliased = CName {
+            identifier: "foo_t".to_string(),
+            aliased: true,
+        };
+        let named = CName {
+            identifier: "foo".to_string(),
+            aliased: false,
+        };
+        let tagging = TagPrefixes {
+            tag_aliased_names: true,
+            ..Default::default()
+        };
+
+        // aliased names are left untagged by default
+        assert_eq!(
+            NameMapping::validated_original_name_with(
+                Some(&aliased),
+                Union,
+                &TagPrefixes::default()
+            ),
+            Some("foo_t".to_string())
+        );
+        assert_eq!(
+            NameMapping::validated_original_name_with(Some(&aliased), Union, &tagging),
+            Some("union foo_t".to_string())
+        );
+
+        // the names of the types are tagged either way
+        for prefixes in [TagPrefixes::default(), tagging.clone()] {
+            assert_eq!(
+                NameMapping::validated_original_name_with(Some(&named), Union, &prefixes),
+                Some("union foo".to_string())
+            );
+        }
+
+        // and a typedef has no tag to get
+        assert_eq!(
+            NameMapping::validated_original_name_with(Some(&aliased), Typedef, &tagging),
+            Some("foo_t".to_string())
+        );
+    }
+
+    #[test]
+    fn case_insensitive_tags() {
+        let c_name = |identifier: &str| CName {
+            identifier: identifier.to_string(),
+            aliased: false,
+        };
+        let lenient = TagPrefixes {
+            case_insensitive_tags: true,
+            ..Default::default()
+        };
+
+        for (name, kind, expected) in [
+            ("Struct Foo", Struct, "struct Foo"),
+            ("STRUCT Foo", Struct, "struct Foo"),
+            ("UNION\tFoo", Union, "union Foo"),
+            ("Struct Union Foo", Enum, "enum Foo"),
+            ("struct Foo", Struct, "struct Foo"),
+            // a name merely starting like a keyword is kept
+            ("Structure", Struct, "struct Structure"),
+            ("ENUMS Foo", Enum, "enum ENUMS Foo"),
+        ] {
+            assert_eq!(
+                NameMapping::validated_original_name_with(Some(&c_name(name)), kind, &lenient),
+                Some(expected.to_string()),
+                "{name:?} as {kind}"
+            );
+        }
+
+        // strict by default
+        assert_eq!(
+            NameMapping::validated_original_name(Some(&c_name("Struct Foo")), Struct),
+            Some("struct Struct Foo".to_string())
+        );
+
+        // through the rename rules
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        NameMappingsCallback(Rc::clone(&mappings)).new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("STRUCT bmp_peer".to_string()),
+                final_name: "bmp_peer".to_string(),
+            },
+        );
+        let toml = mappings
+            .take()
+            .codegen()
+            .tag_prefixes(Some(lenient))
+            .generate_all()
+            .unwrap()
+            .toml;
+        assert!(
+            toml.contains("\"bmp_peer\" = \"struct bmp_peer\"\n"),
+            "{toml}"
+        );
+    }
+
+    #[test]
+    fn checked_c_names() {
+        assert_eq!(
+            CName::new("bmp_peer_hdr", false).unwrap(),
+            CName {
+                identifier: "bmp_peer_hdr".to_string(),
+                aliased: false,
+            }
+        );
+        assert!(CName::new("_Flags2", true).unwrap().aliased);
+        assert!(CName::new("ns::inner::Foo", false).is_ok());
+
+        for invalid in ["", "2fast", "my-type", "struct Foo", "ns::", "caf\u{e9}"] {
+            assert!(
+                matches!(CName::new(invalid, false), Err(Error::InvalidIdentifier(name)) if name == invalid),
+                "{invalid:?}"
+            );
+        }
+
+        assert_eq!(CName::tagged(Struct, "Foo").unwrap(), "struct Foo");
+        assert_eq!(CName::tagged(Union, "  struct  Foo").unwrap(), "union Foo");
+        assert_eq!(CName::tagged(ScopedEnum, "Level").unwrap(), "enum Level");
+        assert_eq!(CName::tagged(Typedef, "foo_t").unwrap(), "foo_t");
+        assert!(matches!(
+            CName::tagged(Struct, "struct 1st"),
+            Err(Error::InvalidIdentifier(_))
+        ));
+    }
+
+    #[test]
+    fn shared_types_attribution() {
+        let mapping = |kind, c_name: Option<(&str, bool)>, rust_name: &str| NameMapping {
+            c_name: c_name.map(|(identifier, aliased)| CName {
+                identifier: identifier.to_string(),
+                aliased,
+            }),
+            ..mapping(kind, rust_name)
+        };
+        let common = mapping(Struct, Some(("common", false)), "common");
+        let flags = mapping(Union, Some(("flags_t", true)), "flags_t");
+        let shared = mapping(Struct, Some(("shared", false)), "shared");
+        let handle = mapping(Typedef, Some(("handle_t", false)), "handle_t");
+        let mappings = |types: Vec<NameMapping>| NameMappings {
+            types: types
+                .into_iter()
+                .enumerate()
+                .map(|(id, mapping)| (DiscoveredItemId::new(id), mapping))
+                .collect(),
+            ..Default::default()
+        };
+
+        let attributed = attribute_shared_types(vec![
+            (
+                PathBuf::from("a.h"),
+                "#include \"common.h\"\nstruct shared;\n".to_string(),
+                mappings(vec![
+                    common.clone(),
+                    flags.clone(),
+                    shared.clone(),
+                    handle.clone(),
+                ]),
+            ),
+            (
+                PathBuf::from("b.h"),
+                "typedef union {\n    int bits;\n} flags_t;\nstruct shared;\n".to_string(),
+                mappings(vec![common.clone(), flags.clone(), shared.clone()]),
+            ),
+            (
+                PathBuf::from("common.h"),
+                "struct common\n{\n    int z;\n};\ntypedef unsigned int handle_t;\n".to_string(),
+                mappings(vec![common.clone(), handle.clone()]),
+            ),
+        ]);
+
+        let types = |header: &str| {
+            let mut types: Vec<&NameMapping> =
+                attributed[&PathBuf::from(header)].types.values().collect();
+            types.sort_by_key(|mapping| &mapping.rust_name);
+            types
+        };
+        // the type defined nowhere stays in the first header
+        assert_eq!(types("a.h"), vec![&shared]);
+        assert_eq!(types("b.h"), vec![&flags]);
+        assert_eq!(types("common.h"), vec![&common, &handle]);
+    }
+
+    #[test]
+    fn semantic_equality() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bgp_peer")),
+                (DiscoveredItemId::new(2), mapping(Struct, "bmp_peer")),
+            ]),
+            aliases: HashMap::from([(DiscoveredItemId::new(3), vec!["as_t".to_string()])]),
+            ..Default::default()
+        };
+        let reidded = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(20), mapping(Struct, "bmp_peer")),
+                (DiscoveredItemId::new(10), mapping(Struct, "bgp_peer")),
+            ]),
+            aliases: HashMap::from([(DiscoveredItemId::new(30), vec!["as_t".to_string()])]),
+            discovery_runs: 2,
+            ..Default::default()
+        };
+
+        assert_ne!(mappings, reidded);
+        assert!(mappings.semantically_eq(&reidded));
+        assert!(reidded.semantically_eq(&mappings));
+
+        let mut changed = reidded.clone();
+        changed
+            .types
+            .get_mut(&DiscoveredItemId::new(10))
+            .unwrap()
+            .aliases
+            .insert("bgp_peer_t".to_string());
+        assert!(!mappings.semantically_eq(&changed));
+
+        let mut extra = reidded.clone();
+        extra.insert(mapping(Struct, "bgp_peer"));
+        assert!(!mappings.semantically_eq(&extra));
+
+        let mut cxx = reidded;
+        cxx.language = Language::Cxx;
+        assert!(!mappings.semantically_eq(&cxx));
+    }
+
+    #[test]
+    fn without_tag_prefix() {
+        let mapping = |kind, name: &str, aliases: &[&str]| NameMapping {
+            c_name: Some(CName {
+                identifier: name.to_string(),
+                aliased: false,
+            }),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..mapping(kind, name.trim_start_matches("union "))
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "Foo", &[])),
+                (DiscoveredItemId::new(2), mapping(Union, "union Bar", &[])),
+                (DiscoveredItemId::new(3), mapping(Struct, "Baz", &["baz_t"])),
+            ]),
+            ..Default::default()
+        };
+
+        let mut codegen = mappings.clone().codegen();
+        codegen.output(CodegenOutput::TomlString);
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(false).unwrap(),
+            "\"Bar\" = \"union Bar\"\n\"Baz\" = \"struct Baz\"\n\"Foo\" = \"struct Foo\"\n"
+        );
+
+        let toml = mappings
+            .to_cbindgen_toml_renames_with(&RenameOptions {
+                no_tag_prefix: true,
+                tag_prefixes: Some(TagPrefixes::default()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            toml,
+            "\"Bar\" = \"Bar\"\n\"Baz\" = \"Baz\"\n\"Foo\" = \"Foo\"\n"
+        );
+
+        codegen.no_tag_prefix(true).force_aliases_use(true);
+        assert_eq!(
+            codegen.generate().unwrap().to_string(),
+            Literal::string("\"Bar\" = \"Bar\"\n\"Baz\" = \"baz_t\"\n\"Foo\" = \"Foo\"\n")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn csv_export() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Union, "bmp_data")),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t", "peer,t"]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mappings.to_csv(),
+            "rust_name,c_name,kind,aliases\n\
+             bgp_peer,struct bgp_peer,struct,\"bgp_peer_t;peer,t\"\n\
+             bmp_data,union bmp_data,union,\n"
+        );
+    }
+
+    #[test]
+    fn function_renames() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback =
+            NameMappingsCallback(Rc::clone(&mappings)).with_options(DiscoveryOptions::default());
+        callback.function_found("bgp_parse", "parse");
+        assert!(mappings.borrow().functions.is_empty());
+
+        let mut options = DiscoveryOptions::default();
+        options.capture_functions(true);
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+        for (c_name, rust_name) in [
+            ("bgp_parse", "parse"),
+            ("bmp_parse", "bmp_parse"),
+            ("type", "r#type"),
+        ] {
+            callback.function_found(c_name, rust_name);
+        }
+
+        let mappings = mappings.take();
+        assert_eq!(mappings.functions.len(), 3);
+        assert_eq!(
+            mappings.to_cbindgen_fn_renames().unwrap(),
+            "\"parse\" = \"bgp_parse\"\n"
+        );
+        assert!(mappings.to_cbindgen_toml_renames(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn discovered_function_renames() {
+        // the functions renamed by bindgen itself are captured during the discovery
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.capture_functions(true);
+        Builder::default()
+            .header_contents("functions.h", "void type(void); int bgp_parse(void);")
+            .parse_callbacks(Box::new(
+                NameMappingsCallback(Rc::clone(&mappings)).with_options(options),
+            ))
+            .generate()
+            .unwrap();
+        assert_eq!(
+            mappings.take().functions,
+            BTreeMap::from([("type_".to_string(), "type".to_string())])
+        );
+    }
+
+    #[test]
+    fn constant_renames() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback =
+            NameMappingsCallback(Rc::clone(&mappings)).with_options(DiscoveryOptions::default());
+        callback.int_macro("BMP_VERSION", 3);
+        callback.constant_found("bmp_default_port", "DEFAULT_PORT");
+        assert!(mappings.borrow().constants.is_empty());
+
+        let mut options = DiscoveryOptions::default();
+        options.capture_constants(true);
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+        // #define BMP_VERSION 3, #define BMP_NAME "bmp", #define match 1, kept by bindgen as `match_`
+        assert_eq!(callback.int_macro("BMP_VERSION", 3), None);
+        callback.str_macro("BMP_NAME", b"bmp");
+        callback.int_macro("match", 1);
+        callback.constant_found("bmp_default_port", "DEFAULT_PORT");
+        callback.constant_found("type", "r#type");
+
+        let mappings = mappings.take();
+        assert_eq!(
+            mappings.constants,
+            BTreeMap::from([
+                ("DEFAULT_PORT".to_string(), "bmp_default_port".to_string()),
+                ("match_".to_string(), "match".to_string()),
+                ("r#type".to_string(), "type".to_string()),
+            ])
+        );
+        // only the renamed constant has a rule
+        assert_eq!(
+            mappings.to_cbindgen_constant_renames().unwrap(),
+            "\"DEFAULT_PORT\" = \"bmp_default_port\"\n\"match_\" = \"match\"\n"
+        );
+        assert!(mappings.to_cbindgen_fn_renames().unwrap().is_empty());
+    }
+
+    #[test]
+    fn discovered_constant_renames() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.capture_constants(true);
+        Builder::default()
+            .header_contents(
+                "constants.h",
+                "#define BMP_VERSION 3\n#define type 4\nextern const char *match;",
+            )
+            .parse_callbacks(Box::new(
+                NameMappingsCallback(Rc::clone(&mappings)).with_options(options),
+            ))
+            .generate()
+            .unwrap();
+
+        let mappings = mappings.take();
+        assert_eq!(
+            mappings.constants,
+            BTreeMap::from([
+                ("match_".to_string(), "match".to_string()),
+                ("type_".to_string(), "type".to_string()),
+            ])
+        );
+        assert_eq!(
+            mappings.to_cbindgen_constant_renames().unwrap(),
+            "\"match_\" = \"match\"\n\"type_\" = \"type\"\n"
+        );
+    }
+
+    #[test]
+    fn ignored_items() {
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&warnings);
+        let mut options = DiscoveryOptions::default();
+        options.diagnostics(move |warning| sink.borrow_mut().push(warning.to_string()));
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options.clone());
+        let item = DiscoveredItem::Struct {
+            original_name: Some("bgp_peer".to_string()),
+            final_name: "bgp_peer".to_string(),
+        };
+        callback.new_item_found(DiscoveredItemId::new(1), item.clone());
+        callback.new_item_found(
+            DiscoveredItemId::new(2),
+            DiscoveredItem::Alias {
+                alias_name: "bgp_peer_t".to_string(),
+                alias_for: DiscoveredItemId::new(1),
+            },
+        );
+        assert_eq!(mappings.borrow().discovered_items, 2);
+        assert!(mappings.borrow().ignored_items.is_empty());
+        assert!(warnings.borrow().is_empty());
+
+        // every kind bindgen reports today is handled, so an unknown kind is simulated
+        Discovery {
+            mappings: &mappings,
+            options: &options,
+            doc: None,
+            source: None,
+        }
+        .item_ignored(&item);
+        assert_eq!(
+            mappings.borrow().ignored_items,
+            BTreeMap::from([("Struct".to_string(), 1)])
+        );
+        assert_eq!(warnings.borrow().len(), 1);
+        assert!(warnings.borrow()[0].starts_with("Warn: ignored discovered item Struct"));
+    }
+
+    #[test]
+    fn anonymous_predicates() {
+        let mapping = |c_name: Option<(&str, bool)>| NameMapping {
+            c_name: c_name.map(|(identifier, aliased)| CName {
+                identifier: identifier.to_string(),
+                aliased,
+            }),
+            ..mapping(Struct, "foo")
+        };
+
+        // struct { .. } without typedef
+        let anonymous = mapping(None);
+        assert!(anonymous.is_anonymous());
+        assert!(!anonymous.is_aliased_name());
+
+        // typedef struct { .. } foo_t;
+        let aliased = mapping(Some(("foo_t", true)));
+        assert!(aliased.is_anonymous());
+        assert!(aliased.is_aliased_name());
+
+        // struct foo { .. };
+        let named = mapping(Some(("foo", false)));
+        assert!(!named.is_anonymous());
+        assert!(!named.is_aliased_name());
+    }
+
+    #[test]
+    fn first_declared_alias() {
+        // typedef struct S S_t;
+        // typedef S_t S_alias;
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("S".to_string()),
+                final_name: "S".to_string(),
+            },
+        );
+        for (id, alias, target) in [(2, "S_t", 1), (3, "S_alias", 2)] {
+            callback.new_item_found(
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Alias {
+                    alias_name: alias.to_string(),
+                    alias_for: DiscoveredItemId::new(target),
+                },
+            );
+        }
+
+        let mappings = mappings.take();
+        let mapping = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(mapping.alias_order, vec!["S_t", "S_alias"]);
+        assert_eq!(
+            mapping.resolved_c_name(true, &AliasSelector::First),
+            Some("S_alias".to_string())
+        );
+        assert_eq!(
+            mapping.resolved_c_name(true, &AliasSelector::FirstDeclared),
+            Some("S_t".to_string())
+        );
+
+        // the aliases missing from the declaration order come after the declared ones
+        let mut by_hand = mapping.clone();
+        by_hand.alias_order.clear();
+        by_hand.aliases.insert("A_t".to_string());
+        assert_eq!(
+            by_hand.selected_alias(&AliasSelector::FirstDeclared),
+            Some(&"A_t".to_string())
+        );
+
+        let toml = mappings
+            .to_cbindgen_toml_renames_with(&RenameOptions {
+                force_aliases_use: true,
+                alias_selector: AliasSelector::FirstDeclared,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(toml, "\"S\" = \"S_t\"\n");
+    }
+
+    #[test]
+    fn resolved_c_names() {
+        let mapping = |c_name: Option<(&str, bool)>, aliases: &[&str]| NameMapping {
+            c_name: c_name.map(|(identifier, aliased)| CName {
+                identifier: identifier.to_string(),
+                aliased,
+            }),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..mapping(Struct, "Foo")
+        };
+        let first = AliasSelector::First;
+
+        // normal
+        let normal = mapping(Some(("Foo", false)), &["foo_t", "Foo_t"]);
+        assert_eq!(
+            normal.resolved_c_name(false, &first),
+            Some("struct Foo".to_string())
+        );
+        assert_eq!(
+            normal.resolved_c_name(true, &first),
+            Some("Foo_t".to_string())
+        );
+        assert_eq!(
+            normal.resolved_c_name(true, &AliasSelector::Longest),
+            Some("Foo_t".to_string())
+        );
+
+        // aliased
+        let aliased = mapping(Some(("foo_t", true)), &[]);
+        assert_eq!(
+            aliased.resolved_c_name(false, &first),
+            Some("foo_t".to_string())
+        );
+        assert_eq!(
+            aliased.resolved_c_name(true, &first),
+            Some("foo_t".to_string())
+        );
+
+        // anonymous
+        let anonymous = mapping(None, &["bar_t"]);
+        assert_eq!(
+            anonymous.resolved_c_name(false, &first),
+            Some("bar_t".to_string())
+        );
+        assert_eq!(mapping(None, &[]).resolved_c_name(true, &first), None);
+    }
+
+    #[test]
+    fn borrowed_codegen() {
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Struct, "bmp_peer"))]),
+            ..Default::default()
+        };
+
+        // the same mappings drive the code and the toml
+        let code = MappingsCodegen::borrowed(&mappings)
+            .output(CodegenOutput::MatchFn)
+            .generate()
+            .unwrap()
+            .to_string();
+        let toml = mappings.to_cbindgen_toml_renames(false).unwrap();
+        assert!(code.contains("\"bmp_peer\" => Some (\"struct bmp_peer\")"));
+        assert_eq!(toml, "\"bmp_peer\" = \"struct bmp_peer\"\n");
+
+        // same code as the consuming builder
+        assert_eq!(
+            MappingsCodegen::borrowed(&mappings)
+                .generate()
+                .unwrap()
+                .to_string(),
+            mappings.clone().codegen().generate().unwrap().to_string()
+        );
+        assert_eq!(MappingsCodegen::from(&mappings).mappings(), mappings);
+    }
+
+    #[test]
+    fn c_header() {
+        let mapping = |kind, c_name: &str, aliased, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased,
+            }),
+            ..mapping(kind, rust_name)
+        };
+        let mut mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(Struct, "bmp_peer", false, "BmpPeer"),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(Struct, "bgp_open", false, "bgp_open"),
+                ),
+                (
+                    DiscoveredItemId::new(3),
+                    mapping(Union, "bmp_data_t", true, "bmp_data_t"),
+                ),
+                (
+                    DiscoveredItemId::new(4),
+                    mapping(Struct, "match", false, "r#match"),
+                ),
+            ]),
+            ..Default::default()
+        };
+        mappings
+            .functions
+            .insert("parse_bmp".to_string(), "bmp_parse".to_string());
+
+        assert_eq!(
+            mappings.to_c_header(),
+            "#ifndef BINDGEN_BRIDGE_RENAMES_H\n#define BINDGEN_BRIDGE_RENAMES_H\n\n\
+             typedef struct bmp_peer BmpPeer;\n\
+             typedef struct bgp_open bgp_open;\n\
+             typedef struct match match;\n\
+             \n#define parse_bmp bmp_parse\n\
+             \n#endif /* BINDGEN_BRIDGE_RENAMES_H */\n"
+        );
+        assert_eq!(
+            NameMappings::default().to_c_header(),
+            "#ifndef BINDGEN_BRIDGE_RENAMES_H\n#define BINDGEN_BRIDGE_RENAMES_H\n\n\
+             \n#endif /* BINDGEN_BRIDGE_RENAMES_H */\n"
+        );
+    }
+
+    #[test]
+    fn typedef_declarations() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    NameMapping {
+                        aliases: BTreeSet::from(["AliasOfFoo".to_string(), "FooT".to_string()]),
+                        ..mapping(Struct, "Foo")
+                    },
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    NameMapping {
+                        c_name: Some(CName {
+                            identifier: "bar_t".to_string(),
+                            aliased: true,
+                        }),
+                        aliases: BTreeSet::from(["other_bar_t".to_string()]),
+                        ..mapping(Union, "bar_t")
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mappings.to_typedef_declarations(),
+            "typedef struct Foo AliasOfFoo;\n\
+             typedef struct Foo FooT;\n\
+             typedef bar_t other_bar_t;\n"
+        );
+        assert_eq!(NameMappings::default().to_typedef_declarations(), "");
+    }
+
+    #[test]
+    fn match_fn_codegen() {
+        let code = NameMappings::default()
+            .codegen()
+            .output(CodegenOutput::MatchFn)
+            .generate()
+            .unwrap();
+
+        assert_eq!(
+            code.to_string(),
+            "pub fn rename (rust_name : & str) -> Option < & 'static str > { \
+             match rust_name { _ => None , } }"
+        );
+
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bmp_peer")),
+                (DiscoveredItemId::new(2), mapping(Struct, "bgp_peer")),
+            ]),
+            ..Default::default()
+        };
+        let code = mappings
+            .codegen()
+            .output(CodegenOutput::MatchFn)
+            .variable_name(Some("rename_type"))
+            .generate()
+            .unwrap();
+
+        assert_eq!(
+            code.to_string(),
+            "pub fn rename_type (rust_name : & str) -> Option < & 'static str > { \
+             match rust_name { \
+             \"bgp_peer\" => Some (\"struct bgp_peer\") , \
+             \"bmp_peer\" => Some (\"struct bmp_peer\") , \
+             _ => None , } }"
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_error() {
+        let mut options = DiscoveryOptions::default();
+        assert!(matches!(options.allow("(unclosed"), Err(Error::Regex(_))));
+    }
+
+    #[test]
+    fn mappings_diff() {
+        let before = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+                ),
+                (DiscoveredItemId::new(2), mapping(Union, "bmp_data")),
+            ]),
+            ..Default::default()
+        };
+        let after = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(3), mapping(Union, "bmp_data")),
+                (
+                    DiscoveredItemId::new(4),
+                    mapping_with_aliases(Struct, "bgp_peer", &["peer_t"]),
+                ),
+                (DiscoveredItemId::new(5), mapping(Struct, "bmp_peer")),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(before.diff(&before).is_empty());
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec![mapping(Struct, "bmp_peer")]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.changed,
+            vec![MappingChange {
+                old: mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+                new: mapping_with_aliases(Struct, "bgp_peer", &["peer_t"]),
+            }]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "+ bmp_peer (struct)\n\
+             ~ bgp_peer (struct {\"bgp_peer_t\"} -> struct {\"peer_t\"})\n"
+        );
+    }
+
+    #[test]
+    fn unnamed_type_policies() {
+        // the anonymous struct of `void function_using_anonymous_struct(struct {} arg0);`
+        let mappings = NameMappings {
+            types: HashMap::from([(
+                DiscoveredItemId::new(1),
+                NameMapping {
+                    c_name: None,
+                    ..mapping(Struct, "_bindgen_ty_1")
+                },
+            )]),
+            ..Default::default()
+        };
+        let mut codegen = mappings.codegen();
+        codegen.output(CodegenOutput::MatchFn);
+
+        let code = codegen.generate().unwrap();
+        assert!(!code.to_string().contains("_bindgen_ty_1"));
+
+        codegen.unnamed_types(UnnamedTypePolicy::Error);
+        assert!(matches!(
+            codegen.generate(),
+            Err(Error::UnnamedType(name)) if name == "_bindgen_ty_1"
+        ));
+
+        codegen.unnamed_types(UnnamedTypePolicy::Synthesize("anon_".to_string()));
+        let code = codegen.generate().unwrap();
+        assert!(code
+            .to_string()
+            .contains("\"_bindgen_ty_1\" => Some (\"anon_1\")"));
+    }
+
+    #[test]
+    fn kind_map_codegen() {
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Union, "bmp_data"))]),
+            ..Default::default()
+        };
+        let code = mappings
+            .codegen()
+            .as_static_map(true)
+            .with_kind(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap();
+
+        // stringify! and TokenStream don't space the tokens the same way
+        assert_same_code(&code, KIND_MAP_CODE);
+        assert_eq!(
+            kind_map::RENAMES.get("bmp_data"),
+            Some(&("union bmp_data", kind_map::CKind::Union))
+        );
+    }
+
+    compiled_code! { kind_map, KIND_MAP_CODE,
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum CKind {
+            Struct,
+            Union,
+            Class,
+            Enum,
+            ScopedEnum,
+            Typedef,
+        }
+        pub static RENAMES: phf::Map<&'static str, (&'static str, CKind)> = ::phf::Map {
+            key: 12913932095322966823,
+            disps: &[(0, 0),],
+            entries: &[("bmp_data", ("union bmp_data", CKind::Union)),],
+        };
+    }
+
+    compiled_code! { unique_targets, UNIQUE_TARGETS_CODE,
+        const _: () = {
+            const TARGETS: &[&str] = &["bgp_peer_t", "struct bmp_peer"];
+            let mut i = 1;
+            while i < TARGETS.len() {
+                let (a, b) = (TARGETS[i - 1].as_bytes(), TARGETS[i].as_bytes());
+                let mut same = a.len() == b.len();
+                let mut j = 0;
+                while same && j < a.len() {
+                    same = a[j] == b[j];
+                    j += 1;
+                }
+                assert!(!same, "two Rust names are renamed to the same C name");
+                i += 1;
+            }
+        };
+    }
+
+    #[test]
+    fn unique_targets_assertion() {
+        let mut mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+                ),
+                (DiscoveredItemId::new(2), mapping(Struct, "bmp_peer")),
+            ]),
+            ..Default::default()
+        };
+        let generate = |mappings: &NameMappings, with_name: bool| {
+            let mut codegen = mappings.clone().codegen();
+            codegen
+                .output(CodegenOutput::StaticMap)
+                .force_aliases_when(|mapping| mapping.rust_name == "bgp_peer")
+                .variable_name(with_name.then_some("RENAMES"))
+                .assert_unique_targets(true);
+            codegen.generate().unwrap().to_string()
+        };
+        let compact = |code: &str| code.split_whitespace().collect::<String>();
+
+        // the same assertion as the one compiled in the `unique_targets` module
+        assert!(compact(&generate(&mappings, true)).contains(&compact(UNIQUE_TARGETS_CODE)));
+        assert!(!generate(&mappings, false).contains("TARGETS"));
+
+        // a duplicated C name ends up next to its twin, failing the assertion when compiled
+        let mut duplicate = mapping(Struct, "peer_alias");
+        duplicate.c_name = Some(CName {
+            identifier: "bgp_peer_t".to_string(),
+            aliased: true,
+        });
+        mappings.types.insert(DiscoveredItemId::new(3), duplicate);
+        assert!(compact(&generate(&mappings, true)).contains(&compact(
+            "const TARGETS: &[&str] = &[\"bgp_peer_t\", \"bgp_peer_t\", \"struct bmp_peer\"];"
+        )));
+    }
+
+    compiled_code! { sorted_slice, SORTED_SLICE_CODE,
+        pub static RENAMES: &[(&str, &str)] = &[
+            ("Bgp_peer", "struct bgp_peer"),
+            ("bmp_data", "union bmp_data"),
+            ("bmp_peer", "struct bmp_peer"),
+        ];
+
+        pub fn lookup(rust_name: &str) -> Option<&'static str> {
+            RENAMES
+                .binary_search_by_key(&rust_name, |(key, _)| *key)
+                .ok()
+                .map(|index| RENAMES[index].1)
+        }
+    }
+
+    #[test]
+    fn sorted_slice_codegen() {
+        let mapping = |kind, c_name: &str, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(kind, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(Struct, "bmp_peer", "bmp_peer"),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(Union, "bmp_data", "bmp_data"),
+                ),
+                (
+                    DiscoveredItemId::new(3),
+                    mapping(Struct, "bgp_peer", "Bgp_peer"),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let code = mappings
+            .clone()
+            .codegen()
+            .output(CodegenOutput::SortedSlice)
+            .generate()
+            .unwrap();
+        assert_same_code(&code, SORTED_SLICE_CODE);
+
+        // the entries are sorted for the binary search
+        assert!(sorted_slice::RENAMES
+            .windows(2)
+            .all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(sorted_slice::lookup("bmp_peer"), Some("struct bmp_peer"));
+        assert_eq!(sorted_slice::lookup("Bgp_peer"), Some("struct bgp_peer"));
+        assert_eq!(sorted_slice::lookup("bgp_peer"), None);
+        assert_eq!(sorted_slice::lookup(""), None);
+
+        let code = mappings
+            .codegen()
+            .output(CodegenOutput::SortedSlice)
+            .variable_name(Some("BMP"))
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub static BMP"));
+        assert!(code.contains("pub fn bmp_lookup"));
+    }
+
+    #[test]
+    fn alias_use_impact() {
+        let mapping =
+            |kind, c_name: Option<(&str, bool)>, rust_name: &str, aliases: &[&str]| NameMapping {
+                c_name: c_name.map(|(identifier, aliased)| CName {
+                    identifier: identifier.to_string(),
+                    aliased,
+                }),
+                aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+                alias_order: aliases.iter().map(|alias| alias.to_string()).collect(),
+                ..mapping(kind, rust_name)
+            };
+        // struct bmp_peer {}; typedef struct bmp_peer bmp_peer_t;
+        // union bgp_attr {}; typedef struct { .. } bmp_stats_t; enum bmp_state {};
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(
+                        Struct,
+                        Some(("bmp_peer", false)),
+                        "bmp_peer",
+                        &["bmp_peer_t"],
+                    ),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(
+                        Union,
+                        Some(("bgp_attr", false)),
+                        "bgp_attr",
+                        &["bgp_attr_t", "attr"],
+                    ),
+                ),
+                (
+                    DiscoveredItemId::new(3),
+                    mapping(
+                        Struct,
+                        Some(("bmp_stats_t", true)),
+                        "bmp_stats_t",
+                        &["bmp_stats_t"],
+                    ),
+                ),
+                (
+                    DiscoveredItemId::new(4),
+                    mapping(Enum, Some(("bmp_state", false)), "bmp_state", &[]),
+                ),
+                (
+                    DiscoveredItemId::new(5),
+                    mapping(Struct, None, "_bindgen_ty_1", &[]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        // the types without alias, already named after one or without name are not impacted
+        assert_eq!(
+            mappings.alias_use_impact(),
+            [
+                (
+                    "bgp_attr".to_string(),
+                    "union bgp_attr".to_string(),
+                    "attr".to_string()
+                ),
+                (
+                    "bmp_peer".to_string(),
+                    "struct bmp_peer".to_string(),
+                    "bmp_peer_t".to_string()
+                ),
+            ]
+        );
+
+        let options = RenameOptions {
+            alias_selector: AliasSelector::Longest,
+            ..Default::default()
+        };
+        assert_eq!(mappings.alias_use_impact_with(&options)[0].2, "bgp_attr_t");
+    }
+
+    #[test]
+    fn annotate_original_names() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping_with_aliases(Struct, "bmp_peer", &["bmp_peer_t"]),
+                ),
+                (DiscoveredItemId::new(2), mapping(Union, "bmp_data")),
+            ]),
+            ..Default::default()
+        };
+
+        let write = |output| {
+            let mut text = Vec::new();
+            mappings
+                .clone()
+                .codegen()
+                .output(output)
+                .force_aliases_use(true)
+                .annotate_original_names(true)
+                .write_to(&mut text)
+                .unwrap();
+            String::from_utf8(text).unwrap()
+        };
+
+        // only the aliased pick is annotated
+        let text = write(CodegenOutput::MatchFn);
+        assert!(text.contains("\"bmp_peer\" => Some (\"bmp_peer_t\") , // struct bmp_peer\n"));
+        assert!(!text.contains("// union bmp_data"));
+        assert_eq!(text.matches("//").count(), 1);
+
+        let text = write(CodegenOutput::SortedSlice);
+        assert!(text.contains("(\"bmp_peer\" , \"bmp_peer_t\") , // struct bmp_peer\n"));
+        assert_eq!(text.matches("//").count(), 1);
+
+        // the comments can't be in the tokens, nor in the other outputs
+        assert!(!write(CodegenOutput::StaticMap).contains("//"));
+        let code = mappings
+            .codegen()
+            .output(CodegenOutput::MatchFn)
+            .force_aliases_use(true)
+            .annotate_original_names(true)
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(!code.contains("//"));
+    }
+
+    #[test]
+    fn source_hash() {
+        let mapping = |c_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, "bgp_peer")
+        };
+        let mappings = |c_name: &str| NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(c_name)),
+                (DiscoveredItemId::new(2), {
+                    let mut other = mapping("bmp_data");
+                    other.rust_name = "bmp_data".to_string();
+                    other
+                }),
+            ]),
+            ..Default::default()
+        };
+
+        // stable for identical input, whatever the ids
+        let hash = mappings("bgp_peer").source_hash();
+        assert_eq!(hash.len(), 16);
+        assert_eq!(mappings("bgp_peer").source_hash(), hash);
+        let mut reordered = mappings("bgp_peer");
+        let peer = reordered.types.remove(&DiscoveredItemId::new(1)).unwrap();
+        reordered.types.insert(DiscoveredItemId::new(3), peer);
+        assert_eq!(reordered.source_hash(), hash);
+
+        // changes with a mapping
+        assert_ne!(mappings("bgp_peer_v2").source_hash(), hash);
+
+        let code = mappings("bgp_peer")
+            .codegen()
+            .with_source_hash(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(code.starts_with(&format!("pub const SOURCE_HASH : & str = \"{hash}\" ;")));
+
+        // the text starts with the comment a CI check can grep
+        let mut written = Vec::new();
+        mappings("bgp_peer")
+            .codegen()
+            .with_source_hash(true)
+            .variable_name(Some("RENAMES"))
+            .write_to(&mut written)
+            .unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.starts_with(&format!("// source-hash: {hash}\npub const SOURCE_HASH")));
+
+        // a bare value can't be preceded by the const
+        let code = mappings("bgp_peer")
+            .codegen()
+            .with_source_hash(true)
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(!code.contains("SOURCE_HASH"));
+    }
+
+    #[test]
+    fn diff_against_file() {
+        let mut codegen = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Struct, "bgp_peer"))]),
+            ..Default::default()
+        }
+        .codegen();
+        codegen.output(CodegenOutput::MatchFn);
+
+        let dir = std::env::temp_dir().join(format!("bindgen-bridge-diff-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("renames.rs");
+
+        let generated = codegen.generate().unwrap().to_string();
+        std::fs::write(&path, format!("{generated}\n")).unwrap();
+        assert_eq!(codegen.diff_against_file(&path).unwrap(), None);
+
+        std::fs::write(&path, "fn outdated() {}\n").unwrap();
+        assert_eq!(
+            codegen.diff_against_file(&path).unwrap(),
+            Some(format!("- fn outdated() {{}}\n+ {generated}\n"))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(
+            codegen.diff_against_file(&path),
+            Err(Error::Io(_))
+        ));
+
+        assert_eq!(line_diff("a\nb\nc", "a\nc\nd"), "- b\n+ d\n");
+    }
+
+    #[test]
+    fn write_region() {
+        let mut codegen = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Struct, "bgp_peer"))]),
+            ..Default::default()
+        }
+        .codegen();
+        codegen.output(CodegenOutput::MatchFn);
+
+        let dir =
+            std::env::temp_dir().join(format!("bindgen-bridge-region-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("ffi.rs");
+        std::fs::write(&path, "mod ffi {\n}\n").unwrap();
+
+        let placement = RegionPlacement::After("mod ffi {".to_string());
+        codegen.write_region_to_file(&path, &placement).unwrap();
+        let first = std::fs::read_to_string(&path).unwrap();
+        codegen.write_region_to_file(&path, &placement).unwrap();
+        let second = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(first, second);
+
+        let generated = codegen.generate().unwrap().to_string();
+        assert_eq!(
+            second,
+            format!(
+                "mod ffi {{\n// BINDGEN-BRIDGE BEGIN\n{generated}\n// BINDGEN-BRIDGE END\n}}\n"
+            )
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn phf_crate_path() {
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Union, "bmp_data"))]),
+            ..Default::default()
+        };
+
+        let code = mappings
+            .clone()
+            .codegen()
+            .as_static_map(true)
+            .bidirectional(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(code.contains("RENAMES : phf :: Map <"));
+        assert!(code.contains("= :: phf :: Map {"));
+
+        let code = mappings
+            .codegen()
+            .as_static_map(true)
+            .bidirectional(true)
+            .phf_crate_path("crate::reexports::phf")
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(code.contains("RENAMES : crate :: reexports :: phf :: Map <"));
+        assert!(code.contains("RENAMES_REVERSE : crate :: reexports :: phf :: Map <"));
+        assert_eq!(
+            code.matches("= crate :: reexports :: phf :: Map {").count(),
+            2
+        );
+        assert!(!code.contains("= :: phf"));
+    }
+
+    #[test]
+    fn settings_survive_new_mappings() {
+        let mut codegen = NameMappings {
+            types: HashMap::from([(
+                DiscoveredItemId::new(1),
+                mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+            )]),
+            ..Default::default()
+        }
+        .codegen();
+        codegen
+            .force_aliases_use(true)
+            .output(CodegenOutput::MatchFn)
+            .variable_name(Some("c_name_of"));
+
+        let mut mappings = codegen.clone().mappings();
+        mappings.types.insert(
+            DiscoveredItemId::new(2),
+            mapping_with_aliases(Struct, "bmp_peer", &["bmp_peer_t"]),
+        );
+        let codegen = codegen.with_mappings(mappings);
+
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.starts_with("pub fn c_name_of"));
+        assert!(code.contains("\"bgp_peer\" => Some (\"bgp_peer_t\")"));
+        assert!(code.contains("\"bmp_peer\" => Some (\"bmp_peer_t\")"));
+    }
+
+    #[test]
+    fn write_codegen_with_line_endings() {
+        let mut codegen = NameMappings::default().codegen();
+        codegen
+            .output(CodegenOutput::MatchFn)
+            .output_format(OutputFormat {
+                line_ending: LineEnding::Crlf,
+                trailing_newline: true,
+            });
+
+        let mut crlf = Vec::new();
+        codegen.write_to(&mut crlf).unwrap();
+        let code = codegen.generate().unwrap().to_string();
+        assert_eq!(crlf, format!("{code}\r\n").as_bytes());
+
+        let mut raw = Vec::new();
+        codegen
+            .output_format(OutputFormat {
+                line_ending: LineEnding::Lf,
+                trailing_newline: false,
+            })
+            .write_to(&mut raw)
+            .unwrap();
+        assert_eq!(raw, code.as_bytes());
+    }
+
+    #[test]
+    fn generate_all_agree() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Union, "bmp_data")),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let GeneratedArtifacts {
+            static_map, toml, ..
+        } = mappings
+            .codegen()
+            .force_aliases_use(true)
+            .generate_all()
+            .unwrap();
+
+        let static_map = static_map.to_string();
+        let renames = toml.parse::<toml::Table>().unwrap();
+        assert_eq!(renames.len(), 2);
+        for (rust_name, c_name) in renames {
+            let c_name = c_name.as_str().unwrap();
+            assert!(static_map.contains(&format!("(\"{rust_name}\" , \"{c_name}\")")));
+        }
+        assert!(static_map.contains("(\"bgp_peer\" , \"bgp_peer_t\")"));
+    }
+
+    #[test]
+    fn toml_special_keys() {
+        let mapping = |rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: "peer".to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, rust_name)
+        };
+        let rust_names = ["bgp.peer", "bgp\"peer", "bgp\\peer", "bgp_peer"];
+        let mappings = NameMappings {
+            types: HashMap::from_iter(
+                rust_names
+                    .iter()
+                    .enumerate()
+                    .map(|(id, rust_name)| (DiscoveredItemId::new(id), mapping(rust_name))),
+            ),
+            ..Default::default()
+        };
+
+        let toml = mappings.to_cbindgen_toml_renames(false).unwrap();
+        assert_eq!(
+            toml,
+            concat!(
+                "\"bgp\\\"peer\" = \"struct peer\"\n",
+                "\"bgp.peer\" = \"struct peer\"\n",
+                "\"bgp\\\\peer\" = \"struct peer\"\n",
+                "\"bgp_peer\" = \"struct peer\"\n",
+            )
+        );
+
+        // every key parses back as a single key, not as a dotted path
+        let renames = toml.parse::<toml::Table>().unwrap();
+        assert_eq!(renames.len(), rust_names.len());
+        for rust_name in rust_names {
+            assert_eq!(renames[rust_name].as_str(), Some("struct peer"));
+        }
+    }
+
+    #[test]
+    fn per_output_filters() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bgp_peer")),
+                (DiscoveredItemId::new(2), mapping(Struct, "bmp_peer")),
+                (DiscoveredItemId::new(3), mapping(Struct, "internal_state")),
+            ]),
+            ..Default::default()
+        };
+
+        let GeneratedArtifacts {
+            static_map, toml, ..
+        } = mappings
+            .clone()
+            .codegen()
+            .filter(|mapping| !mapping.rust_name.starts_with("internal_"))
+            .static_map_filter(|mapping| mapping.rust_name != "bmp_peer")
+            .generate_all()
+            .unwrap();
+
+        // the map omits the type kept in the toml
+        let static_map = static_map.to_string();
+        assert!(static_map.contains("(\"bgp_peer\" , \"struct bgp_peer\")"));
+        assert!(!static_map.contains("bmp_peer"));
+        assert_eq!(
+            toml,
+            "\"bgp_peer\" = \"struct bgp_peer\"\n\"bmp_peer\" = \"struct bmp_peer\"\n"
+        );
+
+        // the general filter applies to every output
+        assert!(!static_map.contains("internal_state"));
+
+        let toml = mappings
+            .codegen()
+            .toml_filter(|mapping| mapping.rust_name == "internal_state")
+            .generate()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            toml,
+            "\"\\\"internal_state\\\" = \\\"struct internal_state\\\"\\n\""
+        );
+    }
+
+    #[test]
+    fn invalid_variable_names() {
+        for name in ["static", "Self", "my-var", "2nd", "_", "my var"] {
+            let mut codegen = NameMappings::default().codegen();
+            codegen.variable_name(Some(name));
+            assert!(
+                matches!(codegen.generate(), Err(Error::InvalidVariableName(invalid)) if invalid == name),
+                "{name:?}"
+            );
+            assert!(matches!(
+                codegen.generate_all(),
+                Err(Error::InvalidVariableName(_))
+            ));
+        }
+
+        for name in ["RENAMES", "_renames", "union", "r\u{e9}nommages"] {
+            assert!(
+                NameMappings::default()
+                    .codegen()
+                    .variable_name(Some(name))
+                    .generate()
+                    .is_ok(),
+                "{name:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn all_c_names() {
+        let mapping = |c_name: Option<(&str, bool)>, aliases: &[&str]| NameMapping {
+            c_name: c_name.map(|(identifier, aliased)| CName {
+                identifier: identifier.to_string(),
+                aliased,
+            }),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            alias_order: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..mapping(Struct, "bmp_peer")
+        };
+
+        assert_eq!(
+            mapping(Some(("bmp_peer", false)), &["peer_t", "bmp_peer_t"]).all_c_names(),
+            ["bmp_peer_t", "peer_t", "struct bmp_peer"]
+        );
+        // an anonymous type named after one of its aliases only has it once
+        assert_eq!(
+            mapping(Some(("bmp_peer_t", true)), &["bmp_peer_t", "peer_t"]).all_c_names(),
+            ["bmp_peer_t", "peer_t"]
+        );
+        assert!(mapping(None, &[]).all_c_names().is_empty());
+    }
+
+    #[test]
+    fn cfg_gated_items() {
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(1), mapping(Struct, "bmp_peer"))]),
+            ..Default::default()
+        };
+
+        let mut codegen = mappings.codegen();
+        codegen
+            .as_static_map(true)
+            .variable_name(Some("RENAMES"))
+            .cfg(Some(quote! { feature = "ffi" }));
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.starts_with("# [cfg (feature = \"ffi\")] pub static RENAMES"));
+
+        // every item is gated
+        codegen
+            .output(CodegenOutput::SortedSlice)
+            .with_source_hash(true);
+        let code = codegen.generate().unwrap().to_string();
+        assert_eq!(code.matches("# [cfg (feature = \"ffi\")]").count(), 3);
+        assert!(code.contains("# [cfg (feature = \"ffi\")] pub fn renames_lookup"));
+
+        codegen.cfg(Some(
+            quote! { all(unix, not(any(test, target_os = "macos")),) },
+        ));
+        assert!(codegen.generate().is_ok());
+
+        for predicate in [
+            quote! { feature = },
+            quote! { feature = ffi },
+            quote! { all(unix,, test) },
+            quote! { unix, test },
+            quote! { "ffi" },
+        ] {
+            codegen.cfg(Some(predicate));
+            assert!(matches!(codegen.generate(), Err(Error::InvalidCfg(_))));
+            assert!(matches!(codegen.generate_all(), Err(Error::InvalidCfg(_))));
+        }
+    }
+
+    #[test]
+    fn cargo_metadata() {
+        let mapping = |kind, c_name: &str, aliased, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased,
+            }),
+            ..mapping(kind, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(Struct, "bmp_peer", false, "BmpPeer"),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(Union, "bmp_data_t", true, "bmp_data_t"),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let mut printed = Vec::new();
+        mappings
+            .codegen()
+            .write_cargo_metadata(&mut printed)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(printed).unwrap(),
+            "cargo:rename_BmpPeer=struct bmp_peer\n\
+             cargo:rename_bmp_data_t=bmp_data_t\n\
+             cargo:renames=BmpPeer,bmp_data_t\n"
+        );
+
+        let mut printed = Vec::new();
+        NameMappings::default()
+            .codegen()
+            .write_cargo_metadata(&mut printed)
+            .unwrap();
+        assert_eq!(String::from_utf8(printed).unwrap(), "cargo:renames=\n");
+    }
+
+    #[test]
+    fn prefix_aware_renames() {
+        let mapping = |kind, c_name: &str, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(kind, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "my_foo", "Foo")),
+                (DiscoveredItemId::new(2), mapping(Union, "bar", "Bar")),
+                (DiscoveredItemId::new(3), mapping(Enum, "my_", "Empty")),
+            ]),
+            ..Default::default()
+        };
+
+        let without = mappings.to_cbindgen_toml_renames(false).unwrap();
+        assert_eq!(
+            without,
+            "\"Bar\" = \"union bar\"\n\"Empty\" = \"enum my_\"\n\"Foo\" = \"struct my_foo\"\n"
+        );
+
+        let with = mappings
+            .to_cbindgen_toml_renames_with(&RenameOptions {
+                prefix_awareness: Some("my_".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            with,
+            "\"Bar\" = \"union bar\"\n\"Empty\" = \"enum my_\"\n\"Foo\" = \"struct foo\"\n"
+        );
+
+        assert_eq!(unprefixed_c_name("my_foo_t", "my_"), "foo_t");
+        assert_eq!(unprefixed_c_name("struct my_foo", ""), "struct my_foo");
+        assert_eq!(unprefixed_c_name("class my_foo", "my_"), "class foo");
+        assert_eq!(unprefixed_c_name("struct  my_foo", "my_"), "struct  foo");
+        assert_eq!(
+            unprefixed_c_name("enum class my_foo", "my_"),
+            "enum class foo"
+        );
+    }
+
+    #[test]
+    fn split_tag() {
+        assert_eq!(super::split_tag("struct foo"), Some(("struct", "foo")));
+        assert_eq!(super::split_tag("struct\t foo"), Some(("struct", "foo")));
+        assert_eq!(super::split_tag("Union foo"), Some(("union", "foo")));
+        assert_eq!(super::split_tag("class Foo"), Some(("class", "Foo")));
+        assert_eq!(super::split_tag("enum class Foo"), Some(("enum", "Foo")));
+        assert_eq!(super::split_tag("struct_t"), None);
+        assert_eq!(super::split_tag("foo_t"), None);
+    }
+
+    #[test]
+    fn anonymous_enum_typedef() {
+        // typedef enum { X } my_flags;
+        // bindgen doesn't report enums yet so the discovery is driven directly
+        let options = DiscoveryOptions::default();
+        let discover = |mappings: &RefCell<NameMappings>, item| {
+            let discovery = Discovery {
+                mappings,
+                options: &options,
+                doc: None,
+                source: None,
+            };
+            match item {
+                None => discovery.new_composite_found(
+                    DiscoveredItemId::new(1),
+                    Enum,
+                    None,
+                    "_bindgen_ty_1",
+                ),
+                Some(alias_name) => discovery.new_alias_found(
+                    DiscoveredItemId::new(2),
+                    alias_name,
+                    DiscoveredItemId::new(1),
+                ),
+            }
+        };
+
+        // the typedef may be found before or after the enum
+        for items in [[None, Some("my_flags")], [Some("my_flags"), None]] {
+            let mappings = RefCell::new(NameMappings::default());
+            for item in items {
+                discover(&mappings, item);
+            }
+
+            let mappings = mappings.take();
+            let mapping = &mappings.types[&DiscoveredItemId::new(1)];
+            assert_eq!(mapping.kind, Enum);
+            assert_eq!(
+                mapping.c_name,
+                Some(CName {
+                    identifier: "my_flags".to_string(),
+                    aliased: true,
+                })
+            );
+            assert!(mapping.aliases.is_empty());
+            assert_eq!(
+                mappings.to_cbindgen_toml_renames(false).unwrap(),
+                "\"_bindgen_ty_1\" = \"my_flags\"\n"
+            );
+        }
+
+        // without a typedef the enum can't be named
+        let mappings = RefCell::new(NameMappings::default());
+        discover(&mappings, None);
+        assert_eq!(mappings.take().to_cbindgen_toml_renames(false).unwrap(), "");
+    }
+
+    #[test]
+    fn static_map_round_trip() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Union, "bmp_data")),
+                (DiscoveredItemId::new(2), mapping(Struct, "bmp_peer")),
+                (
+                    DiscoveredItemId::new(3),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t"]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let code = mappings
+            .clone()
+            .codegen()
+            .as_static_map(true)
+            .force_aliases_use(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+        let parsed = NameMappings::from_static_map_source(&code).unwrap();
+
+        assert_eq!(
+            parsed.to_cbindgen_toml_renames(false).unwrap(),
+            mappings.to_cbindgen_toml_renames(true).unwrap()
+        );
+        assert_eq!(parsed.types.len(), 3);
+
+        let parsed = NameMappings::from_static_map_source(
+            r#"phf_map! { "bmp_data" => "union bmp_data", "peer" => "peer_t" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.to_cbindgen_toml_renames(false).unwrap(),
+            "\"bmp_data\" = \"union bmp_data\"\n\"peer\" = \"peer_t\"\n"
+        );
+    }
+
+    #[test]
+    fn with_capacity() {
+        let items = [
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Alias {
+                    alias_name: "bgp_peer_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: Some("bgp_peer".to_string()),
+                    final_name: "bgp_peer".to_string(),
+                },
+            ),
+        ];
+
+        let default = Rc::new(RefCell::new(NameMappings::default()));
+        let with_capacity = Rc::new(RefCell::new(NameMappings::with_capacity(1024)));
+        assert_eq!(default, with_capacity);
+
+        for mappings in [&default, &with_capacity] {
+            let callback = NameMappingsCallback(Rc::clone(mappings));
+            for (id, item) in items.clone() {
+                callback.new_item_found(id, item);
+            }
+        }
+
+        assert_eq!(default, with_capacity);
+        assert_eq!(
+            default.borrow().to_cbindgen_toml_renames(true).unwrap(),
+            with_capacity
+                .borrow()
+                .to_cbindgen_toml_renames(true)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn case_styles() {
+        assert_eq!(
+            CaseStyle::Snake.convert("HTTPServer_config"),
+            "http_server_config"
+        );
+        assert_eq!(
+            CaseStyle::Pascal.convert("HTTPServer_config"),
+            "HttpServerConfig"
+        );
+        assert_eq!(CaseStyle::Snake.convert("_myType2Peer"), "_my_type2_peer");
+        assert_eq!(CaseStyle::Pascal.convert("foo::bar_baz"), "Foo::BarBaz");
+
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "MyType")),
+                (DiscoveredItemId::new(2), mapping(Union, "bmp_data")),
+            ]),
+            ..Default::default()
+        };
+
+        let renames = |style| {
+            mappings
+                .to_cbindgen_toml_renames_with(&RenameOptions {
+                    case_style: Some(style),
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+        assert_eq!(
+            renames(CaseStyle::Snake),
+            "\"MyType\" = \"struct my_type\"\n\"bmp_data\" = \"union bmp_data\"\n"
+        );
+        assert_eq!(
+            renames(CaseStyle::Pascal),
+            "\"MyType\" = \"struct MyType\"\n\"bmp_data\" = \"union BmpData\"\n"
+        );
+    }
+
+    #[test]
+    fn strip_rust_affixes() {
+        let mapping = |name: &str, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping("bgp_peer", "ffi_bgp_peer_t"),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping("bmp_peer", "ffi_bmp_peer"),
+                ),
+            ]),
+            ..Default::default()
+        };
+        let mut codegen = mappings.codegen();
+        codegen.output(CodegenOutput::MatchFn);
+
+        codegen.strip_rust_prefix("ffi_");
+        assert!(codegen.generate().unwrap().to_string().contains(
+            "\"bgp_peer_t\" => Some (\"struct bgp_peer\") , \
+                       \"bmp_peer\" => Some (\"struct bmp_peer\")"
+        ));
+
+        codegen.strip_rust_suffix("_t");
+        assert!(codegen.generate().unwrap().to_string().contains(
+            "\"bgp_peer\" => Some (\"struct bgp_peer\") , \
+                       \"bmp_peer\" => Some (\"struct bmp_peer\")"
+        ));
+
+        // `ffi_bmp_peer` and `bmp_peer` are both `bmp_peer` once stripped
+        let mut mappings = codegen.mappings();
+        mappings
+            .types
+            .insert(DiscoveredItemId::new(3), mapping("other_peer", "bmp_peer"));
+        let mut codegen = mappings.codegen();
+        codegen.strip_rust_prefix("ffi_");
+        assert!(matches!(
+            codegen.generate(),
+            Err(Error::RenameKeyCollision { key, .. }) if key == "bmp_peer"
+        ));
+    }
+
+    #[test]
+    fn mapped_rust_names() {
+        let mapping = |name: &str, rust_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, rust_name)
+        };
+        let mut mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping("foo", "ffi::Foo")),
+                (DiscoveredItemId::new(2), mapping("bar", "ffi::Bar")),
+            ]),
+            ..Default::default()
+        };
+
+        let mut codegen = mappings.clone().codegen();
+        codegen
+            .output(CodegenOutput::TomlString)
+            .map_rust_name(|key| key.trim_start_matches("ffi::").to_string());
+        assert_eq!(
+            codegen.generate().unwrap().to_string(),
+            Literal::string("\"Bar\" = \"struct bar\"\n\"Foo\" = \"struct foo\"\n").to_string()
+        );
+
+        // `ffi::Foo` and `Foo` are both `Foo` once rewritten
+        mappings
+            .types
+            .insert(DiscoveredItemId::new(3), mapping("other_foo", "Foo"));
+        let mut codegen = mappings.codegen();
+        codegen.map_rust_name(|key| key.trim_start_matches("ffi::").to_string());
+        assert!(matches!(
+            codegen.generate(),
+            Err(Error::RenameKeyCollision { key, .. }) if key == "Foo"
+        ));
+    }
+
+    #[test]
+    fn bidirectional_maps() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Union, "bmp_data")),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t", "peer_t"]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let code = mappings
+            .codegen()
+            .as_static_map(true)
+            .bidirectional(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+
+        assert!(code.contains("pub static RENAMES :"));
+        assert!(code.contains("pub static RENAMES_REVERSE :"));
+
+        let (forward, reverse) = code.split_once("RENAMES_REVERSE").unwrap();
+        for entry in [
+            "(\"bgp_peer\" , \"struct bgp_peer\")",
+            "(\"bmp_data\" , \"union bmp_data\")",
+        ] {
+            assert!(forward.contains(entry));
+        }
+        for entry in [
+            "(\"struct bgp_peer\" , \"bgp_peer\")",
+            "(\"bgp_peer_t\" , \"bgp_peer\")",
+            "(\"peer_t\" , \"bgp_peer\")",
+            "(\"union bmp_data\" , \"bmp_data\")",
+        ] {
+            assert!(reverse.contains(entry));
+        }
+
+        // an alias can't lead back to two types
+        let mut mappings = NameMappings::default();
+        mappings.types.insert(
+            DiscoveredItemId::new(1),
+            mapping_with_aliases(Struct, "a", &["handle_t"]),
+        );
+        mappings.types.insert(
+            DiscoveredItemId::new(2),
+            mapping_with_aliases(Struct, "b", &["handle_t"]),
+        );
+        assert!(matches!(
+            mappings.codegen().as_static_map(true).bidirectional(true).generate(),
+            Err(Error::RenameKeyCollision { key, .. }) if key == "handle_t"
+        ));
+    }
+
+    #[test]
+    fn case_insensitive_aliases() {
+        let items = [
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: Some("handle".to_string()),
+                    final_name: "handle".to_string(),
+                },
+            ),
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Alias {
+                    alias_name: "Handle_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(3),
+                DiscoveredItem::Alias {
+                    alias_name: "HANDLE_T".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(4),
+                DiscoveredItem::Alias {
+                    alias_name: "handle_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+        ];
+
+        let discover = |options: DiscoveryOptions| {
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+            for (id, item) in items.clone() {
+                callback.new_item_found(id, item);
+            }
+            let aliases = mappings.borrow().types[&DiscoveredItemId::new(1)]
+                .aliases
+                .clone();
+            aliases
+        };
+
+        assert_eq!(discover(DiscoveryOptions::default()).len(), 3);
+
+        let mut options = DiscoveryOptions::default();
+        options.case_insensitive_aliases(true);
+        assert_eq!(discover(options), BTreeSet::from(["HANDLE_T".to_string()]));
+    }
+
+    #[test]
+    fn rejected_aliases() {
+        // typedef struct Foo Foo; typedef struct Foo foo_t; found before struct Foo,
+        // along with its tagged self-reference
+        let items = [
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Alias {
+                    alias_name: "struct Foo".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(3),
+                DiscoveredItem::Alias {
+                    alias_name: "Foo".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(4),
+                DiscoveredItem::Alias {
+                    alias_name: "foo_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: Some("Foo".to_string()),
+                    final_name: "Foo".to_string(),
+                },
+            ),
+            (
+                DiscoveredItemId::new(5),
+                DiscoveredItem::Alias {
+                    alias_name: "FOO_T".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+        ];
+        let discover = |options: DiscoveryOptions| {
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+            for (id, item) in items.clone() {
+                callback.new_item_found(id, item);
+            }
+            mappings.take()
+        };
+
+        let mut options = DiscoveryOptions::default();
+        options.record_rejected_aliases(true);
+        let mappings = discover(options.clone());
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].aliases,
+            BTreeSet::from(["FOO_T".to_string(), "Foo".to_string(), "foo_t".to_string()])
+        );
+        assert_eq!(
+            mappings.rejected_aliases,
+            HashMap::from([(
+                DiscoveredItemId::new(1),
+                BTreeSet::from(["struct Foo".to_string()])
+            )])
+        );
+
+        // the aliases collapsed by case are rejected too
+        options.case_insensitive_aliases(true);
+        assert_eq!(
+            discover(options).rejected_aliases[&DiscoveredItemId::new(1)],
+            BTreeSet::from(["foo_t".to_string(), "struct Foo".to_string()])
+        );
+
+        assert!(discover(DiscoveryOptions::default())
+            .rejected_aliases
+            .is_empty());
+    }
+
+    #[test]
+    fn dependencies() {
+        // struct leaf { int x; }; typedef struct leaf leaf_t;
+        // struct inner { leaf_t leaf; }; struct outer { struct inner inner; }; struct unrelated { int y; };
+        let mut options = DiscoveryOptions::default();
+        options.capture_dependencies(true);
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+        let composite = |name: &str| DiscoveredItem::Struct {
+            original_name: Some(name.to_string()),
+            final_name: name.to_string(),
+        };
+
+        // the fields are reported before the type holding them
+        callback.field_found("outer", "inner");
+        callback.new_item_found(DiscoveredItemId::new(1), composite("outer"));
+        callback.field_found("inner", "leaf_t");
+        callback.new_item_found(DiscoveredItemId::new(2), composite("inner"));
+        callback.new_item_found(DiscoveredItemId::new(3), composite("leaf"));
+        callback.new_item_found(
+            DiscoveredItemId::new(4),
+            DiscoveredItem::Alias {
+                alias_name: "leaf_t".to_string(),
+                alias_for: DiscoveredItemId::new(3),
+            },
+        );
+        callback.new_item_found(DiscoveredItemId::new(5), composite("unrelated"));
+        callback.field_found("dangling", "missing_t");
+
+        // resolved once the discovery is over
+        assert_eq!(mappings.borrow().pending_field_references.len(), 3);
+        drop(callback);
+        let mappings = mappings.borrow();
+        assert_eq!(
+            mappings.pending_field_references,
+            vec![("dangling".to_string(), "missing_t".to_string())]
+        );
+        assert_eq!(
+            mappings.dependencies_of(DiscoveredItemId::new(1)),
+            BTreeSet::from([DiscoveredItemId::new(2), DiscoveredItemId::new(3)])
+        );
+        assert_eq!(
+            mappings.dependencies_of(DiscoveredItemId::new(2)),
+            BTreeSet::from([DiscoveredItemId::new(3)])
+        );
+        assert!(mappings
+            .dependencies_of(DiscoveredItemId::new(5))
+            .is_empty());
+    }
+
+    #[test]
+    fn discovered_dependencies() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let mut options = DiscoveryOptions::default();
+        options.capture_dependencies(true);
+        Builder::default()
+            .header_contents(
+                "dependencies.h",
+                "struct leaf { int x; }; typedef struct leaf leaf_t;
+                struct inner { leaf_t leaf; }; struct outer { struct inner inner; };
+                struct unrelated { int y; };",
+            )
+            .parse_callbacks(Box::new(
+                NameMappingsCallback(Rc::clone(&mappings)).with_options(options),
+            ))
+            .generate()
+            .unwrap();
+
+        let mappings = mappings.take();
+        let id = |rust_name: &str| {
+            mappings
+                .types
+                .iter()
+                .find(|(_, mapping)| mapping.rust_name == rust_name)
+                .map(|(id, _)| *id)
+                .unwrap()
+        };
+        assert!(mappings.pending_field_references.is_empty());
+        assert_eq!(
+            mappings.dependencies_of(id("outer")),
+            BTreeSet::from([id("inner"), id("leaf")])
+        );
+        assert!(mappings.dependencies_of(id("unrelated")).is_empty());
+    }
+
+    #[test]
+    fn alias_conflict_policies() {
+        // typedef struct first handle_t; in a header
+        // typedef struct second handle_t; in another one
+        let discover = |policy| {
+            let mut options = DiscoveryOptions::default();
+            options.alias_conflicts(policy);
+
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+            // the first alias is still pending when the second one is found
+            callback.new_item_found(
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Alias {
+                    alias_name: "handle_t".to_string(),
+                    alias_for: DiscoveredItemId::new(2),
+                },
+            );
+            for (id, name) in [(2, "first"), (3, "second")] {
+                callback.new_item_found(
+                    DiscoveredItemId::new(id),
+                    DiscoveredItem::Struct {
+                        original_name: Some(name.to_string()),
+                        final_name: name.to_string(),
+                    },
+                );
+            }
+            callback.new_item_found(
+                DiscoveredItemId::new(4),
+                DiscoveredItem::Alias {
+                    alias_name: "handle_t".to_string(),
+                    alias_for: DiscoveredItemId::new(3),
+                },
+            );
+            mappings.take()
+        };
+        let aliases = |mappings: &NameMappings, id| {
+            mappings.types[&DiscoveredItemId::new(id)].aliases.clone()
+        };
+        let handle_t = BTreeSet::from(["handle_t".to_string()]);
+
+        let mappings = discover(AliasConflictPolicy::KeepFirst);
+        assert_eq!(aliases(&mappings, 2), handle_t);
+        assert!(aliases(&mappings, 3).is_empty());
+        assert_eq!(
+            mappings.alias_conflicts,
+            vec![AliasConflict {
+                alias: "handle_t".to_string(),
+                first: DiscoveredItemId::new(2),
+                second: DiscoveredItemId::new(3),
+                policy: AliasConflictPolicy::KeepFirst,
+            }]
+        );
+        assert!(mappings.to_cbindgen_toml_renames(true).is_ok());
+
+        let mappings = discover(AliasConflictPolicy::KeepLast);
+        assert!(aliases(&mappings, 2).is_empty());
+        assert_eq!(aliases(&mappings, 3), handle_t);
+        assert_eq!(mappings.alias_conflicts.len(), 1);
+
+        let mappings = discover(AliasConflictPolicy::Error);
+        assert_eq!(aliases(&mappings, 2), handle_t);
+        assert!(matches!(
+            mappings.to_cbindgen_toml_renames(false),
+            Err(Error::AliasConflict(alias)) if alias == "handle_t"
+        ));
+
+        // an anonymous type losing its name takes its first declared alias, not the first alphabetically
+        let mut mappings = NameMappings::default();
+        mappings.types.insert(
+            DiscoveredItemId::new(1),
+            NameMapping {
+                c_name: Some(CName {
+                    identifier: "handle_t".to_string(),
+                    aliased: true,
+                }),
+                ..mapping_with_aliases(Struct, "_bindgen_ty_1", &["b_t", "a_t"])
+            },
+        );
+        // the hand-made mappings are indexed on first use
+        mappings.attach_alias(
+            DiscoveredItemId::new(2),
+            "handle_t".to_string(),
+            AliasConflictPolicy::KeepLast,
+        );
+        let anonymous = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(
+            anonymous
+                .c_name
+                .as_ref()
+                .map(|c_name| c_name.identifier.as_str()),
+            Some("b_t")
+        );
+        assert_eq!(anonymous.alias_order, ["a_t"]);
+        assert_eq!(mappings.alias_conflicts.len(), 1);
+        assert_eq!(
+            mappings.alias_holder("handle_t"),
+            Some(DiscoveredItemId::new(2))
+        );
+        assert_eq!(mappings.alias_holder("b_t"), Some(DiscoveredItemId::new(1)));
+        assert_eq!(mappings.alias_holder("a_t"), Some(DiscoveredItemId::new(1)));
+    }
+
+    #[test]
+    fn callback_builder() {
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&warnings);
+
+        let mut builder = NameMappingsCallbackBuilder::default();
+        builder
+            .skip_system_headers(true)
+            .keep_prefix("__mylib_")
+            .capacity(8)
+            .diagnostics(move |warning| sink.borrow_mut().push(warning.to_string()))
+            .options()
+            .alias_conflicts(AliasConflictPolicy::KeepLast);
+        let (callback, mappings) = builder.build();
+        assert!(mappings.borrow().types.capacity() >= 8);
+
+        for (id, name) in [(1, "__mylib_first"), (2, "second"), (3, "__libc_state")] {
+            callback.new_item_found(
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Struct {
+                    original_name: Some(name.to_string()),
+                    final_name: name.to_string(),
+                },
+            );
+        }
+        for (id, target) in [(4, 1), (5, 2)] {
+            callback.new_item_found(
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Alias {
+                    alias_name: "handle_t".to_string(),
+                    alias_for: DiscoveredItemId::new(target),
+                },
+            );
+        }
+
+        let mappings = mappings.take();
+        assert_eq!(mappings.types.len(), 2);
+        assert!(mappings.types[&DiscoveredItemId::new(1)].aliases.is_empty());
+        assert_eq!(mappings.alias_conflicts.len(), 1);
+        assert_eq!(
+            warnings.take(),
+            vec![format!(
+                "Warn: alias \"handle_t\" found for {{ id={:?} }} and {{ id={:?} }}, applying KeepLast",
+                DiscoveredItemId::new(1),
+                DiscoveredItemId::new(2)
+            )]
+        );
+
+        // an invalid pattern is reported right away
+        assert!(NameMappingsCallbackBuilder::default().deny("(").is_err());
+    }
+
+    #[test]
+    fn discovery_runs() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let structure = |name: &str| DiscoveredItem::Struct {
+            original_name: Some(name.to_string()),
+            final_name: name.to_string(),
+        };
+        let alias = |name: &str, target| DiscoveredItem::Alias {
+            alias_name: name.to_string(),
+            alias_for: DiscoveredItemId::new(target),
+        };
+
+        // both runs include the header of `common`, and bindgen's ids start over at each run
+        let first =
+            NameMappingsCallback(Rc::clone(&mappings)).with_options(DiscoveryOptions::default());
+        first.new_item_found(DiscoveredItemId::new(1), structure("common"));
+        first.new_item_found(DiscoveredItemId::new(2), alias("common_t", 1));
+        first.new_item_found(DiscoveredItemId::new(3), structure("bgp_peer"));
+
+        let second =
+            NameMappingsCallback(Rc::clone(&mappings)).with_options(DiscoveryOptions::default());
+        second.new_item_found(DiscoveredItemId::new(1), alias("common_t", 2));
+        second.new_item_found(DiscoveredItemId::new(2), structure("common"));
+        second.new_item_found(DiscoveredItemId::new(3), structure("bmp_peer"));
+        second.new_item_found(DiscoveredItemId::new(4), alias("bmp_peer_t", 3));
+
+        let mappings = mappings.take();
+        assert_eq!(mappings.discovery_runs, 2);
+        assert_eq!(mappings.types.len(), 3);
+        assert!(mappings.alias_conflicts.is_empty());
+        assert!(!mappings.has_pending_aliases());
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(3)].rust_name,
+            "bgp_peer"
+        );
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].aliases,
+            BTreeSet::from(["common_t".to_string()])
+        );
+
+        let bmp_peer = mappings
+            .types
+            .iter()
+            .find(|(_, mapping)| mapping.rust_name == "bmp_peer")
+            .unwrap();
+        assert!(mappings.is_synthetic_id(*bmp_peer.0));
+        assert_eq!(
+            bmp_peer.1.aliases,
+            BTreeSet::from(["bmp_peer_t".to_string()])
+        );
+
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(true).unwrap(),
+            "\"bgp_peer\" = \"struct bgp_peer\"\n\"bmp_peer\" = \"bmp_peer_t\"\n\"common\" = \"common_t\"\n"
+        );
+    }
+
+    #[test]
+    fn anonymous_type_names() {
+        let anonymous = |id: usize| {
+            (
+                DiscoveredItemId::new(id),
+                DiscoveredItem::Union {
+                    original_name: None,
+                    final_name: format!("_bindgen_ty_{id}"),
+                },
+            )
+        };
+
+        let mut options = DiscoveryOptions::default();
+        options.name_anonymous_types(|id, kind| {
+            (id != DiscoveredItemId::new(3)).then(|| format!("anon_{kind}"))
+        });
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options);
+        for (id, item) in [anonymous(1), anonymous(3)] {
+            callback.new_item_found(id, item);
+        }
+        // the typedef found before the type wins
+        callback.new_item_found(
+            DiscoveredItemId::new(4),
+            DiscoveredItem::Alias {
+                alias_name: "typedefed_t".to_string(),
+                alias_for: DiscoveredItemId::new(5),
+            },
+        );
+        let (id, item) = anonymous(5);
+        callback.new_item_found(id, item);
+
+        let mappings = mappings.take();
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].c_name,
+            Some(CName {
+                identifier: "anon_union".to_string(),
+                aliased: true,
+            })
+        );
+        assert_eq!(mappings.types[&DiscoveredItemId::new(3)].c_name, None);
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(5)].c_name,
+            Some(CName {
+                identifier: "typedefed_t".to_string(),
+                aliased: true,
+            })
+        );
+
+        // no tag keyword
+        assert!(mappings
+            .to_cbindgen_toml_renames(false)
+            .unwrap()
+            .contains("\"_bindgen_ty_1\" = \"anon_union\"\n"));
+    }
+
+    #[test]
+    fn capture_sources() {
+        let bgp_peer = (
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("bgp_peer".to_string()),
+                final_name: "bgp_peer".to_string(),
+            },
+        );
+
+        let mut options = DiscoveryOptions::default();
+        options.capture_sources(true);
+
+        // bindgen reports the headers before discovering the types
+        let discover = |headers: &[&str]| {
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let callback = NameMappingsCallback(Rc::clone(&mappings)).with_options(options.clone());
+            for header in headers {
+                callback.header_file(header);
+            }
+            let (id, item) = bgp_peer.clone();
+            callback.new_item_found(id, item);
+            mappings.take()
+        };
+
+        let mappings = discover(&["include/bgp.h"]);
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].source,
+            Some(PathBuf::from("include/bgp.h"))
+        );
+        let by_source = mappings.by_source();
+        assert_eq!(by_source.len(), 1);
+        assert_eq!(
+            by_source[&PathBuf::from("include/bgp.h")][0].rust_name,
+            "bgp_peer"
+        );
+
+        // can't tell which header a type comes from
+        let mappings = discover(&["include/bgp.h", "include/bmp.h"]);
+        assert_eq!(mappings.types[&DiscoveredItemId::new(1)].source, None);
+        assert!(mappings.by_source().is_empty());
+    }
+
+    #[test]
+    fn split_by_kind() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bgp_peer")),
+                (DiscoveredItemId::new(2), mapping(Union, "bmp_data")),
+                (DiscoveredItemId::new(3), mapping(Enum, "bmp_type")),
+                (DiscoveredItemId::new(4), mapping(Struct, "bmp_peer")),
+            ]),
+            ..Default::default()
+        };
+
+        let code = mappings
+            .codegen()
+            .as_static_map(true)
+            .split_by_kind(true)
+            .variable_name(Some("RENAMES"))
+            .generate()
+            .unwrap()
+            .to_string();
+
+        let statics: Vec<&str> = code
+            .split("pub static ")
+            .skip(1)
+            .map(|item| item.split(' ').next().unwrap())
+            .collect();
+        assert_eq!(
+            statics,
+            ["RENAMES_STRUCTS", "RENAMES_UNIONS", "RENAMES_ENUMS"]
+        );
+
+        let (structs, rest) = code.split_once("RENAMES_UNIONS").unwrap();
+        let (unions, enums) = rest.split_once("RENAMES_ENUMS").unwrap();
+        assert!(structs.contains("(\"bgp_peer\" , \"struct bgp_peer\")"));
+        assert!(structs.contains("(\"bmp_peer\" , \"struct bmp_peer\")"));
+        assert!(unions.contains("(\"bmp_data\" , \"union bmp_data\")"));
+        assert!(!unions.contains("bmp_peer"));
+        assert!(enums.contains("(\"bmp_type\" , \"enum bmp_type\")"));
+    }
+
+    #[test]
+    fn partition_by() {
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bgp_peer")),
+                (DiscoveredItemId::new(2), mapping(Union, "bmp_data")),
+                (DiscoveredItemId::new(3), mapping(Enum, "bmp_type")),
+                (DiscoveredItemId::new(4), mapping(Struct, "bmp_peer")),
+            ]),
+            ..Default::default()
+        };
+        let statics = |code: &str| -> Vec<String> {
+            code.split("pub static ")
+                .skip(1)
+                .map(|item| item.split(' ').next().unwrap().to_string())
+                .collect()
+        };
+
+        let mut codegen = mappings.codegen();
+        codegen
+            .as_static_map(true)
+            .partition_by(|mapping| mapping.kind.to_string());
+        let by_kind = codegen.generate().unwrap().to_string();
+        assert_eq!(
+            statics(&by_kind),
+            ["RENAMES_ENUM", "RENAMES_STRUCT", "RENAMES_UNION"]
+        );
+
+        // overrides split_by_kind
+        let by_header = codegen
+            .split_by_kind(true)
+            .variable_name(Some("MAP"))
+            .partition_by(|mapping| format!("{}.h", &mapping.rust_name[..3]))
+            .generate()
+            .unwrap()
+            .to_string();
+        assert_eq!(statics(&by_header), ["MAP_BGP_H", "MAP_BMP_H"]);
+        let (bgp, bmp) = by_header.split_once("MAP_BMP_H").unwrap();
+        assert!(bgp.contains("(\"bgp_peer\" , \"struct bgp_peer\")"));
+        assert!(!bgp.contains("bmp_"));
+        assert!(bmp.contains("(\"bmp_data\" , \"union bmp_data\")"));
+        assert!(bmp.contains("(\"bmp_type\" , \"enum bmp_type\")"));
+        assert!(bmp.contains("(\"bmp_peer\" , \"struct bmp_peer\")"));
+    }
+
+    #[test]
+    fn roots() {
+        // bmp_peer holds a bgp_open, bgp_state is unrelated
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping(Struct, "bmp_peer")),
+                (DiscoveredItemId::new(2), mapping(Struct, "bgp_open")),
+                (DiscoveredItemId::new(3), mapping(Struct, "bgp_state")),
+            ]),
+            dependencies: HashMap::from([(
+                DiscoveredItemId::new(1),
+                BTreeSet::from([DiscoveredItemId::new(2)]),
+            )]),
+            ..Default::default()
+        };
+
+        let toml = mappings
+            .clone()
+            .codegen()
+            .roots(&["bmp_peer", "not_a_type"])
+            .generate_all()
+            .unwrap()
+            .toml;
+        assert_eq!(
+            toml,
+            "\"bgp_open\" = \"struct bgp_open\"\n\"bmp_peer\" = \"struct bmp_peer\"\n"
+        );
+
+        let map = mappings
+            .codegen()
+            .as_static_map(true)
+            .roots(&["bgp_open"])
+            .generate()
+            .unwrap()
+            .to_string();
+        assert!(map.contains("bgp_open"));
+        assert!(!map.contains("bmp_peer"));
+        assert!(!map.contains("bgp_state"));
+    }
+
+    #[test]
+    fn stable_anonymous_names() {
+        let mapping = |kind, rust_name: &str, c_name: &str, order| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased: true,
+            }),
+            aliases: BTreeSet::from([c_name.to_string()]),
+            alias_order: vec![c_name.to_string()],
+            order,
+            ..mapping(kind, rust_name)
+        };
+        // the same header discovered twice, bindgen numbered its anonymous types differently
+        let first = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(Struct, "bmp_peer", "bmp_peer", 0),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(Struct, "_bindgen_ty_1", "bmp_flags", 1),
+                ),
+                (
+                    DiscoveredItemId::new(3),
+                    mapping(Enum, "_bindgen_ty_2", "bmp_state", 2),
+                ),
+                (
+                    DiscoveredItemId::new(4),
+                    mapping(Struct, "_bindgen_ty_3", "bmp_stats", 3),
+                ),
+            ]),
+            ..Default::default()
+        };
+        let second = NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(10),
+                    mapping(Struct, "bmp_peer", "bmp_peer", 0),
+                ),
+                (
+                    DiscoveredItemId::new(14),
+                    mapping(Struct, "_bindgen_ty_9", "bmp_flags", 1),
+                ),
+                (
+                    DiscoveredItemId::new(12),
+                    mapping(Enum, "_bindgen_ty_7", "bmp_state", 2),
+                ),
+                (
+                    DiscoveredItemId::new(11),
+                    mapping(Struct, "_bindgen_ty_8", "bmp_stats", 3),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let generate = |mappings: NameMappings| {
+            mappings
+                .codegen()
+                .stable_anonymous_names(Some("anon_"))
+                .generate_all()
+                .unwrap()
+        };
+        let artifacts = generate(first.clone());
+        // the rules keep the names of the Rust types
+        assert_eq!(
+            artifacts.toml,
+            "\"_bindgen_ty_1\" = \"bmp_flags\"\n\"_bindgen_ty_2\" = \"bmp_state\"\n\"_bindgen_ty_3\" = \"bmp_stats\"\n\"bmp_peer\" = \"bmp_peer\"\n"
+        );
+        assert_eq!(
+            artifacts.stable_names,
+            "\"anon_enum_0\" = \"_bindgen_ty_2\"\n\"anon_struct_0\" = \"_bindgen_ty_1\"\n\"anon_struct_1\" = \"_bindgen_ty_3\"\n"
+        );
+        assert_eq!(
+            generate(second).stable_names,
+            "\"anon_enum_0\" = \"_bindgen_ty_7\"\n\"anon_struct_0\" = \"_bindgen_ty_9\"\n\"anon_struct_1\" = \"_bindgen_ty_8\"\n"
+        );
 
-    use crate::import::{CName, NameMapping, NameMappings, NameMappingsCallback};
-    use crate::import::CompositeKind::{Struct, Union};
+        assert_eq!(
+            first.stable_anonymous_names("anon_"),
+            HashMap::from([
+                (DiscoveredItemId::new(2), "anon_struct_0".to_string()),
+                (DiscoveredItemId::new(3), "anon_enum_0".to_string()),
+                (DiscoveredItemId::new(4), "anon_struct_1".to_string()),
+            ])
+        );
 
-    #[test]
-    fn pass() {}
+        // no lookup table by default
+        assert!(first
+            .codegen()
+            .generate_all()
+            .unwrap()
+            .stable_names
+            .is_empty());
+    }
 
     #[test]
-    fn test_mappings() {
+    fn stable_anonymous_names_across_headers() {
+        // the anonymous union added in front shifts bindgen's numbers, not the stable names of the structs
+        let discover = |header: &str| {
+            let mappings = Rc::new(RefCell::new(NameMappings::default()));
+            let bindings = Builder::default()
+                .header_contents("anonymous.h", header)
+                .parse_callbacks(Box::new(NameMappingsCallback(Rc::clone(&mappings))))
+                .generate()
+                .unwrap()
+                .to_string();
+            let stable_names = mappings.take().to_stable_names_toml("anon_").unwrap();
+            (stable_names, bindings)
+        };
+        let structs = "struct { int a; } first; struct { int b; } second;";
+        let (before, before_bindings) = discover(structs);
+        let (after, after_bindings) = discover(&format!("union {{ int c; }} extra; {structs}"));
 
-        let mappings = Rc::new(RefCell::new(NameMappings::default()));
-        Builder::default()
-            .header_contents("sample_header.h","
-                // Unions
-                void function_using_anonymous_struct(struct {} arg0);
+        let table = |stable_names: &str| -> Vec<(String, String)> {
+            stable_names
+                .lines()
+                .filter_map(|line| line.split_once(" = "))
+                .map(|(key, value)| {
+                    (
+                        key.trim_matches('"').to_string(),
+                        value.trim_matches('"').to_string(),
+                    )
+                })
+                .collect()
+        };
+        let (before, after) = (table(&before), table(&after));
+        let keys = |table: &[(String, String)]| -> Vec<String> {
+            table.iter().map(|(key, _)| key.clone()).collect()
+        };
+        assert_eq!(keys(&before), ["anon_struct_0", "anon_struct_1"]);
+        assert_eq!(
+            keys(&after),
+            ["anon_struct_0", "anon_struct_1", "anon_union_0"]
+        );
+        assert_ne!(before[0].1, after[0].1);
+        // the table points to the actual Rust types
+        for (table, bindings) in [(&before, &before_bindings), (&after, &after_bindings)] {
+            for (_, rust_name) in table {
+                assert!(bindings.contains(rust_name.as_str()));
+            }
+        }
+    }
 
-                struct NamedStruct {
-                };
+    #[test]
+    fn duplicate_rust_names() {
+        let mapping = |name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, "bgp_peer")
+        };
 
-                typedef struct NamedStruct AliasOfNamedStruct;
+        // e.g. after merging the mappings of two bindgen runs
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping("bgp_peer")),
+                (DiscoveredItemId::new(2), mapping("bgp_peer_v2")),
+            ]),
+            ..Default::default()
+        };
 
+        let is_collision = |result: Result<_, Error>| {
+            matches!(
+                result,
+                Err(Error::RenameKeyCollision { key, rust_names })
+                    if key == "bgp_peer" && rust_names == ["bgp_peer", "bgp_peer"]
+            )
+        };
+        assert!(is_collision(mappings.to_static_map(false).map(|_| ())));
+        assert!(is_collision(
+            mappings.to_cbindgen_toml_renames(false).map(|_| ())
+        ));
+        assert!(is_collision(
+            mappings
+                .codegen()
+                .as_static_map(true)
+                .generate()
+                .map(|_| ())
+        ));
+    }
 
-                // Unions
-                void function_using_anonymous_union(union {} arg0);
+    #[test]
+    fn checked_static_map() {
+        let mapping = |rust_name: &str, c_name: &str| NameMapping {
+            c_name: Some(CName {
+                identifier: c_name.to_string(),
+                aliased: false,
+            }),
+            ..mapping(Struct, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                (DiscoveredItemId::new(1), mapping("bgp_peer", "bgp_peer")),
+                (DiscoveredItemId::new(2), mapping("bgp_peer", "bgp_peer_v2")),
+                (DiscoveredItemId::new(3), mapping("bmp_peer", "peer")),
+                (DiscoveredItemId::new(4), mapping("Peer", "peer")),
+                (DiscoveredItemId::new(5), mapping("bmp_data", "bmp_data")),
+            ]),
+            ..Default::default()
+        };
 
-                union NamedUnion {
-                };
+        let (map, conflicts) = mappings
+            .to_static_map_checked(&RenameOptions::default())
+            .unwrap();
+        assert_eq!(
+            conflicts,
+            [
+                MapConflict::DuplicateKey {
+                    key: "bgp_peer".to_string(),
+                    rust_names: vec!["bgp_peer".to_string(), "bgp_peer".to_string()],
+                },
+                MapConflict::DuplicateTarget {
+                    c_name: "struct peer".to_string(),
+                    keys: vec!["Peer".to_string(), "bmp_peer".to_string()],
+                },
+            ]
+        );
 
-                typedef union NamedUnion AliasOfNamedUnion;
-        ")
-            .parse_callbacks(Box::new(NameMappingsCallback(Rc::clone(&mappings))))
-            .generate()
+        // the map holds the other entries, including the ones sharing a target
+        let map = map.build().to_string();
+        assert!(!map.contains("bgp_peer"));
+        assert!(map.contains("(\"bmp_data\", \"struct bmp_data\")"));
+        assert!(map.contains("(\"bmp_peer\", \"struct peer\")"));
+        assert!(map.contains("(\"Peer\", \"struct peer\")"));
+
+        // same as to_static_map without conflicts
+        let mappings = NameMappings {
+            types: HashMap::from([(DiscoveredItemId::new(5), mapping("bmp_data", "bmp_data"))]),
+            ..Default::default()
+        };
+        let (map, conflicts) = mappings
+            .to_static_map_checked(&RenameOptions::default())
             .unwrap();
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            map.build().to_string(),
+            mappings.to_static_map(false).unwrap().build().to_string()
+        );
+    }
 
-        let expected =  NameMappings {
+    #[test]
+    fn force_aliases_when() {
+        let mappings = NameMappings {
             types: HashMap::from([
-                (DiscoveredItemId::new(1),
-                 NameMapping {
-                    kind: Struct,
-                    c_name: None,
-                    rust_name: "_bindgen_ty_1".to_string(),
-                    aliases: BTreeSet::default(),
-                }),
-                (DiscoveredItemId::new(10),
-                 NameMapping {
-                    kind: Union,
-                    c_name: None,
-                    rust_name: "_bindgen_ty_2".to_string(),
-                    aliases: BTreeSet::default(),
-                }),
-                (DiscoveredItemId::new(16),
-                 NameMapping {
-                    kind: Union,
-                    c_name: Some(
-                        CName {
-                            identifier: "NamedUnion".to_string(),
-                            aliased: false,
-                        },
-                    ),
-                    rust_name: "NamedUnion".to_string(),
-                    aliases: BTreeSet::from(["AliasOfNamedUnion".to_string()])
-                }),
-                (DiscoveredItemId::new(7),
-                    NameMapping {
-                    kind: Struct,
-                    c_name: Some(
-                        CName {
-                            identifier: "NamedStruct".to_string(),
-                            aliased: false,
-                        },
-                    ),
-                    rust_name: "NamedStruct".to_string(),
-                    aliases: BTreeSet::from(["AliasOfNamedStruct".to_string()])
-                })
+                (
+                    DiscoveredItemId::new(1),
+                    mapping_with_aliases(Struct, "bgp_peer", &["bgp_peer_t", "peer_t"]),
+                ),
+                (
+                    DiscoveredItemId::new(2),
+                    mapping_with_aliases(Union, "bmp_data", &["bmp_data_t"]),
+                ),
             ]),
-            aliases: HashMap::default(),
+            ..Default::default()
         };
-        
-        assert!(expected.eq(&mappings.borrow()));
+
+        let mut codegen = mappings.codegen();
+        codegen
+            .output(CodegenOutput::MatchFn)
+            // overridden by the closure
+            .force_aliases_use(false)
+            .force_aliases_when(|mapping| mapping.kind == Struct);
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.contains("\"bgp_peer\" => Some (\"bgp_peer_t\")"));
+        assert!(code.contains("\"bmp_data\" => Some (\"union bmp_data\")"));
+
+        codegen.alias_selector(AliasSelector::Shortest);
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.contains("\"bgp_peer\" => Some (\"peer_t\")"));
     }
 
     #[test]
-    fn codegen() {
-        let mappings = NameMappings::default();
-        let code = mappings
-            .codegen()
-            .variable_name(Some("super_var"))
-            .generate()
+    fn name_sources() {
+        let mapping = |c_name: Option<&str>, rust_name: &str, aliases: &[&str]| NameMapping {
+            c_name: c_name.map(|name| CName {
+                identifier: name.to_string(),
+                aliased: false,
+            }),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+            alias_order: aliases.iter().map(|alias| alias.to_string()).collect(),
+            ..mapping(Struct, rust_name)
+        };
+        let mappings = NameMappings {
+            types: HashMap::from([
+                // the first source hits
+                (
+                    DiscoveredItemId::new(1),
+                    mapping(Some("bgp_peer"), "bgp_peer", &["peer", "bgp_peer_t"]),
+                ),
+                // the first source misses, the second hits
+                (
+                    DiscoveredItemId::new(2),
+                    mapping(Some("bmp_data"), "bmp_data", &["data"]),
+                ),
+                // the first two miss, the third hits
+                (
+                    DiscoveredItemId::new(3),
+                    mapping(None, "_bindgen_ty_1", &["stats", "bmp_stats"]),
+                ),
+                // none hits
+                (
+                    DiscoveredItemId::new(4),
+                    mapping(None, "_bindgen_ty_2", &[]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let mut codegen = mappings.codegen();
+        codegen
+            .output(CodegenOutput::MatchFn)
+            // overridden by the chain
+            .force_aliases_use(true)
+            .name_sources(&[
+                NameSource::AliasMatching(Regex::new("_t$").unwrap()),
+                NameSource::Original,
+                NameSource::Alias(AliasSelector::Longest),
+            ])
+            .unnamed_types(UnnamedTypePolicy::Synthesize("anon_".to_string()));
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.contains("\"bgp_peer\" => Some (\"bgp_peer_t\")"));
+        assert!(code.contains("\"bmp_data\" => Some (\"struct bmp_data\")"));
+        assert!(code.contains("\"_bindgen_ty_1\" => Some (\"bmp_stats\")"));
+        assert!(code.contains("\"_bindgen_ty_2\" => Some (\"anon_2\")"));
+
+        // an empty chain falls back to force_aliases_use
+        codegen.name_sources(&[]);
+        let code = codegen.generate().unwrap().to_string();
+        assert!(code.contains("\"bgp_peer\" => Some (\"bgp_peer_t\")"));
+        assert!(code.contains("\"bmp_data\" => Some (\"data\")"));
+
+        assert_eq!(
+            NameSource::AliasMatching(Regex::new("_t$").unwrap()),
+            NameSource::AliasMatching(Regex::new("_t$").unwrap())
+        );
+        assert_ne!(
+            NameSource::Original,
+            NameSource::Alias(AliasSelector::First)
+        );
+    }
+
+    #[test]
+    fn synthetic_ids() {
+        let mut first = NameMappings::default();
+        let foo = first.insert(mapping(Struct, "Foo"));
+        first
+            .types
+            .insert(DiscoveredItemId::new(1), mapping(Struct, "Discovered"));
+        let mut second = NameMappings::default();
+        let bar = second.insert(mapping(Struct, "Bar"));
+
+        // both sets minted the same first id
+        assert_eq!(foo, DiscoveredItemId::new(SYNTHETIC_IDS_START));
+        assert_eq!(foo, bar);
+        assert!(first.is_synthetic_id(foo));
+        assert!(!first.is_synthetic_id(DiscoveredItemId::new(1)));
+
+        first.merge(second);
+        assert_eq!(first.types.len(), 3);
+        assert_eq!(first.minted_ids, 2);
+        assert_eq!(first.types[&foo].rust_name, "Foo");
+        assert_eq!(
+            first.types[&DiscoveredItemId::new(SYNTHETIC_IDS_START + 1)].rust_name,
+            "Bar"
+        );
+        assert_eq!(
+            first.types[&DiscoveredItemId::new(1)].rust_name,
+            "Discovered"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_keeps_synthetic_ids() {
+        let mut mappings = NameMappings::default();
+        mappings
+            .types
+            .insert(DiscoveredItemId::new(1), mapping(Struct, "Discovered"));
+        mappings.insert(mapping(Struct, "Foo"));
+        let bar = mappings.insert(mapping(Union, "Bar"));
+        mappings.alias_targets.insert(DiscoveredItemId::new(2), bar);
+        mappings
+            .dependencies
+            .insert(DiscoveredItemId::new(1), BTreeSet::from([bar]));
+
+        let mut loaded = NameMappings::from_json(&mappings.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, mappings);
+        assert_eq!(bar, DiscoveredItemId::new(SYNTHETIC_IDS_START + 1));
+        assert!(loaded.is_synthetic_id(bar));
+        assert_eq!(loaded.types[&bar].rust_name, "Bar");
+
+        // the next minted id follows the loaded ones
+        assert_eq!(
+            loaded.insert(mapping(Struct, "Baz")),
+            DiscoveredItemId::new(SYNTHETIC_IDS_START + 2)
+        );
+        assert!(NameMappings::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn primitive_typedefs() {
+        // typedef uint32_t as_t;
+        // typedef struct Foo {} Foo_t;
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Alias {
+                alias_name: "as_t".to_string(),
+                alias_for: DiscoveredItemId::new(2),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(3),
+            DiscoveredItem::Struct {
+                original_name: Some("Foo".to_string()),
+                final_name: "Foo".to_string(),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(4),
+            DiscoveredItem::Alias {
+                alias_name: "Foo_t".to_string(),
+                alias_for: DiscoveredItemId::new(3),
+            },
+        );
+
+        let mut mappings = mappings.take();
+        // opt-in: the pending typedef is not a type until captured
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(false).unwrap(),
+            "\"Foo\" = \"struct Foo\"\n"
+        );
+
+        assert_eq!(mappings.capture_pending_typedefs(), 1);
+        assert!(!mappings.has_pending_aliases());
+        assert_eq!(
+            mappings.to_cbindgen_toml_renames(false).unwrap(),
+            "\"Foo\" = \"struct Foo\"\n\"as_t\" = \"as_t\"\n"
+        );
+
+        let typedef = mappings
+            .types
+            .values()
+            .find(|mapping| mapping.kind == Typedef)
+            .unwrap();
+        assert_eq!(typedef.rust_name, "as_t");
+        assert_eq!(
+            NameMapping::validated_original_name(typedef.c_name.as_ref(), Typedef),
+            Some("as_t".to_string())
+        );
+
+        // the typedefs of a denied struct, directly or through another typedef, are not captured
+        mappings.denied_types.insert(DiscoveredItemId::new(5));
+        mappings
+            .alias_targets
+            .insert(DiscoveredItemId::new(6), DiscoveredItemId::new(5));
+        mappings.aliases.extend([
+            (DiscoveredItemId::new(5), vec!["bmp_peer_t".to_string()]),
+            (
+                DiscoveredItemId::new(6),
+                vec!["bmp_peer_alias_t".to_string()],
+            ),
+        ]);
+        assert_eq!(mappings.capture_pending_typedefs(), 0);
+        assert!(!mappings.has_pending_aliases());
+    }
+
+    #[test]
+    fn skipped_anonymous_types() {
+        // void function_using_anonymous_struct(struct {} arg0);
+        // struct NamedStruct {};
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: None,
+                final_name: "_bindgen_ty_1".to_string(),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(2),
+            DiscoveredItem::Struct {
+                original_name: Some("NamedStruct".to_string()),
+                final_name: "NamedStruct".to_string(),
+            },
+        );
+
+        let mappings = mappings.take();
+        let (map, skipped) = mappings
+            .to_static_map_reporting(&RenameOptions::default())
             .unwrap();
+        assert_eq!(skipped, vec![DiscoveredItemId::new(1)]);
 
+        let map = map.build().to_string();
+        assert!(map.contains("(\"NamedStruct\", \"struct NamedStruct\")"));
+        assert!(!map.contains("_bindgen_ty_1"));
+
+        // synthesized names are not skipped
+        let (_, skipped) = mappings
+            .to_static_map_reporting(&RenameOptions {
+                unnamed_types: UnnamedTypePolicy::Synthesize("anonymous_".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn dangling_aliases_are_pending() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        callback.new_item_found(
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("Foo".to_string()),
+                final_name: "Foo".to_string(),
+            },
+        );
+        callback.new_item_found(
+            DiscoveredItemId::new(2),
+            DiscoveredItem::Alias {
+                alias_name: "Foo_t".to_string(),
+                alias_for: DiscoveredItemId::new(1),
+            },
+        );
+        // typedef of a type that is never discovered
+        callback.new_item_found(
+            DiscoveredItemId::new(3),
+            DiscoveredItem::Alias {
+                alias_name: "blocked_t".to_string(),
+                alias_for: DiscoveredItemId::new(42),
+            },
+        );
+
+        let mut mappings = mappings.take();
+        assert!(mappings.has_pending_aliases());
         assert_eq!(
-            code.to_string(),
-            "pub static super_var : & 'static str = \"\" ;"
-        )
+            mappings.pending_aliases(),
+            &HashMap::from([(DiscoveredItemId::new(42), vec!["blocked_t".to_string()])])
+        );
+
+        assert_eq!(mappings.forget_unused_aliases(), 1);
+        assert!(!mappings.has_pending_aliases());
+        assert!(!NameMappings::default().has_pending_aliases());
+    }
+
+    #[test]
+    fn first_found_alias_names_anonymous_type() {
+        // typedef struct { .. } zeta_t;
+        // typedef zeta_t alpha_t;
+        // both typedefs are found before the anonymous struct
+        let items = [
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Alias {
+                    alias_name: "zeta_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(3),
+                DiscoveredItem::Alias {
+                    alias_name: "alpha_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: None,
+                    final_name: "_bindgen_ty_1".to_string(),
+                },
+            ),
+        ];
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in items {
+            callback.new_item_found(id, item);
+        }
+
+        let mappings = mappings.take();
+        let mapping = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(
+            mapping.c_name,
+            Some(CName {
+                identifier: "zeta_t".to_string(),
+                aliased: true,
+            })
+        );
+        assert_eq!(mapping.aliases, BTreeSet::from(["alpha_t".to_string()]));
+        assert!(mappings.aliases.is_empty());
+    }
+
+    #[test]
+    fn resolve_anonymous_names() {
+        // typedef struct { .. } zeta_t;
+        // typedef zeta_t alpha_t;
+        // typedef struct { .. } unnamed;
+        let items = [
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: None,
+                    final_name: "_bindgen_ty_1".to_string(),
+                },
+            ),
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Alias {
+                    alias_name: "zeta_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(3),
+                DiscoveredItem::Alias {
+                    alias_name: "alpha_t".to_string(),
+                    alias_for: DiscoveredItemId::new(1),
+                },
+            ),
+            (
+                DiscoveredItemId::new(4),
+                DiscoveredItem::Struct {
+                    original_name: None,
+                    final_name: "_bindgen_ty_2".to_string(),
+                },
+            ),
+        ];
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in items {
+            callback.new_item_found(id, item);
+        }
+
+        // as loaded without its name, with all its aliases
+        let mut mappings = mappings.take();
+        let anonymous = mappings.types.get_mut(&DiscoveredItemId::new(1)).unwrap();
+        let c_name = anonymous.c_name.take().unwrap();
+        anonymous.aliases.insert(c_name.identifier.clone());
+        anonymous.alias_order.insert(0, c_name.identifier);
+        let loaded = mappings.clone();
+
+        assert_eq!(
+            mappings.resolve_anonymous_names(&AliasSelector::FirstDeclared),
+            1
+        );
+        let resolved = &mappings.types[&DiscoveredItemId::new(1)];
+        assert_eq!(
+            resolved.c_name,
+            Some(CName {
+                identifier: "zeta_t".to_string(),
+                aliased: true,
+            })
+        );
+        assert_eq!(resolved.aliases, BTreeSet::from(["alpha_t".to_string()]));
+        assert_eq!(resolved.alias_order, ["alpha_t"]);
+        assert_eq!(mappings.types[&DiscoveredItemId::new(4)].c_name, None);
+
+        // the named types are left alone
+        assert_eq!(mappings.resolve_anonymous_names(&AliasSelector::First), 0);
+
+        let mut mappings = loaded;
+        mappings.resolve_anonymous_names(&AliasSelector::First);
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)]
+                .c_name
+                .as_ref()
+                .unwrap()
+                .identifier,
+            "alpha_t"
+        );
+    }
+
+    #[test]
+    fn monomorphizations() {
+        // namespace geo { struct Point {}; template <typename T> struct Box { T item; }; }
+        // typedef geo::Box<geo::Point> PointBox;
+        let items = [
+            (
+                DiscoveredItemId::new(3),
+                DiscoveredItem::Alias {
+                    alias_name: "PointBox".to_string(),
+                    alias_for: DiscoveredItemId::new(2),
+                },
+            ),
+            (
+                DiscoveredItemId::new(1),
+                DiscoveredItem::Struct {
+                    original_name: Some("geo::Point".to_string()),
+                    final_name: "geo_Point".to_string(),
+                },
+            ),
+            (
+                DiscoveredItemId::new(2),
+                DiscoveredItem::Struct {
+                    original_name: Some("geo::Box<geo::Point>".to_string()),
+                    final_name: "geo_Box_geo_Point".to_string(),
+                },
+            ),
+        ];
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in items {
+            callback.new_item_found(id, item);
+        }
+        let mappings = mappings.take();
+
+        let point = &mappings.types[&DiscoveredItemId::new(1)];
+        assert!(!point.is_monomorphization());
+        let instantiation = &mappings.types[&DiscoveredItemId::new(2)];
+        assert!(instantiation.is_monomorphization());
+        assert_eq!(instantiation.generic_base.as_deref(), Some("Box"));
+        // the namespaces of the template arguments stay in the name
+        assert_eq!(instantiation.namespace, ["geo"]);
+        assert_eq!(
+            instantiation.c_name.as_ref().unwrap().identifier,
+            "Box<geo::Point>"
+        );
+
+        // left out by default
+        let toml = mappings.clone().codegen().generate_all().unwrap().toml;
+        assert!(toml.contains("\"geo_Point\""));
+        assert!(!toml.contains("geo_Box_geo_Point"));
+
+        let toml = mappings
+            .codegen()
+            .keep_monomorphizations(true)
+            .force_aliases_use(true)
+            .generate_all()
+            .unwrap()
+            .toml;
+        assert!(toml.contains("\"geo_Box_geo_Point\" = \"PointBox\"\n"));
+    }
+
+    #[test]
+    fn tagged_self_aliases() {
+        // aliases naming the struct itself with a tag, even a stale one, are not kept
+        let aliases = [
+            "struct Foo",
+            "union Foo",
+            "enum  Foo",
+            "Foo",
+            "struct Foo_t",
+            "Foo_t",
+        ];
+        let mut items: Vec<(DiscoveredItemId, DiscoveredItem)> = aliases
+            .into_iter()
+            .enumerate()
+            .map(|(index, alias_name)| {
+                (
+                    DiscoveredItemId::new(index + 2),
+                    DiscoveredItem::Alias {
+                        alias_name: alias_name.to_string(),
+                        alias_for: DiscoveredItemId::new(1),
+                    },
+                )
+            })
+            .collect();
+        items.push((
+            DiscoveredItemId::new(1),
+            DiscoveredItem::Struct {
+                original_name: Some("Foo".to_string()),
+                final_name: "Foo".to_string(),
+            },
+        ));
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in items {
+            callback.new_item_found(id, item);
+        }
+
+        let mappings = mappings.take();
+        assert_eq!(
+            mappings.types[&DiscoveredItemId::new(1)].aliases,
+            BTreeSet::from([
+                "Foo".to_string(),
+                "Foo_t".to_string(),
+                "struct Foo_t".to_string()
+            ])
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn jsonl_round_trip() {
+        use super::StreamingNameMappingsCallback;
+
+        let items = || {
+            [
+                // typedef struct bgp_peer bgp_peer_t; before the struct
+                (
+                    DiscoveredItemId::new(20),
+                    DiscoveredItem::Alias {
+                        alias_name: "bgp_peer_t".to_string(),
+                        alias_for: DiscoveredItemId::new(10),
+                    },
+                ),
+                (
+                    DiscoveredItemId::new(10),
+                    DiscoveredItem::Struct {
+                        original_name: Some("bgp_peer".to_string()),
+                        final_name: "bgp_peer".to_string(),
+                    },
+                ),
+                (
+                    DiscoveredItemId::new(30),
+                    DiscoveredItem::Union {
+                        original_name: None,
+                        final_name: "_bindgen_ty_1".to_string(),
+                    },
+                ),
+                (
+                    DiscoveredItemId::new(40),
+                    DiscoveredItem::Alias {
+                        alias_name: "bmp_\"data\"\t\u{e9}\\".to_string(),
+                        alias_for: DiscoveredItemId::new(30),
+                    },
+                ),
+            ]
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("bindgen-bridge-{}.jsonl", std::process::id()));
+        let callback = StreamingNameMappingsCallback::create(&path).unwrap();
+        let bindgen_callback = callback.clone();
+        for (id, item) in items() {
+            bindgen_callback.new_item_found(id, item);
+        }
+        drop(bindgen_callback);
+        callback.finish().unwrap();
+        let loaded = NameMappings::from_jsonl_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        let callback = NameMappingsCallback(Rc::clone(&mappings));
+        for (id, item) in items() {
+            callback.new_item_found(id, item);
+        }
+
+        // same types, under the numbers of the file
+        let sorted = |mappings: &NameMappings| {
+            let mut types: Vec<NameMapping> = mappings.types.values().cloned().collect();
+            types.sort_by(|a, b| a.rust_name.cmp(&b.rust_name));
+            types
+        };
+        assert_eq!(sorted(&loaded), sorted(&mappings.borrow()));
+        assert_eq!(
+            loaded.types[&DiscoveredItemId::new(1)].rust_name,
+            "bgp_peer"
+        );
+        assert_eq!(
+            loaded.types[&DiscoveredItemId::new(2)].c_name,
+            Some(CName {
+                identifier: "bmp_\"data\"\t\u{e9}\\".to_string(),
+                aliased: true,
+            })
+        );
+
+        let invalid = "{\"item\":\"alias\",\"id\":0,\"alias_name\":\"a\",\"alias_for\":1}\n\
+                       {\"item\":\"struct\",\"id\":\"1\",\"original_name\":null,\"final_name\":\"b\"}\n";
+        assert!(matches!(
+            NameMappings::from_jsonl(invalid.as_bytes()),
+            Err(Error::Jsonl { line: 2, .. })
+        ));
+    }
+
+    #[cfg(all(feature = "json", target_os = "linux"))]
+    #[test]
+    fn jsonl_write_error() {
+        use super::StreamingNameMappingsCallback;
+
+        // every write to /dev/full fails with "no space left on device"
+        let callback = StreamingNameMappingsCallback::create("/dev/full").unwrap();
+        callback.new_item_found(
+            DiscoveredItemId::new(0),
+            DiscoveredItem::Struct {
+                original_name: Some("point".to_string()),
+                final_name: "point".to_string(),
+            },
+        );
+        assert!(matches!(callback.finish(), Err(Error::Io(_))));
     }
 }
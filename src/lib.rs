@@ -7,5 +7,22 @@ pub mod import;
 /// correct [export.rename] section
 pub mod export;
 
+/// The module formatting the generated text when it is written to files
+pub mod output;
+
+/// Helpers for the tests of the crates using the generated mappings
+/// Available with the `testing` feature
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+/// The errors of the crate
+mod error;
+
+pub use error::Error;
+
 /// Common Result Wrapper
-pub type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>;
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Type-erased Result Wrapper, the previous [Result]
+/// Kept during the transition to the typed [Error], which converts into it with `?`
+pub type BoxedResult<T> = core::result::Result<T, Box<dyn std::error::Error>>;
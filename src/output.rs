@@ -0,0 +1,217 @@
+use crate::{Error, Result};
+
+/// Line terminator of the written files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+
+    /// `\r\n`, e.g.: for Windows users
+    Crlf,
+}
+
+impl LineEnding {
+    /// The line terminator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// How the generated text is written to files
+/// see [crate::export::Template::write_to] and [crate::import::MappingsCodegen::write_to]
+///
+/// default: [LineEnding::Lf] with a single trailing newline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputFormat {
+    /// Line terminator of every line
+    pub line_ending: LineEnding,
+
+    /// Should the text end with exactly one line terminator, if `false` it is left as generated
+    pub trailing_newline: bool,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl OutputFormat {
+    /// The text with its line terminators replaced by the [OutputFormat::line_ending]
+    /// and its trailing newline normalized if needed
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.replace("\r\n", "\n");
+        if self.trailing_newline {
+            text.truncate(text.trim_end_matches('\n').len());
+            text.push('\n');
+        }
+
+        match self.line_ending {
+            LineEnding::Lf => text,
+            LineEnding::Crlf => text.replace('\n', LineEnding::Crlf.as_str()),
+        }
+    }
+}
+
+/// Line opening the generated region of a hand-maintained file, see [replace_region]
+pub const REGION_BEGIN: &str = "// BINDGEN-BRIDGE BEGIN";
+
+/// Line closing the generated region of a hand-maintained file, see [replace_region]
+pub const REGION_END: &str = "// BINDGEN-BRIDGE END";
+
+/// Where the generated region is inserted in a file without its markers yet, see [replace_region]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RegionPlacement {
+    /// Before the first line
+    Start,
+
+    /// After the last line
+    #[default]
+    End,
+
+    /// After the first line containing the given text, e.g.: `"mod ffi {"`
+    After(String),
+}
+
+/// The `text` with the lines between [REGION_BEGIN] and [REGION_END] replaced by the `generated` ones
+///
+/// The markers are matched as whole lines, indentation aside, and are kept along with the rest of the text.
+/// Without markers, they are inserted around the `generated` lines according to the `placement`.
+/// Fails with [Error::Region] if only one of the markers is found or if the placement can't be found
+pub fn replace_region(text: &str, generated: &str, placement: &RegionPlacement) -> Result<String> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let is_marker = |line: &str, marker: &str| line.trim() == marker;
+    let begin = lines.iter().position(|line| is_marker(line, REGION_BEGIN));
+    let end = lines.iter().position(|line| is_marker(line, REGION_END));
+
+    let mut region = generated.trim_end_matches('\n').to_string();
+    if !region.is_empty() {
+        region.push('\n');
+    }
+
+    let (before, after) = match (begin, end) {
+        (Some(begin), Some(end)) if begin < end => {
+            return Ok(format!(
+                "{}{region}{}",
+                lines[..=begin].concat(),
+                lines[end..].concat()
+            ));
+        }
+        (None, None) => match placement {
+            RegionPlacement::Start => (0, 0),
+            RegionPlacement::End => (lines.len(), lines.len()),
+            RegionPlacement::After(anchor) => {
+                let line = lines
+                    .iter()
+                    .position(|line| line.contains(anchor.as_str()))
+                    .ok_or_else(|| Error::Region(format!("no line contains {:?}", anchor)))?;
+                (line + 1, line + 1)
+            }
+        },
+        _ => {
+            return Err(Error::Region(format!(
+                "{:?} must be followed by {:?}",
+                REGION_BEGIN, REGION_END
+            )))
+        }
+    };
+
+    let mut result = lines[..before].concat();
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result.push_str(&format!("{REGION_BEGIN}\n{region}{REGION_END}\n"));
+    result.push_str(&lines[after..].concat());
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::{replace_region, LineEnding, OutputFormat, RegionPlacement};
+    use crate::Error;
+
+    #[test]
+    fn line_endings() {
+        let text = "a = 1\r\nb = 2\n\n\n";
+
+        assert_eq!(
+            OutputFormat::default().apply(text).as_bytes(),
+            b"a = 1\nb = 2\n"
+        );
+        assert_eq!(
+            OutputFormat {
+                line_ending: LineEnding::Crlf,
+                trailing_newline: true,
+            }
+            .apply(text)
+            .as_bytes(),
+            b"a = 1\r\nb = 2\r\n"
+        );
+        assert_eq!(
+            OutputFormat {
+                line_ending: LineEnding::Crlf,
+                trailing_newline: false,
+            }
+            .apply(text)
+            .as_bytes(),
+            b"a = 1\r\nb = 2\r\n\r\n\r\n"
+        );
+        assert_eq!(OutputFormat::default().apply("code").as_bytes(), b"code\n");
+    }
+
+    #[test]
+    fn regions() {
+        let text = "mod ffi {\n    fn hand_written() {}\n}\n";
+
+        let inserted = replace_region(text, "static MAP: u8 = 0;", &RegionPlacement::End).unwrap();
+        assert_eq!(
+            inserted,
+            "mod ffi {\n    fn hand_written() {}\n}\n\
+             // BINDGEN-BRIDGE BEGIN\nstatic MAP: u8 = 0;\n// BINDGEN-BRIDGE END\n"
+        );
+
+        // regenerating replaces the region only, and twice gives the same text
+        let regenerated =
+            replace_region(&inserted, "static MAP: u8 = 1;\n", &RegionPlacement::Start).unwrap();
+        assert_eq!(regenerated, inserted.replace("= 0", "= 1"));
+        assert_eq!(
+            replace_region(&regenerated, "static MAP: u8 = 1;", &RegionPlacement::End).unwrap(),
+            regenerated
+        );
+
+        let placement = RegionPlacement::After("mod ffi {".to_string());
+        assert_eq!(
+            replace_region(text, "x", &placement).unwrap(),
+            "mod ffi {\n// BINDGEN-BRIDGE BEGIN\nx\n// BINDGEN-BRIDGE END\n    fn hand_written() {}\n}\n"
+        );
+        assert_eq!(
+            replace_region("", "x", &RegionPlacement::Start).unwrap(),
+            "// BINDGEN-BRIDGE BEGIN\nx\n// BINDGEN-BRIDGE END\n"
+        );
+
+        // indented markers
+        let indented =
+            "mod ffi {\n    // BINDGEN-BRIDGE BEGIN\n    old\n    // BINDGEN-BRIDGE END\n}\n";
+        assert_eq!(
+            replace_region(indented, "new", &RegionPlacement::End).unwrap(),
+            "mod ffi {\n    // BINDGEN-BRIDGE BEGIN\nnew\n    // BINDGEN-BRIDGE END\n}\n"
+        );
+
+        assert!(matches!(
+            replace_region("// BINDGEN-BRIDGE BEGIN\n", "x", &RegionPlacement::End),
+            Err(Error::Region(_))
+        ));
+        assert!(matches!(
+            replace_region(text, "x", &RegionPlacement::After("mod other".to_string())),
+            Err(Error::Region(_))
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+use crate::import::{CName, CompositeKind, NameMapping};
+use crate::import::{NameMappings, RenameOptions};
+
+/// A type of the given `kind` named `name` in both C and Rust, without aliases nor anything else,
+/// to build the fixtures of the tests with the struct update syntax
+/// e.g.: `NameMapping { order: 2, ..mapping(Struct, "bmp_peer") }`
+#[cfg(test)]
+pub(crate) fn mapping(kind: CompositeKind, name: &str) -> NameMapping {
+    NameMapping {
+        kind,
+        c_name: Some(CName {
+            identifier: name.to_string(),
+            aliased: false,
+        }),
+        rust_name: name.to_string(),
+        aliases: BTreeSet::new(),
+        alias_order: Vec::new(),
+        order: 0,
+        namespace: Vec::new(),
+        doc: None,
+        source: None,
+        generic_base: None,
+    }
+}
+
+/// Same as [mapping], with the given aliases declared in this order, see [NameMapping::alias_order]
+#[cfg(test)]
+pub(crate) fn mapping_with_aliases(
+    kind: CompositeKind,
+    name: &str,
+    aliases: &[&str],
+) -> NameMapping {
+    NameMapping {
+        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        alias_order: aliases.iter().map(|alias| alias.to_string()).collect(),
+        ..mapping(kind, name)
+    }
+}
+
+/// Asserts that the rename rules of the `mappings` are exactly the `expected` `(rust, c)` pairs,
+/// e.g.: `("NamedStruct", "struct NamedStruct")`, whatever the ids of the types and the order of the rules
+///
+/// The rules are the ones of the default [RenameOptions], see [assert_renames_with].
+/// Panics listing the missing and unexpected pairs
+#[track_caller]
+pub fn assert_renames(mappings: &NameMappings, expected: &[(&str, &str)]) {
+    assert_renames_with(mappings, &RenameOptions::default(), expected)
+}
+
+/// Same as [assert_renames], with the rules generated using the given `options`
+#[track_caller]
+pub fn assert_renames_with(
+    mappings: &NameMappings,
+    options: &RenameOptions,
+    expected: &[(&str, &str)],
+) {
+    let rules = match mappings.rename_rules(options) {
+        Ok(rules) => rules,
+        Err(e) => panic!("could not generate the rename rules: {e}"),
+    };
+    let actual: BTreeSet<(&str, &str)> = rules
+        .iter()
+        .map(|rule| (rule.key.as_str(), rule.c_name.as_str()))
+        .collect();
+    let expected: BTreeSet<(&str, &str)> = expected.iter().copied().collect();
+
+    let missing: Vec<_> = expected.difference(&actual).collect();
+    let unexpected: Vec<_> = actual.difference(&expected).collect();
+    if !missing.is_empty() || !unexpected.is_empty() {
+        panic!("rename rules differ\n  missing: {missing:?}\n  unexpected: {unexpected:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use bindgen::callbacks::DiscoveredItemId;
+    use bindgen::Builder;
+
+    use crate::import::CompositeKind::{Struct, Union};
+    use crate::import::{NameMappings, NameMappingsCallback, RenameOptions};
+    use crate::testing::{assert_renames, assert_renames_with, mapping_with_aliases};
+
+    #[test]
+    fn sample_header_renames() {
+        let mappings = Rc::new(RefCell::new(NameMappings::default()));
+        Builder::default()
+            .header_contents(
+                "sample_header.h",
+                "
+                void function_using_anonymous_struct(struct {} arg0);
+                struct NamedStruct {};
+                typedef struct NamedStruct AliasOfNamedStruct;
+
+                void function_using_anonymous_union(union {} arg0);
+                union NamedUnion {};
+                typedef union NamedUnion AliasOfNamedUnion;
+                ",
+            )
+            .parse_callbacks(Box::new(NameMappingsCallback(Rc::clone(&mappings))))
+            .generate()
+            .unwrap();
+
+        assert_renames(
+            &mappings.borrow(),
+            &[
+                ("NamedUnion", "union NamedUnion"),
+                ("NamedStruct", "struct NamedStruct"),
+            ],
+        );
+    }
+
+    fn mappings() -> NameMappings {
+        NameMappings {
+            types: HashMap::from([
+                (
+                    DiscoveredItemId::new(3),
+                    mapping_with_aliases(Struct, "bmp_peer", &["bmp_peer_t"]),
+                ),
+                (
+                    DiscoveredItemId::new(5),
+                    mapping_with_aliases(Union, "bgp_attr", &["bgp_attr_t"]),
+                ),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renames() {
+        let mappings = mappings();
+        assert_renames(
+            &mappings,
+            &[
+                ("bgp_attr", "union bgp_attr"),
+                ("bmp_peer", "struct bmp_peer"),
+            ],
+        );
+
+        let options = RenameOptions {
+            force_aliases_use: true,
+            ..Default::default()
+        };
+        assert_renames_with(
+            &mappings,
+            &options,
+            &[("bmp_peer", "bmp_peer_t"), ("bgp_attr", "bgp_attr_t")],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected: [(\"bmp_peer\", \"struct bmp_peer\")]")]
+    fn missing_renames() {
+        assert_renames(&mappings(), &[("bgp_attr", "union bgp_attr")]);
+    }
+}